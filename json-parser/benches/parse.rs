@@ -0,0 +1,60 @@
+//! Compares the char by char lexer with the stage 1 structural index.
+//!
+//! ```sh
+//! cargo bench
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use json_parser::stage1::{self, Kernel};
+
+/// A few megabytes of records mixing every kind of value.
+fn document() -> String {
+    let records = (0..20_000)
+        .map(|i| {
+            format!(
+                r#"{{"id": {i}, "name": "user \"{i}\"", "score": {}.25, "active": {}, "tags": ["a", "b\\n", null], "address": {{"city": "Café", "zip": "{:05}"}}}}"#,
+                i % 100,
+                i % 2 == 0,
+                i * 7 % 100_000
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[\n  {}\n]\n", records.join(",\n  "))
+}
+
+fn bench_index(c: &mut Criterion) {
+    let input = document();
+    let mut group = c.benchmark_group("index");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    group.bench_function("lexer", |b| b.iter(|| json_parser::lex(&input).unwrap()));
+    for kernel in [Kernel::Scalar, Kernel::Sse2, Kernel::Avx2] {
+        if kernel.is_supported() {
+            group.bench_with_input(
+                BenchmarkId::new("stage1", format!("{kernel:?}")),
+                &input,
+                |b, input| b.iter(|| stage1::index(input.as_bytes(), kernel).unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let input = document();
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    // `parse_with_spans` always goes through the lexer.
+    group.bench_function("lexer", |b| {
+        b.iter(|| json_parser::parse_with_spans(&input).unwrap())
+    });
+    group.bench_function("stage1", |b| b.iter(|| json_parser::parse(&input).unwrap()));
+    group.bench_function("stage1/borrowed", |b| {
+        b.iter(|| json_parser::parse_borrowed(&input).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_index, bench_parse);
+criterion_main!(benches);
@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Anything that parses must serialize back into a document that parses to the same value.
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(value) = json_parser::parse(input) else {
+        return;
+    };
+
+    let compact = json_parser::parse(&value.to_string()).expect("compact output parses");
+    assert_eq!(value, compact);
+
+    let pretty = json_parser::parse(&value.to_pretty_string(2)).expect("pretty output parses");
+    assert_eq!(value, pretty);
+});
@@ -0,0 +1,431 @@
+//! CBOR, RFC 8949.
+
+use super::{base64url, float, key, Reader};
+use crate::{
+    convert::check_unique,
+    path::Path,
+    value::{Number, Value},
+};
+use anyhow::Result;
+
+const FORMAT: &str = "CBOR";
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+/// Additional info marking an indefinite length item, or the break ending one.
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = 0xFF;
+
+/// Encode `value`, with the shortest head for every length and integer.
+pub fn encode(value: &Value) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    write(value, &Path::root(), &mut out)?;
+    Ok(out)
+}
+
+/// Decode a single CBOR data item.
+pub fn decode(input: &[u8]) -> Result<Value> {
+    let mut reader = Reader::new(input, FORMAT);
+    let value = read(&mut reader, &Path::root())?;
+    reader.finish()?;
+    Ok(value)
+}
+
+fn write(value: &Value, path: &Path, out: &mut Vec<u8>) -> Result<()> {
+    match value {
+        Value::Null => out.push(0xF6),
+        Value::Bool(false) => out.push(0xF4),
+        Value::Bool(true) => out.push(0xF5),
+        Value::Number(Number::Integer(n)) if *n >= 0 => head(UNSIGNED, n.unsigned_abs(), out),
+        // -1 - n, computed without overflowing on `i64::MIN`.
+        Value::Number(Number::Integer(n)) => head(NEGATIVE, !*n as u64, out),
+        Value::Number(Number::Float(n)) => write_float(*n, out),
+        Value::String(s) => {
+            head(TEXT, s.len() as u64, out);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            head(ARRAY, items.len() as u64, out);
+            for (i, item) in items.iter().enumerate() {
+                write(item, &path.index(i), out)?;
+            }
+        }
+        Value::Object(members) => {
+            check_unique(members, path, FORMAT)?;
+            head(MAP, members.len() as u64, out);
+            for (key, value) in members {
+                head(TEXT, key.len() as u64, out);
+                out.extend_from_slice(key.as_bytes());
+                write(value, &path.key(key), out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The initial byte of an item and its argument, in as few bytes as possible.
+fn head(major: u8, argument: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    if let Ok(n @ 0..=23) = u8::try_from(argument) {
+        out.push(major | n);
+    } else if let Ok(n) = u8::try_from(argument) {
+        out.extend([major | 24, n]);
+    } else if let Ok(n) = u16::try_from(argument) {
+        out.push(major | 25);
+        out.extend(n.to_be_bytes());
+    } else if let Ok(n) = u32::try_from(argument) {
+        out.push(major | 26);
+        out.extend(n.to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend(argument.to_be_bytes());
+    }
+}
+
+/// Half, single or double precision, whichever is the narrowest to hold `n` exactly.
+fn write_float(n: f64, out: &mut Vec<u8>) {
+    let single = n as f32;
+    if f64::from(single) != n && !n.is_nan() {
+        out.push(0xFB);
+        out.extend(n.to_be_bytes());
+    } else if let Some(half) = to_half(single) {
+        out.push(0xF9);
+        out.extend(half.to_be_bytes());
+    } else {
+        out.push(0xFA);
+        out.extend(single.to_be_bytes());
+    }
+}
+
+/// `n` as an IEEE 754 half, `None` if it would lose precision.
+fn to_half(n: f32) -> Option<u16> {
+    let bits = n.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+
+    match exponent {
+        0xFF if mantissa == 0 => Some(sign | 0x7C00),
+        0xFF => Some(sign | 0x7E00),
+        0 if mantissa == 0 => Some(sign),
+        _ => {
+            let half_exponent = exponent - 127 + 15;
+            if (1..=30).contains(&half_exponent) {
+                // Normal halves keep 10 of the 23 mantissa bits.
+                (mantissa & 0x1FFF == 0)
+                    .then_some(sign | (half_exponent as u16) << 10 | (mantissa >> 13) as u16)
+            } else {
+                // Subnormal halves are a multiple of 2^-24.
+                let full = mantissa | 0x80_0000;
+                let shift = u32::try_from(126 - exponent).ok().filter(|s| *s < 32)?;
+                (half_exponent <= 0 && full & ((1 << shift) - 1) == 0)
+                    .then(|| sign | (full >> shift) as u16)
+            }
+        }
+    }
+}
+
+/// RFC 8949 appendix D.
+fn from_half(half: u16) -> f64 {
+    let exponent = i32::from((half >> 10) & 0x1F);
+    let mantissa = f64::from(half & 0x3FF);
+    let value = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent - 25),
+    };
+    match half & 0x8000 {
+        0 => value,
+        _ => -value,
+    }
+}
+
+fn read(reader: &mut Reader, path: &Path) -> Result<Value> {
+    let initial = reader.u8()?;
+    let (major, info) = (initial >> 5, initial & 0x1F);
+
+    if major == SIMPLE {
+        return Ok(match info {
+            20 => Value::Bool(false),
+            21 => Value::Bool(true),
+            // `null`, `undefined` and unassigned simple values.
+            0..=19 | 22 | 23 => Value::Null,
+            24 => {
+                reader.u8()?;
+                Value::Null
+            }
+            25 => float(from_half(u16::from_be_bytes(reader.array()?)), path)?,
+            26 => float(f64::from(f32::from_be_bytes(reader.array()?)), path)?,
+            27 => float(f64::from_be_bytes(reader.array()?), path)?,
+            INDEFINITE => Err(reader.invalid("unexpected break"))?,
+            _ => Err(reader.invalid(format!("reserved simple value {info}")))?,
+        });
+    }
+
+    if info == INDEFINITE {
+        return read_indefinite(reader, major, path);
+    }
+    let argument = argument(reader, info)?;
+
+    Ok(match major {
+        UNSIGNED => Value::from(argument),
+        NEGATIVE => match i64::try_from(argument) {
+            Ok(n) => Value::from(-1 - n),
+            Err(_) => Value::Number(Number::Float(-1.0 - argument as f64)),
+        },
+        BYTES => Value::String(base64url(reader.take(length(reader, argument)?)?)),
+        TEXT => Value::String(reader.text(length(reader, argument)?)?),
+        ARRAY => {
+            reader.nest()?;
+            let len = length(reader, argument)?;
+            let mut items = Vec::with_capacity(len);
+            for i in 0..len {
+                items.push(read(reader, &path.index(i))?);
+            }
+            reader.depth -= 1;
+            Value::Array(items)
+        }
+        MAP => {
+            reader.nest()?;
+            let len = length(reader, argument)?;
+            let mut members = Vec::with_capacity(len);
+            for _ in 0..len {
+                members.push(read_member(reader, path)?);
+            }
+            reader.depth -= 1;
+            Value::Object(members)
+        }
+        TAG => {
+            // Tags can wrap tags, they count towards the nesting too.
+            reader.nest()?;
+            let value = match argument {
+                2 | 3 => bignum(reader, argument == 3, path)?,
+                _ => read(reader, path)?,
+            };
+            reader.depth -= 1;
+            value
+        }
+        _ => Err(reader.invalid(format!("major type {major}")))?,
+    })
+}
+
+/// Strings made of chunks and arrays and maps ended by a break.
+fn read_indefinite(reader: &mut Reader, major: u8, path: &Path) -> Result<Value> {
+    let at_break = |reader: &mut Reader| {
+        let done = reader.peek() == Some(BREAK);
+        if done {
+            reader.offset += 1;
+        }
+        done
+    };
+
+    Ok(match major {
+        BYTES | TEXT => {
+            let mut bytes = Vec::new();
+            while !at_break(reader) {
+                let initial = reader.u8()?;
+                let info = initial & 0x1F;
+                if initial >> 5 != major || info == INDEFINITE {
+                    Err(reader.invalid("a chunk of another type in an indefinite string"))?
+                }
+                let len = argument(reader, info)?;
+                bytes.extend_from_slice(reader.take(length(reader, len)?)?);
+            }
+            match major {
+                BYTES => Value::String(base64url(&bytes)),
+                _ => Value::String(
+                    String::from_utf8(bytes)
+                        .map_err(|_| reader.invalid("invalid UTF-8 in a string"))?,
+                ),
+            }
+        }
+        ARRAY => {
+            reader.nest()?;
+            let mut items = Vec::new();
+            while !at_break(reader) {
+                items.push(read(reader, &path.index(items.len()))?);
+            }
+            reader.depth -= 1;
+            Value::Array(items)
+        }
+        MAP => {
+            reader.nest()?;
+            let mut members = Vec::new();
+            while !at_break(reader) {
+                members.push(read_member(reader, path)?);
+            }
+            reader.depth -= 1;
+            Value::Object(members)
+        }
+        _ => Err(reader.invalid(format!("indefinite length for major type {major}")))?,
+    })
+}
+
+fn read_member(reader: &mut Reader, path: &Path) -> Result<(String, Value)> {
+    let key = key(read(reader, path)?, path)?;
+    let value = read(reader, &path.key(&key))?;
+    Ok((key, value))
+}
+
+/// The argument following the initial byte.
+fn argument(reader: &mut Reader, info: u8) -> Result<u64> {
+    Ok(match info {
+        0..=23 => u64::from(info),
+        24 => u64::from(reader.u8()?),
+        25 => u64::from(u16::from_be_bytes(reader.array()?)),
+        26 => u64::from(u32::from_be_bytes(reader.array()?)),
+        27 => u64::from_be_bytes(reader.array()?),
+        _ => Err(reader.invalid(format!("reserved additional information {info}")))?,
+    })
+}
+
+/// A length that can't run past the end of the input, every item takes at least a byte.
+fn length(reader: &Reader, argument: u64) -> Result<usize> {
+    match usize::try_from(argument) {
+        Ok(len) if len <= reader.remaining() => Ok(len),
+        _ => Err(reader.invalid(format!("length {argument} runs past the end")))?,
+    }
+}
+
+/// Tags 2 and 3, a big-endian magnitude `n` standing for `n` or `-1 - n`.
+fn bignum(reader: &mut Reader, negative: bool, path: &Path) -> Result<Value> {
+    let initial = reader.u8()?;
+    if initial >> 5 != BYTES || initial & 0x1F == INDEFINITE {
+        Err(reader.invalid("a bignum that is not a byte string"))?
+    }
+    let len = argument(reader, initial & 0x1F)?;
+    let bytes = reader.take(length(reader, len)?)?;
+
+    let magnitude = bytes
+        .iter()
+        .try_fold(0u64, |n, &b| n.checked_mul(256)?.checked_add(u64::from(b)));
+    Ok(match (magnitude, negative) {
+        (Some(n), false) => Value::from(n),
+        (Some(n), true) if i64::try_from(n).is_ok() => Value::from(-1 - n as i64),
+        _ => {
+            let n = bytes.iter().fold(0.0, |n, &b| n * 256.0 + f64::from(b));
+            float(if negative { -1.0 - n } else { n }, path)?
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_encode() {
+        // Examples from RFC 8949 appendix A.
+        let cases = [
+            (json!(0), "00"),
+            (json!(23), "17"),
+            (json!(24), "1818"),
+            (json!(1000), "1903e8"),
+            (json!(1000000), "1a000f4240"),
+            (json!(1000000000000_i64), "1b000000e8d4a51000"),
+            (json!(-1), "20"),
+            (json!(-1000), "3903e7"),
+            (json!(i64::MIN), "3b7fffffffffffffff"),
+            (json!(0.0), "f90000"),
+            (json!(-0.0), "f98000"),
+            (json!(1.5), "f93e00"),
+            (json!(65504.0), "f97bff"),
+            (json!(100000.0), "fa47c35000"),
+            (json!(5.960464477539063e-8), "f90001"),
+            (json!(1.1), "fb3ff199999999999a"),
+            (json!(null), "f6"),
+            (json!("IETF"), "6449455446"),
+            (json!("\u{00fc}"), "62c3bc"),
+            (json!([1, [2, 3]]), "8201820203"),
+            (json!({ "a": 1, "b": [2, 3] }), "a26161016162820203"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(encode(&value).unwrap(), hex(expected), "{value}");
+            assert_eq!(decode(&hex(expected)).unwrap(), value, "{expected}");
+        }
+
+        assert!(encode(&Value::Object(vec![
+            (String::from("a"), json!(1)),
+            (String::from("a"), json!(2)),
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_decode() {
+        let cases = [
+            // Integers beyond i64 become floats.
+            ("1bffffffffffffffff", json!(18446744073709551615.0)),
+            ("3bffffffffffffffff", json!(-18446744073709551616.0)),
+            ("c249010000000000000000", json!(18446744073709551616.0)),
+            ("c34101", json!(-2)),
+            // Non-string keys, byte strings, tags, undefined.
+            (
+                "a301020a6174f4f6",
+                json!({ "1": 2, "10": "t", "false": null }),
+            ),
+            ("4401020304", json!("AQIDBA")),
+            (
+                "c074323031332d30332d32315432303a30343a30305a",
+                json!("2013-03-21T20:04:00Z"),
+            ),
+            ("f7", json!(null)),
+            // Indefinite lengths.
+            ("5f42010243030405ff", json!("AQIDBAU")),
+            ("7f657374726561646d696e67ff", json!("streaming")),
+            ("9f018202039f0405ffff", json!([1, [2, 3], [4, 5]])),
+            (
+                "bf6346756ef563416d7421ff",
+                json!({ "Fun": true, "Amt": -2 }),
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(decode(&hex(input)).unwrap(), expected, "{input}");
+        }
+
+        let err = |input: &str| decode(&hex(input)).unwrap_err().to_string();
+        assert_eq!(err("1903"), "[offset 2] Unexpected end of CBOR data.");
+        assert_eq!(
+            err("0000"),
+            "[offset 1] Trailing bytes after the CBOR data."
+        );
+        assert_eq!(err("ff"), "[offset 1] Invalid CBOR data, unexpected break.");
+        assert_eq!(
+            err("1c"),
+            "[offset 1] Invalid CBOR data, reserved additional information 28."
+        );
+        assert_eq!(
+            err("9b00000000ffffffff00"),
+            "[offset 9] Invalid CBOR data, length 4294967295 runs past the end."
+        );
+        assert_eq!(
+            err("62c328"),
+            "[offset 1] Invalid CBOR data, invalid UTF-8 in a string."
+        );
+        assert_eq!(
+            err("a1810100"),
+            "$: an array as a map key can't be represented in JSON."
+        );
+        assert_eq!(err("f97c00"), "$: inf can't be represented in JSON.");
+        assert!(decode(&[0x81; 200])
+            .unwrap_err()
+            .to_string()
+            .contains("deeper"));
+    }
+}
@@ -0,0 +1,171 @@
+//! Binary encodings of the same data model as JSON, CBOR (RFC 8949) and MessagePack.
+//!
+//! Encoding picks the smallest representation of every integer, and floats are
+//! written at the narrowest width that holds them exactly. Objects with duplicate
+//! keys can't be encoded, both formats have maps.
+//!
+//! Decoding maps what JSON lacks as RFC 8949 section 6.1 suggests:
+//!
+//! - Byte strings become base64url strings without padding.
+//! - Integer, boolean and null map keys become their JSON text, `1` is `"1"`.
+//!   Arrays and maps as keys are an error.
+//! - Integers beyond the `i64` range become floats, NaN and infinities are an error.
+//! - CBOR tags are dropped, bignums (tags 2 and 3) become numbers. `undefined` and
+//!   other simple values become null. MessagePack extension types are an error.
+
+pub mod cbor;
+pub mod msgpack;
+
+use crate::{
+    convert::unrepresentable,
+    errors::BinaryError,
+    parser::MAX_DEPTH,
+    path::Path,
+    value::{Number, Value},
+};
+use anyhow::Result;
+
+/// A cursor over the input that reports errors with the offset they occur at.
+struct Reader<'a> {
+    input: &'a [u8],
+    offset: usize,
+    format: &'static str,
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(input: &'a [u8], format: &'static str) -> Self {
+        Reader {
+            input,
+            offset: 0,
+            format,
+            depth: 0,
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BinaryError> {
+        let bytes = self
+            .offset
+            .checked_add(n)
+            .and_then(|end| self.input.get(self.offset..end))
+            .ok_or(BinaryError::UnexpectedEnd {
+                offset: self.input.len(),
+                format: self.format,
+            })?;
+        self.offset += n;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BinaryError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, BinaryError> {
+        Ok(u8::from_be_bytes(self.array()?))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.offset).copied()
+    }
+
+    /// Bytes left, an upper bound for how many items a length prefix can announce.
+    fn remaining(&self) -> usize {
+        self.input.len() - self.offset
+    }
+
+    fn invalid(&self, reason: impl Into<String>) -> BinaryError {
+        BinaryError::Invalid {
+            offset: self.offset,
+            format: self.format,
+            reason: reason.into(),
+        }
+    }
+
+    fn text(&mut self, len: usize) -> Result<String, BinaryError> {
+        let start = self.offset;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| BinaryError::Invalid {
+            offset: start + e.utf8_error().valid_up_to(),
+            format: self.format,
+            reason: String::from("invalid UTF-8 in a string"),
+        })
+    }
+
+    /// Enter an array or map.
+    fn nest(&mut self) -> Result<(), BinaryError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            Err(BinaryError::TooDeep {
+                offset: self.offset,
+                max: MAX_DEPTH,
+            })?
+        }
+        Ok(())
+    }
+
+    /// Make sure the whole input was a single item.
+    fn finish(&self) -> Result<(), BinaryError> {
+        if self.offset < self.input.len() {
+            Err(BinaryError::TrailingBytes {
+                offset: self.offset,
+                format: self.format,
+            })?
+        }
+        Ok(())
+    }
+}
+
+fn float(n: f64, path: &Path) -> Result<Value> {
+    if !n.is_finite() {
+        Err(unrepresentable(path, n.to_string(), "JSON"))?
+    }
+    Ok(Value::Number(Number::Float(n)))
+}
+
+/// The JSON object key standing in for a decoded map key.
+fn key(key: Value, path: &Path) -> Result<String> {
+    Ok(match key {
+        Value::String(s) => s,
+        Value::Array(_) | Value::Object(_) => Err(unrepresentable(
+            path,
+            format!("an {} as a map key", key.type_name()),
+            "JSON",
+        ))?,
+        scalar => scalar.to_string(),
+    })
+}
+
+/// Unpadded base64url, RFC 4648 section 5.
+fn base64url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk.first().copied().unwrap_or(0),
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..=chunk.len() {
+            let index = (n >> (18 - 6 * i)) & 0x3F;
+            out.push(char::from(ALPHABET[index as usize]));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base64url() {
+        assert_eq!(base64url(b""), "");
+        assert_eq!(base64url(b"f"), "Zg");
+        assert_eq!(base64url(b"fo"), "Zm8");
+        assert_eq!(base64url(b"foo"), "Zm9v");
+        assert_eq!(base64url(&[0xFB, 0xFF]), "-_8");
+    }
+}
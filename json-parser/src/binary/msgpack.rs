@@ -0,0 +1,290 @@
+//! MessagePack, <https://github.com/msgpack/msgpack/blob/master/spec.md>.
+
+use super::{base64url, float, key, Reader};
+use crate::{
+    convert::{check_unique, unrepresentable},
+    path::Path,
+    value::{Number, Value},
+};
+use anyhow::Result;
+
+const FORMAT: &str = "MessagePack";
+
+/// Encode `value`, with the smallest format family member for every integer and length.
+pub fn encode(value: &Value) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    write(value, &Path::root(), &mut out)?;
+    Ok(out)
+}
+
+/// Decode a single MessagePack object.
+pub fn decode(input: &[u8]) -> Result<Value> {
+    let mut reader = Reader::new(input, FORMAT);
+    let value = read(&mut reader, &Path::root())?;
+    reader.finish()?;
+    Ok(value)
+}
+
+fn write(value: &Value, path: &Path, out: &mut Vec<u8>) -> Result<()> {
+    match value {
+        Value::Null => out.push(0xC0),
+        Value::Bool(false) => out.push(0xC2),
+        Value::Bool(true) => out.push(0xC3),
+        Value::Number(Number::Integer(n)) => write_integer(*n, out),
+        Value::Number(Number::Float(n)) => {
+            let single = *n as f32;
+            if f64::from(single) == *n || n.is_nan() {
+                out.push(0xCA);
+                out.extend(single.to_be_bytes());
+            } else {
+                out.push(0xCB);
+                out.extend(n.to_be_bytes());
+            }
+        }
+        Value::String(s) => {
+            write_length(s.len(), [0xA0, 0xD9, 0xDA, 0xDB], 31, path, out)?;
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            write_length(items.len(), [0x90, 0, 0xDC, 0xDD], 15, path, out)?;
+            for (i, item) in items.iter().enumerate() {
+                write(item, &path.index(i), out)?;
+            }
+        }
+        Value::Object(members) => {
+            check_unique(members, path, FORMAT)?;
+            write_length(members.len(), [0x80, 0, 0xDE, 0xDF], 15, path, out)?;
+            for (key, value) in members {
+                write_length(key.len(), [0xA0, 0xD9, 0xDA, 0xDB], 31, path, out)?;
+                out.extend_from_slice(key.as_bytes());
+                write(value, &path.key(key), out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_integer(n: i64, out: &mut Vec<u8>) {
+    match n {
+        -32..=127 => out.push(n as u8),
+        _ if n > 0 => {
+            if let Ok(n) = u8::try_from(n) {
+                out.extend([0xCC, n]);
+            } else if let Ok(n) = u16::try_from(n) {
+                out.push(0xCD);
+                out.extend(n.to_be_bytes());
+            } else if let Ok(n) = u32::try_from(n) {
+                out.push(0xCE);
+                out.extend(n.to_be_bytes());
+            } else {
+                out.push(0xCF);
+                out.extend(n.to_be_bytes());
+            }
+        }
+        _ => {
+            if let Ok(n) = i8::try_from(n) {
+                out.push(0xD0);
+                out.extend(n.to_be_bytes());
+            } else if let Ok(n) = i16::try_from(n) {
+                out.push(0xD1);
+                out.extend(n.to_be_bytes());
+            } else if let Ok(n) = i32::try_from(n) {
+                out.push(0xD2);
+                out.extend(n.to_be_bytes());
+            } else {
+                out.push(0xD3);
+                out.extend(n.to_be_bytes());
+            }
+        }
+    }
+}
+
+/// Write a length with the fix, 8, 16 or 32 bit member of a family, a `0` marks a
+/// width the family lacks.
+fn write_length(
+    len: usize,
+    [fix, width8, width16, width32]: [u8; 4],
+    fix_max: usize,
+    path: &Path,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    if len <= fix_max {
+        out.push(fix | len as u8);
+    } else if let (Ok(len), true) = (u8::try_from(len), width8 != 0) {
+        out.extend([width8, len]);
+    } else if let Ok(len) = u16::try_from(len) {
+        out.push(width16);
+        out.extend(len.to_be_bytes());
+    } else if let Ok(len) = u32::try_from(len) {
+        out.push(width32);
+        out.extend(len.to_be_bytes());
+    } else {
+        Err(unrepresentable(path, format!("a length of {len}"), FORMAT))?
+    }
+    Ok(())
+}
+
+fn read(reader: &mut Reader, path: &Path) -> Result<Value> {
+    let marker = reader.u8()?;
+    Ok(match marker {
+        0x00..=0x7F => Value::from(marker),
+        0x80..=0x8F => read_map(reader, usize::from(marker & 0x0F), path)?,
+        0x90..=0x9F => read_array(reader, usize::from(marker & 0x0F), path)?,
+        0xA0..=0xBF => Value::String(reader.text(usize::from(marker & 0x1F))?),
+        0xC0 => Value::Null,
+        0xC2 => Value::Bool(false),
+        0xC3 => Value::Bool(true),
+        0xC4..=0xC6 => {
+            let len = length(reader, marker - 0xC4)?;
+            Value::String(base64url(reader.take(len)?))
+        }
+        0xC7..=0xC9 | 0xD4..=0xD8 => Err(unrepresentable(
+            path,
+            String::from("an extension type"),
+            "JSON",
+        ))?,
+        0xCA => float(f64::from(f32::from_be_bytes(reader.array()?)), path)?,
+        0xCB => float(f64::from_be_bytes(reader.array()?), path)?,
+        0xCC => Value::from(u8::from_be_bytes(reader.array()?)),
+        0xCD => Value::from(u16::from_be_bytes(reader.array()?)),
+        0xCE => Value::from(u32::from_be_bytes(reader.array()?)),
+        0xCF => Value::from(u64::from_be_bytes(reader.array()?)),
+        0xD0 => Value::from(i8::from_be_bytes(reader.array()?)),
+        0xD1 => Value::from(i16::from_be_bytes(reader.array()?)),
+        0xD2 => Value::from(i32::from_be_bytes(reader.array()?)),
+        0xD3 => Value::from(i64::from_be_bytes(reader.array()?)),
+        0xD9..=0xDB => {
+            let len = length(reader, marker - 0xD9)?;
+            Value::String(reader.text(len)?)
+        }
+        0xDC | 0xDD => {
+            let len = length(reader, marker - 0xDC + 1)?;
+            read_array(reader, len, path)?
+        }
+        0xDE | 0xDF => {
+            let len = length(reader, marker - 0xDE + 1)?;
+            read_map(reader, len, path)?
+        }
+        0xE0..=0xFF => Value::from(marker as i8),
+        0xC1 => Err(reader.invalid("the never used marker 0xc1"))?,
+    })
+}
+
+/// A length of 1, 2 or 4 bytes for `width` 0, 1 and 2, that can't run past the end.
+fn length(reader: &mut Reader, width: u8) -> Result<usize> {
+    let len = match width {
+        0 => u32::from(reader.u8()?),
+        1 => u32::from(u16::from_be_bytes(reader.array()?)),
+        _ => u32::from_be_bytes(reader.array()?),
+    };
+    match usize::try_from(len) {
+        // Every item takes at least a byte.
+        Ok(len) if len <= reader.remaining() => Ok(len),
+        _ => Err(reader.invalid(format!("length {len} runs past the end")))?,
+    }
+}
+
+fn read_array(reader: &mut Reader, len: usize, path: &Path) -> Result<Value> {
+    reader.nest()?;
+    let mut items = Vec::with_capacity(len.min(reader.remaining()));
+    for i in 0..len {
+        items.push(read(reader, &path.index(i))?);
+    }
+    reader.depth -= 1;
+    Ok(Value::Array(items))
+}
+
+fn read_map(reader: &mut Reader, len: usize, path: &Path) -> Result<Value> {
+    reader.nest()?;
+    let mut members = Vec::with_capacity(len.min(reader.remaining()));
+    for _ in 0..len {
+        let key = key(read(reader, path)?, path)?;
+        let value = read(reader, &path.key(&key))?;
+        members.push((key, value));
+    }
+    reader.depth -= 1;
+    Ok(Value::Object(members))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_encode() {
+        let cases: [(Value, &[u8]); 15] = [
+            (json!(0), &[0x00]),
+            (json!(127), &[0x7F]),
+            (json!(128), &[0xCC, 0x80]),
+            (json!(256), &[0xCD, 0x01, 0x00]),
+            (json!(70000), &[0xCE, 0x00, 0x01, 0x11, 0x70]),
+            (json!(1_i64 << 40), &[0xCF, 0, 0, 1, 0, 0, 0, 0, 0]),
+            (json!(-1), &[0xFF]),
+            (json!(-32), &[0xE0]),
+            (json!(-33), &[0xD0, 0xDF]),
+            (json!(-200), &[0xD1, 0xFF, 0x38]),
+            (json!(i64::MIN), &[0xD3, 0x80, 0, 0, 0, 0, 0, 0, 0]),
+            (json!(1.5), &[0xCA, 0x3F, 0xC0, 0x00, 0x00]),
+            (
+                json!(1.1),
+                &[0xCB, 0x3F, 0xF1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9A],
+            ),
+            (json!([true, null]), &[0x92, 0xC3, 0xC0]),
+            (json!({ "a": "b" }), &[0x81, 0xA1, b'a', 0xA1, b'b']),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(encode(&value).unwrap(), expected, "{value}");
+            assert_eq!(decode(expected).unwrap(), value, "{value}");
+        }
+
+        // Strings have an 8 bit length, arrays and maps go from 4 bits to 16.
+        let long = json!("x".repeat(32));
+        assert_eq!(encode(&long).unwrap()[..2], [0xD9, 32]);
+        assert_eq!(decode(&encode(&long).unwrap()).unwrap(), long);
+        let items = Value::Array(vec![Value::Null; 16]);
+        assert_eq!(encode(&items).unwrap()[..3], [0xDC, 0, 16]);
+    }
+
+    #[test]
+    fn test_decode() {
+        let decoded = |input: &[u8]| decode(input).unwrap();
+        assert_eq!(
+            decoded(&[0xCF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+            json!(18446744073709551615.0)
+        );
+        assert_eq!(decoded(&[0xC4, 0x02, 0x01, 0x02]), json!("AQI"));
+        assert_eq!(
+            decoded(&[0x82, 0x01, 0xC2, 0xC3, 0xC0]),
+            json!({ "1": false, "true": null })
+        );
+        assert_eq!(decoded(&[0xDA, 0x00, 0x01, b'x']), json!("x"));
+
+        let err = |input: &[u8]| decode(input).unwrap_err().to_string();
+        assert_eq!(
+            err(&[0xCD, 0x01]),
+            "[offset 2] Unexpected end of MessagePack data."
+        );
+        assert_eq!(
+            err(&[0xC1]),
+            "[offset 1] Invalid MessagePack data, the never used marker 0xc1."
+        );
+        assert_eq!(
+            err(&[0xDD, 0xFF, 0xFF, 0xFF, 0xFF]),
+            "[offset 5] Invalid MessagePack data, length 4294967295 runs past the end."
+        );
+        assert_eq!(
+            err(&[0xD4, 0x01, 0x00]),
+            "$: an extension type can't be represented in JSON."
+        );
+        assert_eq!(
+            err(&[0x81, 0x90, 0x00]),
+            "$: an array as a map key can't be represented in JSON."
+        );
+        assert_eq!(
+            err(&[0xC0, 0xC0]),
+            "[offset 1] Trailing bytes after the MessagePack data."
+        );
+        assert!(err(&[0x91; 200]).contains("deeper"));
+    }
+}
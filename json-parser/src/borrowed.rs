@@ -0,0 +1,107 @@
+use crate::value::{Number, Value};
+use alloc::{borrow::Cow, vec::Vec};
+use core::fmt;
+
+/// A parsed JSON document borrowing its strings from the input.
+///
+/// Strings and keys are slices of the input, only the ones containing escapes are
+/// allocated. `into_owned` turns it into a `Value` that can outlive the input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorrowedValue<'a> {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(Cow<'a, str>),
+    Array(Vec<BorrowedValue<'a>>),
+    Object(Vec<(Cow<'a, str>, BorrowedValue<'a>)>),
+}
+
+impl<'a> BorrowedValue<'a> {
+    /// Look up the first member named `key`, `None` if this is not an object.
+    pub fn get(&self, key: &str) -> Option<&BorrowedValue<'a>> {
+        match self {
+            BorrowedValue::Object(members) => {
+                members.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+            }
+            _ => None,
+        }
+    }
+
+    /// The string, `None` if this is not a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BorrowedValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Copy the borrowed strings, detaching the value from the input.
+    pub fn into_owned(self) -> Value {
+        match self {
+            BorrowedValue::Null => Value::Null,
+            BorrowedValue::Bool(b) => Value::Bool(b),
+            BorrowedValue::Number(n) => Value::Number(n),
+            BorrowedValue::String(s) => Value::String(s.into_owned()),
+            BorrowedValue::Array(items) => {
+                Value::Array(items.into_iter().map(BorrowedValue::into_owned).collect())
+            }
+            BorrowedValue::Object(members) => Value::Object(
+                members
+                    .into_iter()
+                    .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<BorrowedValue<'_>> for Value {
+    fn from(value: BorrowedValue<'_>) -> Self {
+        value.into_owned()
+    }
+}
+
+/// Compact serialization, same as `Value`'s.
+impl fmt::Display for BorrowedValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.clone().into_owned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_borrowed;
+
+    #[test]
+    fn test_borrows_unescaped_strings() {
+        let input = String::from(r#"{"plain": "abc", "esc\u0061ped": ["a\nb", "x"]}"#);
+        let value = parse_borrowed(&input).unwrap();
+
+        let BorrowedValue::Object(members) = &value else {
+            panic!("expected an object, got {value:?}");
+        };
+        assert!(matches!(members[0].0, Cow::Borrowed("plain")));
+        assert!(matches!(
+            members[0].1,
+            BorrowedValue::String(Cow::Borrowed("abc"))
+        ));
+        assert!(matches!(&members[1].0, Cow::Owned(key) if key == "escaped"));
+
+        let items = value.get("escaped").unwrap();
+        assert_eq!(
+            items,
+            &BorrowedValue::Array(vec![
+                BorrowedValue::String(Cow::Owned(String::from("a\nb"))),
+                BorrowedValue::String(Cow::Borrowed("x")),
+            ])
+        );
+
+        let owned = value.into_owned();
+        drop(input);
+        assert_eq!(
+            owned.to_string(),
+            r#"{"plain":"abc","escaped":["a\nb","x"]}"#
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! RFC 8785 JSON Canonicalization Scheme (JCS).
+
+use crate::value::{Number, Value};
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+
+/// Serialize `value` into its RFC 8785 canonical form.
+///
+/// Object members are sorted by the UTF-16 code units of their keys, numbers are
+/// formatted the way ECMAScript's `Number.prototype.toString` does and strings use
+/// the minimal escaping already produced by `Value`'s compact serializer.
+///
+/// ## Errors
+/// - If an object contains the same key twice.
+/// - If a number is NaN or infinite.
+pub fn canonicalize(value: &Value) -> Result<String> {
+    let mut out = String::new();
+    write_canonical(value, &mut out)?;
+    Ok(out)
+}
+
+fn write_canonical(value: &Value, out: &mut String) -> Result<()> {
+    match value {
+        Value::Number(n) => out.push_str(&format_number(n.as_f64())?),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(members) => {
+            let mut seen = HashSet::new();
+            if let Some((key, _)) = members.iter().find(|(k, _)| !seen.insert(k)) {
+                bail!("duplicate key {key:?} can not be canonicalized");
+            }
+
+            let mut sorted = members.iter().collect::<Vec<_>>();
+            sorted.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push('{');
+            for (i, (key, value)) in sorted.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(value, out)?;
+            }
+            out.push('}');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+
+    Ok(())
+}
+
+/// Format `n` like ECMAScript's `Number.prototype.toString`.
+fn format_number(n: f64) -> Result<String> {
+    if !n.is_finite() {
+        bail!("{} can not be canonicalized", Number::Float(n));
+    }
+    if n == 0.0 {
+        // Also covers -0.
+        return Ok(String::from("0"));
+    }
+    if n < 0.0 {
+        return Ok(format!("-{}", format_number(-n)?));
+    }
+
+    // Rust's exponent formatting yields the shortest round-tripping digits,
+    // e.g. "1.2345e6", leaving only the layout to ECMAScript's rules.
+    let (mut digits, exponent) = split_exponent(&format!("{n:e}"))?;
+
+    // When the exact value sits halfway between two shortest candidates Rust rounds
+    // up, while ECMAScript picks the one with an even last digit.
+    let (exact, exact_exponent) = split_exponent(&format!("{n:.800e}"))?;
+    let (truncated, rest) = exact.split_at(digits.len());
+    if exact_exponent == exponent
+        && rest.starts_with('5')
+        && rest[1..].bytes().all(|b| b == b'0')
+        && truncated.bytes().last().is_some_and(|b| b % 2 == 0)
+        && format!("{truncated}e{}", exponent - digits.len() as i32 + 1).parse::<f64>()? == n
+    {
+        digits = truncated.trim_end_matches('0').to_string();
+    }
+
+    let k = digits.len() as i32;
+    // The decimal point sits after the `n`th digit.
+    let n = exponent + 1;
+
+    let out = if k <= n && n <= 21 {
+        format!("{digits}{}", "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        let (int, frac) = digits.split_at(n as usize);
+        format!("{int}.{frac}")
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{digits}", "0".repeat(-n as usize))
+    } else {
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        let (first, rest) = digits.split_at(1);
+        let rest = if rest.is_empty() {
+            String::new()
+        } else {
+            format!(".{rest}")
+        };
+        format!("{first}{rest}e{sign}{}", (n - 1).abs())
+    };
+
+    Ok(out)
+}
+
+/// Split Rust's exponent notation ("1.2345e6") into its digits ("12345") and exponent (6).
+fn split_exponent(formatted: &str) -> Result<(String, i32)> {
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((formatted, "0"));
+    Ok((mantissa.replace('.', ""), exponent.parse()?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_str;
+
+    #[test]
+    fn test_number_vectors() {
+        // RFC 8785 Appendix B.
+        let test_data = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+
+        for (bits, expected) in test_data {
+            assert_eq!(format_number(f64::from_bits(bits)).unwrap(), expected);
+        }
+
+        assert!(format_number(f64::from_bits(0x7fffffffffffffff)).is_err());
+        assert!(format_number(f64::from_bits(0x7ff0000000000000)).is_err());
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        // RFC 8785 section 3.2.2.
+        let input = r#"{
+  "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+  "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+  "literals": [null, true, false]
+}"#;
+        let expected = r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#;
+
+        assert_eq!(canonicalize(&parse_str(input).unwrap()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_sort_by_utf16() {
+        // RFC 8785 section 3.2.3.
+        let input = r#"{
+  "\u20ac": "Euro Sign",
+  "\r": "Carriage Return",
+  "\ufb33": "Hebrew Letter Dalet With Dagesh",
+  "1": "One",
+  "\ud83d\ude00": "Emoji: Grinning Face",
+  "\u0080": "Control",
+  "\u00f6": "Latin Small Letter O With Diaeresis"
+}"#;
+        let expected = "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\
+            \"ö\":\"Latin Small Letter O With Diaeresis\",\"€\":\"Euro Sign\",\
+            \"😀\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}";
+
+        assert_eq!(canonicalize(&parse_str(input).unwrap()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let value = parse_str(r#"{"a": 1, "a": 2}"#).unwrap();
+
+        assert!(canonicalize(&value).is_err());
+    }
+}
@@ -0,0 +1,505 @@
+//! The command line interface, see the README for what every subcommand does.
+
+use crate::{
+    canonicalize, codegen, convert, diff, gron, input::Input, jq, lazy, lint, lsp, ndjson, parse,
+    parse_with_spans, redact, schema, stats, Error, Value,
+};
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{io::Write, path::PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
+struct Args {
+    /// path to json file to validate and print, - reads stdin
+    file_path: Option<PathBuf>,
+
+    /// print the RFC 8785 canonical form, suitable for hashing and signing
+    #[arg(long)]
+    canonical: bool,
+
+    /// replace invalid UTF-8/16/32 sequences with U+FFFD instead of failing
+    #[arg(long, global = true)]
+    lossy: bool,
+
+    /// how errors in documents are reported on stderr
+    #[arg(long, value_enum, global = true, default_value_t)]
+    error_format: ReportFormat,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Show the semantic differences between two json files
+    Diff {
+        /// path to the original json file
+        left: PathBuf,
+
+        /// path to the changed json file
+        right: PathBuf,
+
+        /// how arrays are compared
+        #[arg(long, value_enum, default_value_t)]
+        arrays: diff::ArrayMode,
+
+        /// numbers closer than this are considered equal
+        #[arg(long, default_value_t = 0.0)]
+        tolerance: f64,
+
+        /// report format
+        #[arg(long, value_enum, default_value_t)]
+        format: ReportFormat,
+    },
+    /// Print the value at a JSON Pointer, without parsing the rest of the file
+    Get {
+        /// path to json file, - reads stdin
+        file_path: PathBuf,
+
+        /// RFC 6901 JSON Pointer, e.g. /items/0/name
+        pointer: String,
+    },
+    /// Validate a newline delimited json file in parallel, printing every document
+    Ndjson {
+        /// path to the ndjson file, - reads stdin
+        file_path: PathBuf,
+
+        /// only print the value at this RFC 6901 JSON Pointer, lines without it are skipped
+        #[arg(long)]
+        pointer: Option<String>,
+
+        /// print documents in input order or as soon as they are parsed
+        #[arg(long, value_enum, default_value_t)]
+        order: ndjson::Order,
+
+        /// worker threads, defaults to one per core
+        #[arg(long)]
+        jobs: Option<usize>,
+    },
+    /// Report statistics and the shape of a document
+    Stats {
+        /// path to json file, - reads stdin
+        file_path: PathBuf,
+
+        /// report format
+        #[arg(long, value_enum, default_value_t)]
+        format: ReportFormat,
+    },
+    /// Infer a JSON Schema from sample documents
+    Schema {
+        /// paths to sample json files, - reads stdin
+        #[arg(required = true)]
+        file_paths: Vec<PathBuf>,
+
+        /// read every line of the files as a separate sample
+        #[arg(long)]
+        ndjson: bool,
+    },
+    /// Generate type definitions from sample json files
+    Codegen {
+        /// paths to sample json files, - reads stdin
+        #[arg(required = true)]
+        file_paths: Vec<PathBuf>,
+
+        /// language to generate
+        #[arg(long, value_enum, default_value_t)]
+        lang: codegen::Lang,
+
+        /// name of the root type
+        #[arg(long, default_value = "Root")]
+        name: String,
+
+        /// read every line of the files as a separate sample
+        #[arg(long)]
+        ndjson: bool,
+    },
+    /// Print every value of a json file as a `path = value;` line, for grep and diff
+    Flatten {
+        /// path to json file, - reads stdin
+        file_path: PathBuf,
+    },
+    /// Rebuild a json document from the lines printed by flatten
+    Unflatten {
+        /// path to the flattened file, - reads stdin
+        file_path: PathBuf,
+    },
+    /// Remove, mask or hash secrets, keeping the rest of the text as is
+    Redact {
+        /// path to json file, - reads stdin
+        file_path: PathBuf,
+
+        /// path to the json rules file, see the Redact section of the README for the
+        /// format
+        #[arg(long)]
+        rules: PathBuf,
+
+        /// key for hashed values, a random one per run when not set
+        #[arg(long, env = "JSON_PARSER_REDACT_KEY", hide_env_values = true)]
+        key: Option<String>,
+    },
+    /// Check json files against the style rules of a .jsonlint.json file
+    Lint {
+        /// paths to json files, - reads stdin
+        #[arg(required = true)]
+        file_paths: Vec<PathBuf>,
+
+        /// path to the rules, defaults to .jsonlint.json in the current directory,
+        /// see the Lint section of the README for the format
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// report format
+        #[arg(long, value_enum, default_value_t)]
+        format: ReportFormat,
+    },
+    /// Transform a document with a jq filter
+    Jq {
+        /// jq filter, e.g. '.items | map({id, name})'
+        filter: String,
+
+        /// path to json file, - reads stdin
+        file_path: PathBuf,
+
+        /// print every output on a single line
+        #[arg(long, short)]
+        compact: bool,
+
+        /// print strings without quotes
+        #[arg(long, short)]
+        raw_output: bool,
+    },
+    /// Run a language server for json files over stdin and stdout
+    Lsp,
+    /// Convert between json, yaml, toml, csv, cbor and msgpack
+    Convert {
+        /// path to the input file, - reads stdin
+        file_path: PathBuf,
+
+        /// format of the input file
+        #[arg(long, value_enum, default_value_t)]
+        from: convert::Format,
+
+        /// format to print
+        #[arg(long, value_enum, default_value_t)]
+        to: convert::Format,
+
+        /// report the output size next to the compact json size on stderr
+        #[arg(long)]
+        size: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum ReportFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Entrypoint
+pub fn run() -> Result<()> {
+    let args = Args::parse();
+    let error_format = args.error_format;
+
+    execute(args).map_err(|e| match error_format {
+        ReportFormat::Text => e,
+        ReportFormat::Json => match e.downcast_ref::<Error>() {
+            Some(error) => anyhow::anyhow!("{}", error.to_json()),
+            None => e,
+        },
+    })
+}
+
+fn execute(args: Args) -> Result<()> {
+    match args.command {
+        None => {
+            let file_path = args.file_path.context("get file_path")?;
+            let input = Input::open(&file_path)?;
+            let value = parse(&input.text(args.lossy)?)?;
+
+            if args.canonical {
+                // No trailing newline, the output is meant to be hashed byte for byte.
+                print!("{}", canonicalize(&value)?);
+            } else {
+                println!("{}", value.to_pretty_string(2));
+            }
+        }
+        Some(Commands::Get { file_path, pointer }) => {
+            let input = Input::open(&file_path)?;
+            let contents = input.text(args.lossy)?;
+            let doc = lazy::Document::new(&contents)?;
+            let node = doc
+                .root()
+                .pointer(&pointer)?
+                .with_context(|| format!("nothing at {pointer:?}"))?;
+
+            println!("{}", node.value()?.to_pretty_string(2));
+        }
+        Some(Commands::Ndjson {
+            file_path,
+            pointer,
+            order,
+            jobs,
+        }) => {
+            let input = Input::open(&file_path)?;
+            let text = input.text(args.lossy)?;
+
+            let query = |line: &str| {
+                let value = parse(line)?;
+                Ok(match &pointer {
+                    Some(pointer) => value.pointer(pointer).cloned(),
+                    None => Some(value),
+                })
+            };
+
+            let mut stdout = std::io::stdout().lock();
+            let mut lines = 0;
+            let mut errors = Vec::new();
+            ndjson::par_map(
+                &text,
+                ndjson::ParOptions { order, jobs },
+                query,
+                |_, result| {
+                    lines += 1;
+                    match result {
+                        Ok(Some(value)) => writeln!(stdout, "{value}")?,
+                        Ok(None) => {}
+                        Err(e) => errors.push(e),
+                    }
+                    Ok(())
+                },
+            )?;
+
+            if !errors.is_empty() {
+                errors.sort_by_key(|e| e.line);
+                for e in &errors {
+                    match (args.error_format, e.error.downcast_ref::<Error>()) {
+                        (ReportFormat::Json, Some(error)) => {
+                            let mut report = vec![(String::from("line"), Value::from(e.line))];
+                            if let Value::Object(members) = error.to_json() {
+                                report.extend(members);
+                            }
+                            eprintln!("{}", Value::Object(report));
+                        }
+                        _ => eprintln!("{e}"),
+                    }
+                }
+                anyhow::bail!("{} of {lines} lines are invalid", errors.len());
+            }
+        }
+        Some(Commands::Stats { file_path, format }) => {
+            let input = Input::open(&file_path)?;
+            let stats = stats::stats(&input.text(args.lossy)?)?;
+
+            match format {
+                ReportFormat::Text => print!("{}", stats.to_text()),
+                ReportFormat::Json => println!("{}", stats.to_json().to_pretty_string(2)),
+            }
+        }
+        Some(Commands::Schema { file_paths, ndjson }) => {
+            let samples = read_samples(&file_paths, ndjson, args.lossy)?;
+            println!("{}", schema::infer(&samples).to_pretty_string(2));
+        }
+        Some(Commands::Codegen {
+            file_paths,
+            lang,
+            name,
+            ndjson,
+        }) => {
+            let samples = read_samples(&file_paths, ndjson, args.lossy)?;
+            match lang {
+                codegen::Lang::Rust => print!("{}", codegen::rust(&samples, &name)),
+            }
+        }
+        Some(Commands::Flatten { file_path }) => {
+            let input = Input::open(&file_path)?;
+            let value = parse(&input.text(args.lossy)?)?;
+            print!("{}", gron::flatten(&value)?);
+        }
+        Some(Commands::Unflatten { file_path }) => {
+            let input = Input::open(&file_path)?;
+            let value = gron::unflatten(&input.text(args.lossy)?)?;
+            println!("{}", value.to_pretty_string(2));
+        }
+        Some(Commands::Redact {
+            file_path,
+            rules,
+            key,
+        }) => {
+            let rules_input = Input::open(&rules)?;
+            let rules = redact::Rules::parse(&rules_input.text(args.lossy)?)
+                .with_context(|| format!("read rules from {}", rules.display()))?;
+            let key = match key {
+                Some(key) => key.into_bytes(),
+                None => redact::random_key(),
+            };
+
+            let input = Input::open(&file_path)?;
+            print!(
+                "{}",
+                redact::redact(&input.text(args.lossy)?, &rules, &key)?
+            );
+        }
+        Some(Commands::Lint {
+            file_paths,
+            config,
+            format,
+        }) => {
+            let config = match config.or_else(|| {
+                let default = PathBuf::from(".jsonlint.json");
+                default.exists().then_some(default)
+            }) {
+                Some(path) => {
+                    let input = Input::open(&path)?;
+                    lint::Config::parse(&input.text(args.lossy)?)
+                        .with_context(|| format!("read rules from {}", path.display()))?
+                }
+                None => lint::Config::default(),
+            };
+
+            let (mut errors, mut warnings) = (0, 0);
+            for file_path in &file_paths {
+                // Unreadable files are reported like unparsable ones, as a diagnostic.
+                let diagnostics = match Input::open(file_path) {
+                    Ok(input) => match input.text(args.lossy) {
+                        Ok(text) => lint::lint(&text, &config),
+                        Err(e) => vec![lint::Diagnostic::unreadable(&e)],
+                    },
+                    Err(e) => vec![lint::Diagnostic::unreadable(&e)],
+                };
+
+                for diagnostic in diagnostics {
+                    match diagnostic.severity {
+                        lint::Severity::Error => errors += 1,
+                        lint::Severity::Warning => warnings += 1,
+                    }
+                    match format {
+                        ReportFormat::Text => println!("{}: {diagnostic}", file_path.display()),
+                        ReportFormat::Json => {
+                            let file = Value::from(file_path.display().to_string());
+                            let mut report = vec![(String::from("file"), file)];
+                            if let Value::Object(members) = diagnostic.to_json() {
+                                report.extend(members);
+                            }
+                            println!("{}", Value::Object(report));
+                        }
+                    }
+                }
+            }
+
+            if errors > 0 {
+                let plural = |n: usize, what: &str| match n {
+                    1 => format!("1 {what}"),
+                    n => format!("{n} {what}s"),
+                };
+                anyhow::bail!(
+                    "{} and {}",
+                    plural(errors, "error"),
+                    plural(warnings, "warning")
+                );
+            }
+        }
+        Some(Commands::Jq {
+            filter,
+            file_path,
+            compact,
+            raw_output,
+        }) => {
+            let filter = jq::Filter::new(&filter)?;
+            let input = Input::open(&file_path)?;
+            let value = parse(&input.text(args.lossy)?)?;
+
+            for output in filter.run(&value)? {
+                match output {
+                    Value::String(s) if raw_output => println!("{s}"),
+                    output if compact => println!("{output}"),
+                    output => println!("{}", output.to_pretty_string(2)),
+                }
+            }
+        }
+        Some(Commands::Lsp) => {
+            lsp::serve(std::io::stdin().lock(), std::io::stdout().lock())?;
+        }
+        Some(Commands::Convert {
+            file_path,
+            from,
+            to,
+            size,
+        }) => {
+            let input = Input::open(&file_path)?;
+            let value = match from.is_binary() {
+                true => convert::from_bytes(&input, from)?,
+                false => convert::from_str(&input.text(args.lossy)?, from)?,
+            };
+            let output = convert::to_bytes(&value, to)?;
+
+            if size {
+                let json = value.to_string().len();
+                let ratio = output.len() as f64 * 100.0 / json.max(1) as f64;
+                eprintln!(
+                    "{} bytes, {ratio:.1}% of the {json} bytes of compact json",
+                    output.len()
+                );
+            }
+            std::io::stdout()
+                .lock()
+                .write_all(&output)
+                .context("write output")?;
+        }
+        Some(Commands::Diff {
+            left,
+            right,
+            arrays,
+            tolerance,
+            format,
+        }) => {
+            let (left_input, right_input) = (Input::open(&left)?, Input::open(&right)?);
+            let (left_value, left_spans) = parse_with_spans(&left_input.text(args.lossy)?)
+                .with_context(|| format!("parse {}", left.display()))?;
+            let (right_value, right_spans) = parse_with_spans(&right_input.text(args.lossy)?)
+                .with_context(|| format!("parse {}", right.display()))?;
+
+            let options = diff::DiffOptions { arrays, tolerance };
+            let changes = diff::diff(&left_value, &right_value, &options);
+
+            let left_name = left.display().to_string();
+            let right_name = right.display().to_string();
+            let left = diff::Side {
+                name: &left_name,
+                spans: &left_spans,
+            };
+            let right = diff::Side {
+                name: &right_name,
+                spans: &right_spans,
+            };
+
+            match format {
+                ReportFormat::Text => print!("{}", diff::to_text(&changes, &left, &right)),
+                ReportFormat::Json => {
+                    let report = diff::to_json(&changes, &left, &right);
+                    println!("{}", report.to_pretty_string(2))
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Every document of the sample files, one per file or one per line with `ndjson`.
+fn read_samples(file_paths: &[PathBuf], ndjson: bool, lossy: bool) -> Result<Vec<Value>> {
+    let mut samples = Vec::new();
+    for file_path in file_paths {
+        let input = Input::open(file_path)?;
+        let text = input.text(lossy)?;
+        if ndjson {
+            samples.extend(
+                ndjson::parse(&text).with_context(|| format!("parse {}", file_path.display()))?,
+            );
+        } else {
+            samples.push(parse(&text).with_context(|| format!("parse {}", file_path.display()))?);
+        }
+    }
+    Ok(samples)
+}
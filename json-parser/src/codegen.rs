@@ -0,0 +1,478 @@
+//! Type definitions generated from sample documents.
+//!
+//! The samples go through `schema::infer` first, so the rules match the schema
+//! subcommand: members missing from some samples, or `null` in some, become `Option`,
+//! integers seen along with floats become `f64`, and positions holding several
+//! types fall back to `serde_json::Value`.
+//!
+//! Every object gets its own struct named after its key, array items after the
+//! singular of theirs. Objects with the same fields share a single struct. Names
+//! that would shadow the prelude or the serde derives, e.g. `String`, get a `Type`
+//! suffix.
+
+use crate::value::Value;
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum Lang {
+    #[default]
+    Rust,
+}
+
+/// Rust structs deriving serde's `Serialize` and `Deserialize` for the samples,
+/// the root type is called `name`.
+pub fn rust<'a>(samples: impl IntoIterator<Item = &'a Value>, name: &str) -> String {
+    let schema = crate::schema::infer(samples);
+    let name = pascal_case(name);
+    let mut generator = Generator {
+        structs: Vec::new(),
+        root: name.clone(),
+        depth: 0,
+    };
+    let root = generator.type_of(&schema, &name, true);
+
+    let mut out = String::new();
+    if !generator.structs.is_empty() {
+        out.push_str("use serde::{Deserialize, Serialize};\n");
+    }
+    if root != name {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("pub type {name} = {root};\n"));
+    }
+    // Children are generated first, the root reads best at the top.
+    for s in generator.structs.iter().rev() {
+        out.push('\n');
+        out.push_str(&s.to_rust());
+    }
+    out
+}
+
+struct Generator {
+    structs: Vec<Struct>,
+    /// Name of the root type, only the root object may take it.
+    root: String,
+    /// How many objects deep the generator is.
+    depth: usize,
+}
+
+#[derive(PartialEq)]
+struct Struct {
+    name: String,
+    fields: Vec<Field>,
+}
+
+#[derive(PartialEq)]
+struct Field {
+    /// Key in the document.
+    key: String,
+    ident: String,
+    ty: String,
+    /// Missing from some samples.
+    optional: bool,
+}
+
+impl Generator {
+    /// Rust type for values matching `schema`, `name` is used for a struct.
+    fn type_of(&mut self, schema: &Value, name: &str, required: bool) -> String {
+        let mut types = match schema.get("type") {
+            Some(Value::String(ty)) => vec![ty.as_str()],
+            Some(Value::Array(types)) => types
+                .iter()
+                .filter_map(|ty| match ty {
+                    Value::String(ty) => Some(ty.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let nullable = types.contains(&"null");
+        types.retain(|ty| *ty != "null");
+
+        let ty = match types.as_slice() {
+            ["boolean"] => String::from("bool"),
+            ["integer"] => String::from("i64"),
+            ["number"] => String::from("f64"),
+            ["string"] => String::from("String"),
+            ["array"] => {
+                let item = match schema.get("items") {
+                    Some(items) => self.type_of(items, &singular(name), true),
+                    None => String::from("serde_json::Value"),
+                };
+                format!("Vec<{item}>")
+            }
+            ["object"] => self.object(schema, name),
+            _ => String::from("serde_json::Value"),
+        };
+
+        match nullable || !required {
+            true => format!("Option<{ty}>"),
+            false => ty,
+        }
+    }
+
+    /// Name of the struct for an object schema, reusing one with the same fields.
+    fn object(&mut self, schema: &Value, name: &str) -> String {
+        let required: Vec<&Value> = match schema.get("required") {
+            Some(Value::Array(required)) => required.iter().collect(),
+            _ => Vec::new(),
+        };
+
+        let mut fields: Vec<Field> = Vec::new();
+        self.depth += 1;
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            for (key, property) in properties {
+                let optional = !required.iter().any(|r| **r == Value::from(key.as_str()));
+                let ty = self.type_of(property, &pascal_case(key), !optional);
+                let mut ident = snake_case(key);
+                // `fooBar` and `foo_bar` both map to `foo_bar`.
+                let base = ident.clone();
+                let mut n = 1;
+                while fields.iter().any(|f| f.ident == ident) {
+                    n += 1;
+                    ident = format!("{base}_{n}");
+                }
+                fields.push(Field {
+                    key: key.clone(),
+                    ident,
+                    ty,
+                    optional,
+                });
+            }
+        }
+        self.depth -= 1;
+
+        if let Some(same) = self.structs.iter().find(|s| s.fields == fields) {
+            return same.name.clone();
+        }
+
+        let base = match name.is_empty() {
+            true => String::from("Object"),
+            false => String::from(name),
+        };
+        let mut name = base.clone();
+        let mut n = 1;
+        while self.structs.iter().any(|s| s.name == name) || name == self.root && self.depth > 0 {
+            n += 1;
+            name = format!("{base}{n}");
+        }
+        self.structs.push(Struct {
+            name: name.clone(),
+            fields,
+        });
+        name
+    }
+}
+
+impl Struct {
+    fn to_rust(&self) -> String {
+        let mut out = String::from("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n");
+        out.push_str(&format!("pub struct {} {{\n", self.name));
+        for field in &self.fields {
+            if field.ident.trim_start_matches("r#") != field.key {
+                out.push_str(&format!(
+                    "    #[serde(rename = {})]\n",
+                    Value::from(field.key.as_str())
+                ));
+            }
+            if field.optional {
+                out.push_str("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n");
+            }
+            out.push_str(&format!("    pub {}: {},\n", field.ident, field.ty));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Words of a key, split on non alphanumeric chars and lower to upper case steps,
+/// `userID2FA` is `user`, `id2`, `fa`.
+fn words(key: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let chars: Vec<char> = key.chars().collect();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        let previous = i.checked_sub(1).and_then(|i| chars.get(i));
+        let next = chars.get(i + 1);
+        // `aB` starts a word at `B`, and so does `ABc` at `B`.
+        let boundary = c.is_uppercase()
+            && (previous.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit())
+                || previous.is_some_and(|p| p.is_uppercase())
+                    && next.is_some_and(|n| n.is_lowercase()));
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// A valid Rust field name for `key`, e.g. `userId` becomes `user_id` and `type`
+/// becomes `r#type`.
+fn snake_case(key: &str) -> String {
+    let mut ident = words(key).join("_");
+    ident.retain(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if ident.is_empty() {
+        return String::from("field");
+    }
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        return format!("_{ident}");
+    }
+    match ident.as_str() {
+        // Keywords that can't be raw identifiers.
+        "self" | "super" | "crate" => format!("{ident}_"),
+        ident if KEYWORDS.contains(&ident) => format!("r#{ident}"),
+        _ => ident,
+    }
+}
+
+/// A valid Rust type name for `key`, e.g. `billing_address` becomes `BillingAddress`.
+fn pascal_case(key: &str) -> String {
+    let mut name: String = words(key)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .concat();
+    name.retain(|c| c.is_ascii_alphanumeric());
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, 'T');
+    }
+    // `Self`, and names the generated code or its reader may rely on.
+    match name.as_str() {
+        name if RESERVED_TYPES.contains(&name) => format!("{name}Type"),
+        _ => name,
+    }
+}
+
+/// Type name for the items of an array named `name`, `Addresses` holds `Address`.
+fn singular(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix("ies") {
+        return format!("{stem}y");
+    }
+    for suffix in ["sses", "xes", "ches", "shes"] {
+        if let Some(stem) = name.strip_suffix(suffix) {
+            return format!("{stem}{}", &suffix[..suffix.len() - 2]);
+        }
+    }
+    match name.strip_suffix('s') {
+        Some(stem) if !stem.is_empty() && !stem.ends_with('s') => String::from(stem),
+        _ => format!("{name}Item"),
+    }
+}
+
+/// Types, traits and variants of the std prelude, `Self` and the serde derives.
+const RESERVED_TYPES: &[&str] = &[
+    "AsMut",
+    "AsRef",
+    "Box",
+    "Clone",
+    "Copy",
+    "Default",
+    "Deserialize",
+    "DoubleEndedIterator",
+    "Drop",
+    "Eq",
+    "Err",
+    "ExactSizeIterator",
+    "Extend",
+    "Fn",
+    "FnMut",
+    "FnOnce",
+    "From",
+    "FromIterator",
+    "Into",
+    "IntoIterator",
+    "Iterator",
+    "None",
+    "Ok",
+    "Option",
+    "Ord",
+    "PartialEq",
+    "PartialOrd",
+    "Result",
+    "Self",
+    "Send",
+    "Serialize",
+    "Sized",
+    "Some",
+    "String",
+    "Sync",
+    "ToOwned",
+    "ToString",
+    "TryFrom",
+    "TryInto",
+    "Unpin",
+    "Vec",
+];
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "union", "unsafe", "unsized",
+    "use", "virtual", "where", "while", "yield",
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(snake_case("userId"), "user_id");
+        assert_eq!(snake_case("HTTPStatus"), "http_status");
+        assert_eq!(snake_case("created-at"), "created_at");
+        assert_eq!(snake_case("2fa"), "_2fa");
+        assert_eq!(snake_case("type"), "r#type");
+        assert_eq!(snake_case("self"), "self_");
+        assert_eq!(snake_case("$"), "field");
+        assert_eq!(pascal_case("billing_address"), "BillingAddress");
+        assert_eq!(pascal_case("3d"), "T3d");
+        assert_eq!(singular("Addresses"), "Address");
+        assert_eq!(singular("Categories"), "Category");
+        assert_eq!(singular("Tags"), "Tag");
+        assert_eq!(singular("Data"), "DataItem");
+    }
+
+    #[test]
+    fn test_rust() {
+        let samples = [
+            json!({
+                "id": 1,
+                "userName": "a",
+                "type": "admin",
+                "billingAddress": { "city": "x", "zip": "1" },
+                "shippingAddress": { "city": "y", "zip": "2" },
+                "tags": [{ "label": "t", "score": 0.5 }],
+            }),
+            json!({
+                "id": 2,
+                "userName": "b",
+                "type": null,
+                "billingAddress": { "city": "z", "zip": "3" },
+                "shippingAddress": { "city": "w", "zip": "4" },
+                "tags": [],
+                "note": "only here",
+            }),
+        ];
+
+        assert_eq!(
+            rust(&samples, "user"),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    pub id: i64,
+    #[serde(rename = "userName")]
+    pub user_name: String,
+    pub r#type: Option<String>,
+    #[serde(rename = "billingAddress")]
+    pub billing_address: BillingAddress,
+    #[serde(rename = "shippingAddress")]
+    pub shipping_address: BillingAddress,
+    pub tags: Vec<Tag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub label: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BillingAddress {
+    pub city: String,
+    pub zip: String,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_rust_root_array() {
+        let samples = [json!([{ "a": 1, "b": [1, "x"], "root": { "c": true } }])];
+        assert_eq!(
+            rust(&samples, "Root"),
+            r#"use serde::{Deserialize, Serialize};
+
+pub type Root = Vec<RootItem>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RootItem {
+    pub a: i64,
+    pub b: Vec<serde_json::Value>,
+    pub root: Root2,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root2 {
+    pub c: bool,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_rust_reserved_names() {
+        let samples =
+            [json!({ "string": { "a": "x" }, "option": { "b": 1 }, "self": { "c": null } })];
+        assert_eq!(
+            rust(&samples, "vec"),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VecType {
+    pub string: StringType,
+    pub option: OptionType,
+    #[serde(rename = "self")]
+    pub self_: SelfType,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelfType {
+    pub c: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptionType {
+    pub b: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StringType {
+    pub a: String,
+}
+"#
+        );
+        assert_eq!(pascal_case("serialize"), "SerializeType");
+        assert_eq!(pascal_case("strings"), "Strings");
+
+        // No struct, no serde import.
+        assert_eq!(
+            rust(&[json!([])], "Root"),
+            "pub type Root = Vec<serde_json::Value>;\n"
+        );
+    }
+}
@@ -0,0 +1,516 @@
+//! Conversion between JSON and other formats, going through `Value`.
+//!
+//! CBOR and MessagePack are binary, they go through `to_bytes` and `from_bytes`, see
+//! `binary` for how they map to JSON.
+//!
+//! What the other formats can't hold is an error rather than silently dropped:
+//!
+//! - TOML has no null and its top level must be a table.
+//! - YAML and TOML don't allow duplicate keys.
+//! - CSV needs an array of objects, one row each. Nested objects and arrays are
+//!   flattened into dotted headers (`address.city`, `tags.0`), so keys can't contain a
+//!   `.` themselves. Null and missing members are written as empty cells.
+//!
+//! Reading CSV reverses the flattening: headers are split on `.`, members whose keys
+//! are `0`, `1`, ... become arrays. Empty cells are left out of the row, `true` and
+//! `false` become booleans, `[]` and `{}` empty containers, valid JSON numbers become
+//! numbers and everything else a string.
+
+use crate::{
+    binary,
+    errors::ConvertError,
+    lexer::{is_valid_number, to_number},
+    path::Path,
+    value::{Number, Value},
+};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+/// A document format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    #[default]
+    Json,
+    Yaml,
+    Toml,
+    Csv,
+    Cbor,
+    #[value(name = "msgpack")]
+    MessagePack,
+}
+
+impl Format {
+    /// Whether documents in this format are bytes rather than text.
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Format::Cbor | Format::MessagePack)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Yaml => "YAML",
+            Format::Toml => "TOML",
+            Format::Csv => "CSV",
+            Format::Cbor => "CBOR",
+            Format::MessagePack => "MessagePack",
+        }
+    }
+}
+
+/// Serialize `value` as `format`.
+pub fn to_string(value: &Value, format: Format) -> Result<String> {
+    Ok(match format {
+        Format::Json => value.to_pretty_string(2) + "\n",
+        Format::Yaml => serde_yaml::to_string(&to_yaml(value, &Path::root())?)?,
+        Format::Toml => match value {
+            Value::Object(members) => toml::to_string(&to_toml_table(members, &Path::root())?)?,
+            _ => Err(unrepresentable(
+                &Path::root(),
+                format!("a top-level {}", value.type_name()),
+                "TOML",
+            ))?,
+        },
+        Format::Csv => to_csv(value)?,
+        Format::Cbor | Format::MessagePack => Err(ConvertError::Binary {
+            format: format.name(),
+        })?,
+    })
+}
+
+/// Serialize `value` as `format`, text formats are UTF-8 encoded.
+pub fn to_bytes(value: &Value, format: Format) -> Result<Vec<u8>> {
+    match format {
+        Format::Cbor => binary::cbor::encode(value),
+        Format::MessagePack => binary::msgpack::encode(value),
+        _ => Ok(to_string(value, format)?.into_bytes()),
+    }
+}
+
+/// Parse `input` written in `format`.
+pub fn from_str(input: &str, format: Format) -> Result<Value> {
+    Ok(match format {
+        Format::Json => crate::parse(input)?,
+        Format::Yaml => from_yaml(serde_yaml::from_str(input)?, &Path::root())?,
+        Format::Toml => from_toml(toml::Value::Table(input.parse()?), &Path::root())?,
+        Format::Csv => from_csv(input)?,
+        Format::Cbor | Format::MessagePack => Err(ConvertError::Binary {
+            format: format.name(),
+        })?,
+    })
+}
+
+/// Parse `input` written in `format`, text formats are decoded as `encoding::decode` does.
+pub fn from_bytes(input: &[u8], format: Format) -> Result<Value> {
+    match format {
+        Format::Cbor => binary::cbor::decode(input),
+        Format::MessagePack => binary::msgpack::decode(input),
+        _ => from_str(&crate::encoding::decode(input)?, format),
+    }
+}
+
+pub(crate) fn unrepresentable(path: &Path, what: String, format: &'static str) -> ConvertError {
+    ConvertError::Unrepresentable {
+        path: path.to_string(),
+        what,
+        format,
+    }
+}
+
+/// Make sure no key shows up twice, for formats where objects are maps.
+pub(crate) fn check_unique(
+    members: &[(String, Value)],
+    path: &Path,
+    format: &'static str,
+) -> Result<()> {
+    let mut seen = HashSet::new();
+    for (key, _) in members {
+        if !seen.insert(key.as_str()) {
+            Err(ConvertError::DuplicateKey {
+                path: path.to_string(),
+                key: key.clone(),
+                format,
+            })?
+        }
+    }
+    Ok(())
+}
+
+fn to_yaml(value: &Value, path: &Path) -> Result<serde_yaml::Value> {
+    Ok(match value {
+        Value::Null => serde_yaml::Value::Null,
+        Value::Bool(b) => serde_yaml::Value::Bool(*b),
+        Value::Number(Number::Integer(n)) => serde_yaml::Value::Number((*n).into()),
+        Value::Number(Number::Float(n)) => serde_yaml::Value::Number((*n).into()),
+        Value::String(s) => serde_yaml::Value::String(s.clone()),
+        Value::Array(items) => serde_yaml::Value::Sequence(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| to_yaml(item, &path.index(i)))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Object(members) => {
+            check_unique(members, path, "YAML")?;
+            serde_yaml::Value::Mapping(
+                members
+                    .iter()
+                    .map(|(k, v)| {
+                        Ok((
+                            serde_yaml::Value::String(k.clone()),
+                            to_yaml(v, &path.key(k))?,
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+            )
+        }
+    })
+}
+
+fn from_yaml(value: serde_yaml::Value, path: &Path) -> Result<Value> {
+    Ok(match value {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(b) => Value::Bool(b),
+        serde_yaml::Value::Number(n) => match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(n), _, _) => Value::from(n),
+            (_, Some(n), _) => Value::from(n),
+            (_, _, Some(n)) if n.is_finite() => Value::from(n),
+            _ => Err(unrepresentable(path, n.to_string(), "JSON"))?,
+        },
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(items) => Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| from_yaml(item, &path.index(i)))
+                .collect::<Result<_>>()?,
+        ),
+        serde_yaml::Value::Mapping(members) => Value::Object(
+            members
+                .into_iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        serde_yaml::Value::String(s) => s,
+                        serde_yaml::Value::Null => String::from("null"),
+                        serde_yaml::Value::Bool(b) => b.to_string(),
+                        serde_yaml::Value::Number(n) => n.to_string(),
+                        _ => Err(unrepresentable(
+                            path,
+                            String::from("a non-scalar key"),
+                            "JSON",
+                        ))?,
+                    };
+                    let value = from_yaml(v, &path.key(&key))?;
+                    Ok((key, value))
+                })
+                .collect::<Result<_>>()?,
+        ),
+        // Tags have no JSON equivalent, keep what they are attached to.
+        serde_yaml::Value::Tagged(tagged) => from_yaml(tagged.value, path)?,
+    })
+}
+
+fn to_toml(value: &Value, path: &Path) -> Result<toml::Value> {
+    Ok(match value {
+        Value::Null => Err(unrepresentable(path, String::from("null"), "TOML"))?,
+        Value::Bool(b) => toml::Value::Boolean(*b),
+        Value::Number(Number::Integer(n)) => toml::Value::Integer(*n),
+        Value::Number(Number::Float(n)) => toml::Value::Float(*n),
+        Value::String(s) => toml::Value::String(s.clone()),
+        Value::Array(items) => toml::Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| to_toml(item, &path.index(i)))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Object(members) => toml::Value::Table(to_toml_table(members, path)?),
+    })
+}
+
+fn to_toml_table(members: &[(String, Value)], path: &Path) -> Result<toml::Table> {
+    check_unique(members, path, "TOML")?;
+    members
+        .iter()
+        .map(|(k, v)| Ok((k.clone(), to_toml(v, &path.key(k))?)))
+        .collect()
+}
+
+fn from_toml(value: toml::Value, path: &Path) -> Result<Value> {
+    Ok(match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(n) => Value::from(n),
+        toml::Value::Float(n) if n.is_finite() => Value::from(n),
+        toml::Value::Float(n) => Err(unrepresentable(path, n.to_string(), "JSON"))?,
+        toml::Value::Boolean(b) => Value::Bool(b),
+        // JSON has no date type, RFC 3339 text is the usual stand-in.
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| from_toml(item, &path.index(i)))
+                .collect::<Result<_>>()?,
+        ),
+        toml::Value::Table(members) => Value::Object(
+            members
+                .into_iter()
+                .map(|(k, v)| {
+                    let value = from_toml(v, &path.key(&k))?;
+                    Ok((k, value))
+                })
+                .collect::<Result<_>>()?,
+        ),
+    })
+}
+
+fn to_csv(value: &Value) -> Result<String> {
+    let root = Path::root();
+    let Value::Array(rows) = value else {
+        Err(unrepresentable(
+            &root,
+            format!("a top-level {}", value.type_name()),
+            "CSV",
+        ))?
+    };
+
+    let mut headers = Vec::<String>::new();
+    let mut columns = HashMap::<String, usize>::new();
+    let mut cells = Vec::new();
+
+    for (i, row) in rows.iter().enumerate() {
+        let path = root.index(i);
+        if !matches!(row, Value::Object(_)) {
+            Err(unrepresentable(
+                &path,
+                format!("a {} row", row.type_name()),
+                "CSV",
+            ))?
+        }
+
+        let mut flat = Vec::new();
+        flatten(row, None, &path, &mut flat)?;
+        for (header, _) in &flat {
+            if !columns.contains_key(header) {
+                columns.insert(header.clone(), headers.len());
+                headers.push(header.clone());
+            }
+        }
+        cells.push(flat);
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(&headers)?;
+    for flat in cells {
+        let mut record = vec![String::new(); headers.len()];
+        for (header, cell) in flat {
+            if let Some(cell_ref) = columns.get(&header).and_then(|&i| record.get_mut(i)) {
+                *cell_ref = cell;
+            }
+        }
+        writer.write_record(&record)?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Collect the `(header, cell)` pairs of `value`, nested under `prefix`.
+fn flatten(
+    value: &Value,
+    prefix: Option<&str>,
+    path: &Path,
+    out: &mut Vec<(String, String)>,
+) -> Result<()> {
+    let header = |key: &str| match prefix {
+        Some(prefix) => format!("{prefix}.{key}"),
+        None => key.to_string(),
+    };
+
+    match value {
+        // An empty row has no cells at all.
+        Value::Object(members) if !members.is_empty() || prefix.is_none() => {
+            for (key, value) in members {
+                if key.contains('.') {
+                    Err(ConvertError::DottedKey {
+                        path: path.to_string(),
+                        key: key.clone(),
+                    })?
+                }
+                flatten(value, Some(&header(key)), &path.key(key), out)?;
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (i, item) in items.iter().enumerate() {
+                flatten(item, Some(&header(&i.to_string())), &path.index(i), out)?;
+            }
+        }
+        leaf => {
+            let cell = match leaf {
+                Value::Null => String::new(),
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            out.push((prefix.unwrap_or_default().to_string(), cell));
+        }
+    }
+
+    Ok(())
+}
+
+fn from_csv(input: &str) -> Result<Value> {
+    let mut reader = csv::Reader::from_reader(input.as_bytes());
+    let headers = reader.headers()?.clone();
+    let mut rows = Vec::new();
+
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |p| p.line() as usize);
+
+        let mut row = Vec::new();
+        for (header, cell) in headers.iter().zip(record.iter()) {
+            if cell.is_empty() {
+                continue;
+            }
+            let segments = header.split('.').collect::<Vec<_>>();
+            if !insert(&mut row, &segments, parse_cell(cell)) {
+                Err(ConvertError::ConflictingHeader {
+                    line,
+                    header: header.to_string(),
+                })?
+            }
+        }
+        rows.push(unflatten(Value::Object(row)));
+    }
+
+    Ok(Value::Array(rows))
+}
+
+fn parse_cell(cell: &str) -> Value {
+    match cell {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "[]" => Value::Array(Vec::new()),
+        "{}" => Value::Object(Vec::new()),
+        _ if is_valid_number(cell) => to_number(cell)
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::String(cell.to_string())),
+        _ => Value::String(cell.to_string()),
+    }
+}
+
+/// Put `value` at `segments` below `members`, `false` if something is in the way.
+fn insert(members: &mut Vec<(String, Value)>, segments: &[&str], value: Value) -> bool {
+    let Some((first, rest)) = segments.split_first() else {
+        return false;
+    };
+    let existing = members.iter_mut().find(|(k, _)| k == first);
+
+    match (existing, rest.is_empty()) {
+        (None, true) => {
+            members.push((first.to_string(), value));
+            true
+        }
+        (None, false) => {
+            let mut nested = Vec::new();
+            let inserted = insert(&mut nested, rest, value);
+            members.push((first.to_string(), Value::Object(nested)));
+            inserted
+        }
+        (Some((_, Value::Object(nested))), false) => insert(nested, rest, value),
+        _ => false,
+    }
+}
+
+/// Turn objects keyed `0`, `1`, ... back into arrays.
+fn unflatten(value: Value) -> Value {
+    match value {
+        Value::Object(members) => {
+            let is_array = !members.is_empty()
+                && members
+                    .iter()
+                    .enumerate()
+                    .all(|(i, (k, _))| *k == i.to_string());
+            let members = members.into_iter().map(|(k, v)| (k, unflatten(v)));
+            if is_array {
+                Value::Array(members.map(|(_, v)| v).collect())
+            } else {
+                Value::Object(members.collect())
+            }
+        }
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_yaml() {
+        let value = json!({ "a": [1, 2.5, null], "b": { "c": "x", "d": true } });
+        let yaml = to_string(&value, Format::Yaml).unwrap();
+
+        assert_eq!(yaml, "a:\n- 1\n- 2.5\n- null\nb:\n  c: x\n  d: true\n");
+        assert_eq!(from_str(&yaml, Format::Yaml).unwrap(), value);
+        assert_eq!(
+            from_str("1: .inf", Format::Yaml).unwrap_err().to_string(),
+            r#"$["1"]: .inf can't be represented in JSON."#
+        );
+    }
+
+    #[test]
+    fn test_toml() {
+        let value = json!({ "name": "x", "server": { "ports": [80, 443] } });
+        let toml = to_string(&value, Format::Toml).unwrap();
+
+        assert_eq!(toml, "name = \"x\"\n\n[server]\nports = [80, 443]\n");
+        assert_eq!(from_str(&toml, Format::Toml).unwrap(), value);
+        assert_eq!(
+            to_string(&json!({ "a": [1, null] }), Format::Toml)
+                .unwrap_err()
+                .to_string(),
+            "$.a[1]: null can't be represented in TOML."
+        );
+        assert!(to_string(&json!([1]), Format::Toml).is_err());
+    }
+
+    #[test]
+    fn test_csv() {
+        let value = json!([
+            { "id": 1, "user": { "name": "a, b", "tags": ["x", "y"] } },
+            { "id": 2, "user": { "name": "c" }, "extra": null },
+        ]);
+        let csv = to_string(&value, Format::Csv).unwrap();
+
+        assert_eq!(
+            csv,
+            "id,user.name,user.tags.0,user.tags.1,extra\n1,\"a, b\",x,y,\n2,c,,,\n"
+        );
+        assert_eq!(
+            from_str(&csv, Format::Csv).unwrap(),
+            json!([
+                { "id": 1, "user": { "name": "a, b", "tags": ["x", "y"] } },
+                { "id": 2, "user": { "name": "c" } },
+            ])
+        );
+
+        assert!(to_string(&json!([{ "a.b": 1 }]), Format::Csv).is_err());
+        assert!(to_string(&json!([1]), Format::Csv).is_err());
+        assert!(from_str("a,a.b\n1,2\n", Format::Csv).is_err());
+    }
+
+    #[test]
+    fn test_binary() {
+        let value = json!({ "id": 7, "tags": ["a", "b"], "score": 0.5, "ok": null });
+        for format in [Format::Cbor, Format::MessagePack] {
+            let bytes = to_bytes(&value, format).unwrap();
+            assert!(bytes.len() < value.to_string().len());
+            assert_eq!(from_bytes(&bytes, format).unwrap(), value);
+        }
+        assert_eq!(
+            to_string(&value, Format::Cbor).unwrap_err().to_string(),
+            "CBOR is a binary format, convert it to or from bytes."
+        );
+        assert_eq!(from_bytes(b"[1]", Format::Json).unwrap(), json!([1]));
+    }
+}
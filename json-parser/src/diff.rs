@@ -0,0 +1,343 @@
+use crate::{
+    parser::Spans,
+    path::Path,
+    value::{Number, Value},
+};
+use std::collections::HashSet;
+
+/// How arrays are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ArrayMode {
+    /// Items are compared index by index.
+    #[default]
+    Ordered,
+    /// Order is ignored, each item is matched against an equal item on the other side.
+    Set,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiffOptions {
+    pub arrays: ArrayMode,
+    /// Numbers closer than this are considered equal.
+    pub tolerance: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A single difference between two documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub path: Path,
+    /// Value in the left document, `None` when added.
+    pub left: Option<Value>,
+    /// Value in the right document, `None` when removed.
+    pub right: Option<Value>,
+}
+
+/// One of the two compared documents, used to resolve line numbers in reports.
+pub struct Side<'a> {
+    pub name: &'a str,
+    pub spans: &'a Spans,
+}
+
+impl Side<'_> {
+    fn line(&self, path: &Path) -> Option<usize> {
+        self.spans.get(&path.pointer()).map(|span| span.start.line)
+    }
+}
+
+/// Semantic difference between `left` and `right`, ignoring formatting and key order.
+pub fn diff(left: &Value, right: &Value, options: &DiffOptions) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_values(left, right, &Path::root(), options, &mut changes);
+    changes
+}
+
+fn diff_values(
+    left: &Value,
+    right: &Value,
+    path: &Path,
+    options: &DiffOptions,
+    changes: &mut Vec<Change>,
+) {
+    match (left, right) {
+        (Value::Object(l), Value::Object(r)) => {
+            let mut seen = HashSet::new();
+            for (key, l_value) in l {
+                if !seen.insert(key) {
+                    continue;
+                }
+                match right.get(key) {
+                    Some(r_value) => {
+                        diff_values(l_value, r_value, &path.key(key), options, changes)
+                    }
+                    None => changes.push(removed(path.key(key), l_value)),
+                }
+            }
+            for (key, r_value) in r {
+                if left.get(key).is_none() && seen.insert(key) {
+                    changes.push(added(path.key(key), r_value));
+                }
+            }
+        }
+        (Value::Array(l), Value::Array(r)) => match options.arrays {
+            ArrayMode::Ordered => {
+                for (i, (l_item, r_item)) in l.iter().zip(r).enumerate() {
+                    diff_values(l_item, r_item, &path.index(i), options, changes);
+                }
+                for (i, item) in l.iter().enumerate().skip(r.len()) {
+                    changes.push(removed(path.index(i), item));
+                }
+                for (i, item) in r.iter().enumerate().skip(l.len()) {
+                    changes.push(added(path.index(i), item));
+                }
+            }
+            ArrayMode::Set => {
+                let mut matched = vec![false; r.len()];
+                for (i, l_item) in l.iter().enumerate() {
+                    let found = r
+                        .iter()
+                        .enumerate()
+                        .position(|(j, r_item)| !matched[j] && equal(l_item, r_item, options));
+                    match found {
+                        Some(j) => matched[j] = true,
+                        None => changes.push(removed(path.index(i), l_item)),
+                    }
+                }
+                for (j, r_item) in r.iter().enumerate() {
+                    if !matched[j] {
+                        changes.push(added(path.index(j), r_item));
+                    }
+                }
+            }
+        },
+        (l, r) if !equal(l, r, options) => changes.push(Change {
+            kind: ChangeKind::Changed,
+            path: path.clone(),
+            left: Some(l.clone()),
+            right: Some(r.clone()),
+        }),
+        _ => {}
+    }
+}
+
+/// Whether `left` and `right` are semantically equal under `options`.
+fn equal(left: &Value, right: &Value, options: &DiffOptions) -> bool {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => numbers_equal(l, r, options.tolerance),
+        (Value::Object(_), Value::Object(_)) | (Value::Array(_), Value::Array(_)) => {
+            let mut changes = Vec::new();
+            diff_values(left, right, &Path::root(), options, &mut changes);
+            changes.is_empty()
+        }
+        (l, r) => l == r,
+    }
+}
+
+fn numbers_equal(left: &Number, right: &Number, tolerance: f64) -> bool {
+    match (left, right) {
+        (Number::Integer(l), Number::Integer(r)) if tolerance == 0.0 => l == r,
+        (l, r) => (l.as_f64() - r.as_f64()).abs() <= tolerance,
+    }
+}
+
+fn added(path: Path, value: &Value) -> Change {
+    Change {
+        kind: ChangeKind::Added,
+        path,
+        left: None,
+        right: Some(value.clone()),
+    }
+}
+
+fn removed(path: Path, value: &Value) -> Change {
+    Change {
+        kind: ChangeKind::Removed,
+        path,
+        left: Some(value.clone()),
+        right: None,
+    }
+}
+
+/// Human readable report, one line per change.
+///
+/// ```text
+/// ~ $.name: "a" -> "b" (left.json:2, right.json:2)
+/// ```
+pub fn to_text(changes: &[Change], left: &Side, right: &Side) -> String {
+    let mut out = String::new();
+
+    for change in changes {
+        let location = |side: &Side| {
+            side.line(&change.path)
+                .map(|line| format!("{}:{line}", side.name))
+                .unwrap_or_else(|| side.name.to_string())
+        };
+
+        let line = match (&change.left, &change.right) {
+            (Some(l), Some(r)) => format!(
+                "~ {}: {l} -> {r} ({}, {})",
+                change.path,
+                location(left),
+                location(right)
+            ),
+            (Some(l), None) => format!("- {}: {l} ({})", change.path, location(left)),
+            (None, Some(r)) => format!("+ {}: {r} ({})", change.path, location(right)),
+            (None, None) => continue,
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Machine readable report.
+///
+/// `{"equal": false, "changes": [{"kind": "changed", "path": "$.a", "pointer": "/a",
+/// "left": {"value": 1, "line": 2}, "right": {"value": 2, "line": 2}}]}`
+pub fn to_json(changes: &[Change], left: &Side, right: &Side) -> Value {
+    let side = |side: &Side, value: &Option<Value>, path: &Path| match value {
+        Some(value) => {
+            let line = side
+                .line(path)
+                .map(|line| Value::Number(Number::Integer(line as i64)))
+                .unwrap_or(Value::Null);
+            Value::Object(vec![
+                (String::from("file"), Value::String(side.name.to_string())),
+                (String::from("value"), value.clone()),
+                (String::from("line"), line),
+            ])
+        }
+        None => Value::Null,
+    };
+
+    let changes = changes
+        .iter()
+        .map(|change| {
+            let kind = match change.kind {
+                ChangeKind::Added => "added",
+                ChangeKind::Removed => "removed",
+                ChangeKind::Changed => "changed",
+            };
+            Value::Object(vec![
+                (String::from("kind"), Value::String(kind.to_string())),
+                (String::from("path"), Value::String(change.path.to_string())),
+                (
+                    String::from("pointer"),
+                    Value::String(change.path.pointer()),
+                ),
+                (String::from("left"), side(left, &change.left, &change.path)),
+                (
+                    String::from("right"),
+                    side(right, &change.right, &change.path),
+                ),
+            ])
+        })
+        .collect::<Vec<_>>();
+
+    Value::Object(vec![
+        (String::from("equal"), Value::Bool(changes.is_empty())),
+        (String::from("changes"), Value::Array(changes)),
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn changes(left: &str, right: &str, options: DiffOptions) -> Vec<(ChangeKind, String)> {
+        let left = parse_str(left).unwrap();
+        let right = parse_str(right).unwrap();
+        diff(&left, &right, &options)
+            .into_iter()
+            .map(|c| (c.kind, c.path.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_ignores_whitespace_and_key_order() {
+        let left = "{\"a\": 1, \"b\": [true, null]}";
+        let right = "{\n  \"b\": [true,null],\n  \"a\": 1\n}";
+
+        assert!(changes(left, right, DiffOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_added_removed_changed() {
+        let left = r#"{"a": 1, "b": {"c": "x"}, "d": null}"#;
+        let right = r#"{"a": 2, "b": {"c": "x", "e": []}}"#;
+
+        assert_eq!(
+            changes(left, right, DiffOptions::default()),
+            vec![
+                (ChangeKind::Changed, String::from("$.a")),
+                (ChangeKind::Added, String::from("$.b.e")),
+                (ChangeKind::Removed, String::from("$.d")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_array_modes() {
+        let left = r#"{"a": [1, 2, 3]}"#;
+        let right = r#"{"a": [3, 1, 4]}"#;
+
+        assert_eq!(changes(left, right, DiffOptions::default()).len(), 3);
+
+        let options = DiffOptions {
+            arrays: ArrayMode::Set,
+            ..Default::default()
+        };
+        assert_eq!(
+            changes(left, right, options),
+            vec![
+                (ChangeKind::Removed, String::from("$.a[1]")),
+                (ChangeKind::Added, String::from("$.a[2]")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_numeric_tolerance() {
+        let left = r#"{"a": 1.0, "b": 10}"#;
+        let right = r#"{"a": 1.05, "b": 10.0}"#;
+
+        assert_eq!(changes(left, right, DiffOptions::default()).len(), 1);
+
+        let options = DiffOptions {
+            tolerance: 0.1,
+            ..Default::default()
+        };
+        assert!(changes(left, right, options).is_empty());
+    }
+
+    #[test]
+    fn test_text_report_lines() {
+        let (left, left_spans) = crate::parser::parse_str_with_spans("{\n\"a\": 1\n}").unwrap();
+        let (right, right_spans) =
+            crate::parser::parse_str_with_spans("{\n\n\"a\": 2,\n\"b\": 3\n}").unwrap();
+
+        let changes = diff(&left, &right, &DiffOptions::default());
+        let left = Side {
+            name: "l.json",
+            spans: &left_spans,
+        };
+        let right = Side {
+            name: "r.json",
+            spans: &right_spans,
+        };
+
+        assert_eq!(
+            to_text(&changes, &left, &right),
+            "~ $.a: 1 -> 2 (l.json:2, r.json:3)\n+ $.b: 3 (r.json:4)\n"
+        );
+    }
+}
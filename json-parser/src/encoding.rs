@@ -0,0 +1,228 @@
+//! Turning raw bytes into text.
+//!
+//! The encoding is taken from the byte order mark when there is one, otherwise it is
+//! guessed from where the zero bytes are, a JSON text always starts with an ASCII
+//! character (RFC 4627, section 3). The BOM is dropped either way.
+
+use crate::{
+    errors::{Error, ErrorKind},
+    token::Position,
+};
+use alloc::{borrow::Cow, string::String};
+use core::fmt;
+
+/// Encodings a JSON text can come in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Encoding {
+    /// Detect the encoding of `bytes`, along with the length of its BOM.
+    pub fn detect(bytes: &[u8]) -> (Self, usize) {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8, 3),
+            [0xFF, 0xFE, 0, 0, ..] => (Encoding::Utf32Le, 4),
+            [0, 0, 0xFE, 0xFF, ..] => (Encoding::Utf32Be, 4),
+            [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+            [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+            [0, 0, 0, b, ..] if *b != 0 => (Encoding::Utf32Be, 0),
+            [a, 0, 0, 0, ..] if *a != 0 => (Encoding::Utf32Le, 0),
+            [0, b, ..] if *b != 0 => (Encoding::Utf16Be, 0),
+            [a, 0, ..] if *a != 0 => (Encoding::Utf16Le, 0),
+            _ => (Encoding::Utf8, 0),
+        }
+    }
+
+    /// Bytes per code unit.
+    fn unit(&self) -> usize {
+        match self {
+            Encoding::Utf8 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf32Le | Encoding::Utf32Be => 4,
+        }
+    }
+}
+
+/// Decode `bytes`, invalid sequences are reported with their position.
+///
+/// UTF-8 input without a BOM is borrowed as is.
+pub fn decode(bytes: &[u8]) -> Result<Cow<'_, str>, Error> {
+    decode_bytes(bytes, false)
+}
+
+/// Decode `bytes`, replacing invalid sequences with U+FFFD.
+pub fn decode_lossy(bytes: &[u8]) -> Cow<'_, str> {
+    // Nothing fails when replacing.
+    decode_bytes(bytes, true).unwrap_or_default()
+}
+
+pub(crate) fn decode_bytes(bytes: &[u8], lossy: bool) -> Result<Cow<'_, str>, Error> {
+    let (encoding, bom) = Encoding::detect(bytes);
+    let body = bytes.get(bom..).unwrap_or_default();
+
+    if encoding == Encoding::Utf8 {
+        return match core::str::from_utf8(body) {
+            Ok(text) => Ok(Cow::Borrowed(text)),
+            Err(_) if lossy => Ok(String::from_utf8_lossy(body)),
+            Err(e) => {
+                let mut position = Position::at(body, e.valid_up_to());
+                position.offset += bom;
+                Err(Error::at(ErrorKind::InvalidEncoding { encoding }, position))
+            }
+        };
+    }
+
+    let mut text = String::with_capacity(body.len() / encoding.unit());
+    let mut offset = bom;
+    let invalid = |offset: usize, text: &mut String| {
+        if lossy {
+            text.push(char::REPLACEMENT_CHARACTER);
+            Ok(())
+        } else {
+            // Lines and columns of what was decoded so far, the offset in raw bytes.
+            let mut position = Position::at(text.as_bytes(), text.len());
+            position.offset = offset;
+            Err(Error::at(ErrorKind::InvalidEncoding { encoding }, position))
+        }
+    };
+
+    let whole = body.len() - body.len() % encoding.unit();
+    let units = body
+        .get(..whole)
+        .unwrap_or_default()
+        .chunks_exact(encoding.unit())
+        .map(|chunk| match *chunk {
+            [a, b] if encoding == Encoding::Utf16Le => u16::from_le_bytes([a, b]) as u32,
+            [a, b] => u16::from_be_bytes([a, b]) as u32,
+            [a, b, c, d] if encoding == Encoding::Utf32Le => u32::from_le_bytes([a, b, c, d]),
+            [a, b, c, d] => u32::from_be_bytes([a, b, c, d]),
+            _ => u32::MAX,
+        });
+
+    match encoding {
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            for c in char::decode_utf16(units.map(|unit| unit as u16)) {
+                match c {
+                    Ok(c) => {
+                        text.push(c);
+                        offset += 2 * c.len_utf16();
+                    }
+                    Err(_) => {
+                        invalid(offset, &mut text)?;
+                        offset += 2;
+                    }
+                }
+            }
+        }
+        _ => {
+            for unit in units {
+                match char::from_u32(unit) {
+                    Some(c) => text.push(c),
+                    None => invalid(offset, &mut text)?,
+                }
+                offset += 4;
+            }
+        }
+    }
+
+    // Truncated code unit at the end.
+    if whole < body.len() {
+        invalid(offset, &mut text)?;
+    }
+
+    Ok(Cow::Owned(text))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn utf16le(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn utf32be(s: &str) -> Vec<u8> {
+        s.chars().flat_map(|c| (c as u32).to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn test_detect() {
+        let input = r#"{"é": ["😀"]}"#;
+
+        let mut bom = vec![0xEF, 0xBB, 0xBF];
+        bom.extend_from_slice(input.as_bytes());
+        assert_eq!(decode(&bom).unwrap(), input);
+        assert!(matches!(
+            decode(input.as_bytes()).unwrap(),
+            Cow::Borrowed(_)
+        ));
+
+        assert_eq!(decode(&utf16le(input)).unwrap(), input);
+        assert_eq!(
+            decode(&utf16le(&format!("\u{FEFF}{input}"))).unwrap(),
+            input
+        );
+        assert_eq!(decode(&utf32be(input)).unwrap(), input);
+        assert_eq!(
+            decode(&utf32be(&format!("\u{FEFF}{input}"))).unwrap(),
+            input
+        );
+        assert_eq!(decode(&utf16le("1")).unwrap(), "1");
+    }
+
+    #[test]
+    fn test_invalid() {
+        let input = b"[\n\"ok\",\n\"\xC3\x28\"]";
+        let err = decode_bytes(input, false).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ErrorKind::InvalidEncoding {
+                encoding: Encoding::Utf8
+            }
+        );
+        assert_eq!(
+            err.span().start,
+            Position {
+                line: 3,
+                column: 2,
+                offset: 9
+            }
+        );
+        assert_eq!(decode_lossy(input), "[\n\"ok\",\n\"\u{FFFD}(\"]");
+
+        // Lone surrogate, then a truncated code unit.
+        let mut input = utf16le("[\n\"");
+        input.extend_from_slice(&[0x00, 0xD8]);
+        input.extend(utf16le("\"]"));
+        input.push(b' ');
+        let err = decode_bytes(&input, false).unwrap_err();
+        assert_eq!(err.kind().name(), "invalid-encoding");
+        assert_eq!(
+            err.span().start,
+            Position {
+                line: 2,
+                column: 2,
+                offset: 6
+            }
+        );
+        assert_eq!(decode_lossy(&input), "[\n\"\u{FFFD}\"]\u{FFFD}");
+    }
+}
@@ -1,41 +1,510 @@
-use thiserror::Error;
+//! Errors reported for invalid documents.
+//!
+//! Every `ErrorKind` has a code and a name that never change once released, tools
+//! can match on them rather than on the message, which may be reworded.
 
-#[derive(Error, Debug)]
-pub(crate) enum LexError {
-    #[error("Empty JSON file is invalid.")]
+use crate::{
+    encoding::Encoding,
+    token::{Position, Span},
+    value::Value,
+};
+use alloc::{
+    string::{String, ToString},
+    vec,
+};
+use core::fmt;
+
+/// Why a document was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Nothing but whitespace.
     EmptyFile,
+    /// A char that can't start any token.
+    UnexpectedToken {
+        token: char,
+    },
+    /// A comma right before a closing bracket or brace.
+    TrailingComma,
+    /// The input ended inside an object.
+    UnclosedObject,
+    /// A token where the grammar expects something else.
+    InvalidSyntax {
+        expected: String,
+    },
+    /// A misspelled `null`, `true` or `false`.
+    InvalidKeyword {
+        keyword: String,
+        maybe: String,
+    },
+    /// A newline inside a string.
+    MissingClosingQuote,
+    /// The input ended inside a string.
+    UnterminatedString,
+    InvalidNumber {
+        number: String,
+    },
+    /// A number too large for an `f64`.
+    NumberOutOfRange {
+        number: String,
+    },
+    /// A control char other than newline inside a string.
+    ControlCharacter {
+        token: char,
+    },
+    /// The input ended inside an array.
+    UnclosedArray,
+    TooDeep {
+        max: usize,
+    },
+    /// Bytes that aren't valid in the detected encoding, see `encoding::decode`.
+    InvalidEncoding {
+        encoding: Encoding,
+    },
+    /// A value outgrew the limit set with `incremental::Feeder::with_max_len`.
+    TooLarge {
+        max: usize,
+    },
+}
 
-    #[error("[line {line:?}] Trailing Comma.")]
-    TrailingComa { line: i16 },
+impl ErrorKind {
+    /// Stable code, e.g. `E0003`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::EmptyFile => "E0001",
+            ErrorKind::UnexpectedToken { .. } => "E0002",
+            ErrorKind::TrailingComma => "E0003",
+            ErrorKind::UnclosedObject => "E0004",
+            ErrorKind::InvalidSyntax { .. } => "E0005",
+            ErrorKind::InvalidKeyword { .. } => "E0006",
+            ErrorKind::MissingClosingQuote => "E0007",
+            ErrorKind::UnterminatedString => "E0008",
+            ErrorKind::InvalidNumber { .. } => "E0009",
+            ErrorKind::NumberOutOfRange { .. } => "E0010",
+            ErrorKind::ControlCharacter { .. } => "E0011",
+            ErrorKind::UnclosedArray => "E0012",
+            ErrorKind::TooDeep { .. } => "E0013",
+            ErrorKind::InvalidEncoding { .. } => "E0014",
+            ErrorKind::TooLarge { .. } => "E0015",
+        }
+    }
 
-    #[error("Expected opening curly braces '{{'")]
-    OpeningCurly,
+    /// Stable name going along with the code, e.g. `trailing-comma`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::EmptyFile => "empty-file",
+            ErrorKind::UnexpectedToken { .. } => "unexpected-token",
+            ErrorKind::TrailingComma => "trailing-comma",
+            ErrorKind::UnclosedObject => "unclosed-object",
+            ErrorKind::InvalidSyntax { .. } => "invalid-syntax",
+            ErrorKind::InvalidKeyword { .. } => "invalid-keyword",
+            ErrorKind::MissingClosingQuote => "missing-closing-quote",
+            ErrorKind::UnterminatedString => "unterminated-string",
+            ErrorKind::InvalidNumber { .. } => "invalid-number",
+            ErrorKind::NumberOutOfRange { .. } => "number-out-of-range",
+            ErrorKind::ControlCharacter { .. } => "control-character",
+            ErrorKind::UnclosedArray => "unclosed-array",
+            ErrorKind::TooDeep { .. } => "too-deep",
+            ErrorKind::InvalidEncoding { .. } => "invalid-encoding",
+            ErrorKind::TooLarge { .. } => "too-large",
+        }
+    }
+}
 
-    #[error("Expected closing curly braces '}}'")]
-    ClosingCurly,
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::EmptyFile => write!(f, "Empty JSON file is invalid."),
+            ErrorKind::UnexpectedToken { token } => write!(f, "Unexpected token {token:?}"),
+            ErrorKind::TrailingComma => write!(f, "Trailing Comma."),
+            ErrorKind::UnclosedObject => write!(f, "Expected closing curly braces '}}'"),
+            ErrorKind::InvalidSyntax { expected } => {
+                write!(f, "Invalid Syntax, Expected {expected:?}.")
+            }
+            ErrorKind::InvalidKeyword { keyword, maybe } => {
+                write!(f, "Invalid keyword {keyword:?}, Maybe you mean {maybe:?}.")
+            }
+            ErrorKind::MissingClosingQuote => write!(f, "Missing closing quote."),
+            ErrorKind::UnterminatedString => write!(f, "Unterminated string literal."),
+            ErrorKind::InvalidNumber { number } => write!(f, "Invalid number {number:?}."),
+            ErrorKind::NumberOutOfRange { number } => {
+                write!(f, "Number {number:?} is out of range.")
+            }
+            ErrorKind::ControlCharacter { token } => {
+                write!(f, "Unescaped control character {token:?} in string.")
+            }
+            ErrorKind::UnclosedArray => write!(f, "Unterminated Array, Expected ]."),
+            ErrorKind::TooDeep { max } => write!(f, "Nesting is deeper than {max:?} levels."),
+            ErrorKind::InvalidEncoding { encoding } => write!(f, "Invalid {encoding}."),
+            ErrorKind::TooLarge { max } => write!(f, "Value is larger than {max:?} bytes."),
+        }
+    }
+}
 
-    #[error("[line {line:?}] Unexpected token {token:?}")]
-    UnexpectedToken { line: i16, token: char },
+/// An invalid document, along with where the problem is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    span: Span,
+}
 
-    #[error("[line {line:?}] Invalid Syntax, Expected {expected:?}.")]
-    InvalidSyntax { line: i16, expected: String },
+impl Error {
+    pub(crate) fn new(kind: ErrorKind, span: Span) -> Self {
+        Error { kind, span }
+    }
 
-    #[error("[line {line:?}] Invalid keyword {keyword:?}, Maybe you mean {maybe:?}.")]
-    InvalidKeyword {
-        line: i16,
-        keyword: String,
-        maybe: String,
+    /// An error pointing between two chars rather than at a region.
+    pub(crate) fn at(kind: ErrorKind, position: Position) -> Self {
+        Self::new(
+            kind,
+            Span {
+                start: position,
+                end: position,
+            },
+        )
+    }
+
+    /// The same error for a document starting at `origin` rather than at the start
+    /// of the input.
+    pub(crate) fn shifted(self, origin: Position) -> Self {
+        let shift = |mut p: Position| {
+            if p.line == 1 {
+                p.column += origin.column - 1;
+            }
+            p.line += origin.line - 1;
+            p.offset += origin.offset;
+            p
+        };
+        let span = Span {
+            start: shift(self.span.start),
+            end: shift(self.span.end),
+        };
+        Self::new(self.kind, span)
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Source text the error is about, empty when it is about a single place, e.g. the
+    /// end of input. Offsets of `InvalidEncoding` count bytes of the raw input.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Line the error starts on.
+    pub fn line(&self) -> usize {
+        self.span.start.line
+    }
+
+    /// Code, name, message and span, for tools reading the output of the cli.
+    pub fn to_json(&self) -> Value {
+        let position = |p: Position| {
+            Value::Object(vec![
+                (String::from("line"), Value::from(p.line)),
+                (String::from("column"), Value::from(p.column)),
+                (String::from("offset"), Value::from(p.offset)),
+            ])
+        };
+
+        Value::Object(vec![
+            (String::from("code"), Value::from(self.kind.code())),
+            (String::from("name"), Value::from(self.kind.name())),
+            (String::from("message"), Value::from(self.kind.to_string())),
+            (
+                String::from("span"),
+                Value::Object(vec![
+                    (String::from("start"), position(self.span.start)),
+                    (String::from("end"), position(self.span.end)),
+                ]),
+            ),
+        ])
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Position { line, column, .. } = self.span.start;
+        write!(
+            f,
+            "[line {line}, column {column}] {} {}: {}",
+            self.kind.code(),
+            self.kind.name(),
+            self.kind
+        )
+    }
+}
+
+impl core::error::Error for Error {}
+
+#[cfg(feature = "std")]
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ConvertError {
+    #[error("{path}: {what} can't be represented in {format}.")]
+    Unrepresentable {
+        path: String,
+        what: String,
+        format: &'static str,
+    },
+
+    #[error("{path}: duplicate key {key:?} isn't allowed in {format}.")]
+    DuplicateKey {
+        path: String,
+        key: String,
+        format: &'static str,
     },
 
-    #[error("[line {line:?}] Missing closing quote.")]
-    MissingClosingQuote { line: i16 },
+    #[error("{path}: key {key:?} contains a '.', which is used to flatten CSV headers.")]
+    DottedKey { path: String, key: String },
+
+    #[error("[line {line:?}] Column {header:?} conflicts with another column.")]
+    ConflictingHeader { line: usize, header: String },
+
+    #[error("{format} is a binary format, convert it to or from bytes.")]
+    Binary { format: &'static str },
+}
+
+/// Malformed CBOR or MessagePack input.
+#[cfg(feature = "std")]
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum BinaryError {
+    #[error("[offset {offset:?}] Unexpected end of {format} data.")]
+    UnexpectedEnd { offset: usize, format: &'static str },
+
+    #[error("[offset {offset:?}] Invalid {format} data, {reason}.")]
+    Invalid {
+        offset: usize,
+        format: &'static str,
+        reason: String,
+    },
+
+    #[error("[offset {offset:?}] Nesting is deeper than {max:?} levels.")]
+    TooDeep { offset: usize, max: usize },
+
+    #[error("[offset {offset:?}] Trailing bytes after the {format} data.")]
+    TrailingBytes { offset: usize, format: &'static str },
+}
+
+/// Malformed `unflatten` input.
+#[cfg(feature = "std")]
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum GronError {
+    #[error("[line {line:?}, column {column:?}] Invalid Syntax, Expected {expected}.")]
+    Syntax {
+        line: usize,
+        column: usize,
+        expected: &'static str,
+    },
+
+    #[error("[line {line:?}] Cannot set {path}, {parent} is {found}.")]
+    Conflict {
+        line: usize,
+        path: String,
+        parent: String,
+        found: &'static str,
+    },
+
+    #[error("[line {line:?}] Nesting is deeper than {max:?} levels.")]
+    TooDeep { line: usize, max: usize },
+
+    #[error("[line {line:?}] Filling gaps in arrays takes more than {max:?} nulls.")]
+    TooSparse { line: usize, max: usize },
+
+    #[error("No assignments to rebuild a document from.")]
+    Empty,
+}
+
+/// Invalid redaction rules, or a redaction that can't be applied.
+#[cfg(feature = "std")]
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum RedactError {
+    #[error("{path}: {reason}.")]
+    InvalidRule { path: String, reason: String },
+
+    #[error("[column {column:?}] Invalid JSONPath {path:?}, Expected {expected}.")]
+    InvalidPath {
+        path: String,
+        column: usize,
+        expected: &'static str,
+    },
+
+    #[error("The root of the document can't be removed.")]
+    RemoveRoot,
+}
+
+/// Invalid `.jsonlint.json` configuration.
+#[cfg(feature = "std")]
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum LintError {
+    #[error("{path}: {reason}.")]
+    InvalidConfig { path: String, reason: String },
+}
+
+#[cfg(feature = "std")]
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum JqError {
+    #[error("[column {column:?}] Invalid Syntax, Expected {expected}, found {found}.")]
+    Syntax {
+        column: usize,
+        expected: String,
+        found: String,
+    },
+
+    #[error("[column {column:?}] {name}/{arity} is not defined.")]
+    UnknownFunction {
+        column: usize,
+        name: String,
+        arity: usize,
+    },
+
+    #[error("[column {column:?}] ${name} is not defined.")]
+    UndefinedVariable { column: usize, name: String },
+
+    #[error("[column {column:?}] Nesting is deeper than {max:?} levels.")]
+    TooDeep { column: usize, max: usize },
+
+    #[error("Cannot index {target} with {key}.")]
+    Index { target: String, key: String },
+
+    #[error("Cannot iterate over {target}.")]
+    Iterate { target: String },
+
+    #[error("Object keys must be strings, got {key}.")]
+    ObjectKey { key: String },
+
+    #[error("{left} and {right} cannot be {op}.")]
+    Operands {
+        left: String,
+        right: String,
+        op: &'static str,
+    },
+
+    #[error("{left} and {right} cannot be divided because the divisor is zero.")]
+    DivisionByZero { left: String, right: String },
+
+    #[error("{function} would output more than {max:?} values.")]
+    TooMany { function: &'static str, max: usize },
+
+    #[error("{function} is not defined for {value}.")]
+    Unsupported {
+        function: &'static str,
+        value: String,
+    },
+
+    /// Raised by `error`, carries any value.
+    #[error("{}", raised_message(.0))]
+    Raised(Value),
+}
+
+#[cfg(feature = "std")]
+impl JqError {
+    /// What `catch` receives, the raised value or the message.
+    pub(crate) fn value(&self) -> Value {
+        match self {
+            JqError::Raised(value) => value.clone(),
+            e => Value::String(e.to_string()),
+        }
+    }
+}
+
+/// Failures answered with a JSON-RPC error response by the language server.
+#[cfg(feature = "std")]
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum LspError {
+    #[error("Invalid Message, {reason}.")]
+    Parse { reason: String },
+
+    #[error("Invalid Request, {reason}.")]
+    InvalidRequest { reason: String },
+
+    #[error("Method Not Found, {method}.")]
+    MethodNotFound { method: String },
+
+    #[error("Invalid Params, {reason}.")]
+    InvalidParams { reason: String },
+
+    #[error("Server Not Initialized.")]
+    NotInitialized,
+}
+
+#[cfg(feature = "std")]
+impl LspError {
+    /// The JSON-RPC error code.
+    pub(crate) fn code(&self) -> i64 {
+        match self {
+            LspError::Parse { .. } => -32700,
+            LspError::InvalidRequest { .. } => -32600,
+            LspError::MethodNotFound { .. } => -32601,
+            LspError::InvalidParams { .. } => -32602,
+            LspError::NotInitialized => -32002,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn raised_message(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => format!("{value} (not a string)"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{json, parse};
 
-    #[error("[line {line:?}] Unterminated string literal.")]
-    UnterminatedString { line: i16 },
+    #[test]
+    fn test_kinds() {
+        let cases = [
+            ("", "E0001 empty-file", (1, 1), (1, 1)),
+            ("[1] @", "E0002 unexpected-token", (1, 5), (1, 6)),
+            ("[1,\n 2,\n]", "E0003 trailing-comma", (2, 3), (2, 4)),
+            ("{\"a\": 1", "E0004 unclosed-object", (1, 8), (1, 8)),
+            ("[1 2]", "E0005 invalid-syntax", (1, 4), (1, 5)),
+            ("[nul]", "E0006 invalid-keyword", (1, 2), (1, 5)),
+            ("\"ab\ncd\"", "E0007 missing-closing-quote", (1, 1), (1, 4)),
+            ("[\"ab", "E0008 unterminated-string", (1, 2), (1, 5)),
+            ("01", "E0009 invalid-number", (1, 1), (1, 3)),
+            ("1e999", "E0010 number-out-of-range", (1, 1), (1, 6)),
+            ("\"a\tb\"", "E0011 control-character", (1, 3), (1, 4)),
+            ("[1, 2", "E0012 unclosed-array", (1, 6), (1, 6)),
+            (&"[".repeat(129), "E0013 too-deep", (1, 129), (1, 130)),
+        ];
 
-    #[error("[line {line:?}] Unterminated number.")]
-    UnterminatedNumber { line: i16 },
+        for (input, code, start, end) in cases {
+            let err = parse(input).unwrap_err();
+            let kind = err.kind();
+            assert_eq!(
+                format!("{} {}", kind.code(), kind.name()),
+                code,
+                "{input:?}"
+            );
+            let span = err.span();
+            assert_eq!((span.start.line, span.start.column), start, "{input:?}");
+            assert_eq!((span.end.line, span.end.column), end, "{input:?}");
+        }
+    }
 
-    #[error("[line {line:?}] Unterminated Array, Expected ].")]
-    UnterminatedArray { line: i16 },
+    #[test]
+    fn test_to_json() {
+        let err = parse("[\n  1,]").unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::TrailingComma);
+        assert_eq!(
+            err.to_string(),
+            "[line 2, column 4] E0003 trailing-comma: Trailing Comma."
+        );
+        assert_eq!(
+            err.to_json(),
+            json!({
+                "code": "E0003",
+                "name": "trailing-comma",
+                "message": "Trailing Comma.",
+                "span": {
+                    "start": { "line": 2, "column": 4, "offset": 5 },
+                    "end": { "line": 2, "column": 5, "offset": 6 },
+                },
+            })
+        );
+    }
 }
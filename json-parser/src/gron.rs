@@ -0,0 +1,345 @@
+//! One assignment per line, like [gron](https://github.com/tomnomnom/gron), so
+//! documents can be searched with `grep` and compared with line based diffs.
+//!
+//! ```text
+//! json = {};
+//! json.items = [];
+//! json.items[0] = {};
+//! json.items[0].name = "x";
+//! json.items[0]["odd key"] = null;
+//! ```
+//!
+//! Keys that aren't identifiers are written as JSON strings in brackets, so any
+//! key, newlines and quotes included, survives `unflatten`. Containers are assigned
+//! before their members, which keeps empty ones and the order of keys intact. A
+//! later assignment to the same path replaces the value, so objects with duplicate
+//! keys are refused rather than losing all but the last.
+
+use crate::{
+    convert::check_unique,
+    errors::GronError,
+    lexer::Lexer,
+    parser::MAX_DEPTH,
+    path::{Path, Segment},
+    token::Token,
+    value::Value,
+};
+use anyhow::{Context, Result};
+
+/// Name of the root in every path.
+const ROOT: &str = "json";
+
+/// How many `null`s may be added to fill gaps in arrays, so `json[99999999999]`
+/// can't exhaust memory.
+const MAX_PADDING: usize = 1 << 20;
+
+/// Every value of the document as a `path = value;` line, containers as `{}` and `[]`.
+pub fn flatten(value: &Value) -> Result<String> {
+    let mut out = String::new();
+    write(value, &Path::root(), &mut out)?;
+    Ok(out)
+}
+
+fn write(value: &Value, path: &Path, out: &mut String) -> Result<()> {
+    let rendered = match value {
+        Value::Array(_) => String::from("[]"),
+        Value::Object(_) => String::from("{}"),
+        scalar => scalar.to_string(),
+    };
+    out.push_str(&format!("{} = {rendered};\n", render(path)));
+
+    match value {
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                write(item, &path.index(i), out)?;
+            }
+        }
+        Value::Object(members) => {
+            check_unique(members, path, "gron")?;
+            for (key, member) in members {
+                write(member, &path.key(key), out)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// `path` with the root named `json`, e.g. `json.items[0]["odd key"]`.
+fn render(path: &Path) -> String {
+    path.to_string().replacen('$', ROOT, 1)
+}
+
+/// Rebuild a document from assignments, in the format `flatten` prints.
+///
+/// Assignments may come in any order and with gaps, as left by `grep`: missing
+/// containers are created along the way, and arrays are padded with `null`.
+pub fn unflatten(input: &str) -> Result<Value> {
+    let mut root = None;
+    let mut padding = 0;
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let number = i + 1;
+        let (path, value) = statement(line, number)?;
+        set(&mut root, &path, value, number, &mut padding)?;
+    }
+    Ok(root.ok_or(GronError::Empty)?)
+}
+
+/// Split a line into the path and the value assigned to it.
+fn statement(line: &str, number: usize) -> Result<(Path, Value)> {
+    let mut cursor = Cursor {
+        line,
+        rest: line.trim_start(),
+        number,
+    };
+    if !cursor.eat(ROOT) {
+        Err(cursor.expected("json"))?
+    }
+
+    let mut path = Path::root();
+    loop {
+        if cursor.eat(".") {
+            let start = cursor.rest;
+            let ident = cursor.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+            if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+                cursor.rest = start;
+                Err(cursor.expected("a key"))?
+            }
+            path = path.key(ident);
+        } else if cursor.eat("[") {
+            path = match cursor.rest.starts_with('"') {
+                true => path.key(&cursor.string()?),
+                false => {
+                    let digits = cursor.take_while(|c| c.is_ascii_digit());
+                    match digits.parse() {
+                        Ok(index) => path.index(index),
+                        Err(_) => Err(cursor.expected("an index or a string"))?,
+                    }
+                }
+            };
+            if !cursor.eat("]") {
+                Err(cursor.expected("']'"))?
+            }
+        } else {
+            break;
+        }
+        if path.segments().len() > MAX_DEPTH {
+            Err(GronError::TooDeep {
+                line: number,
+                max: MAX_DEPTH,
+            })?
+        }
+    }
+
+    cursor.rest = cursor.rest.trim_start();
+    if !cursor.eat("=") {
+        Err(cursor.expected("'.', '[' or '='"))?
+    }
+    let Some(value) = cursor.rest.trim_end().strip_suffix(';') else {
+        cursor.rest = "";
+        Err(cursor.expected("';'"))?
+    };
+    let value = crate::parse(value).with_context(|| format!("[line {number}] Invalid value"))?;
+    Ok((path, value))
+}
+
+struct Cursor<'a> {
+    line: &'a str,
+    /// What is left of `line`.
+    rest: &'a str,
+    number: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn eat(&mut self, prefix: &str) -> bool {
+        match self.rest.strip_prefix(prefix) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let end = self.rest.find(|c| !f(c)).unwrap_or(self.rest.len());
+        let (taken, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        taken
+    }
+
+    /// A key in brackets, escaped like a JSON string.
+    fn string(&mut self) -> Result<String> {
+        match Lexer::new(self.rest).next_token() {
+            Ok(Some((Token::Literal(key), span))) => {
+                self.rest = self.rest.get(span.end.offset..).unwrap_or_default();
+                Ok(key.into_owned())
+            }
+            _ => Err(self.expected("a string"))?,
+        }
+    }
+
+    fn expected(&self, expected: &'static str) -> GronError {
+        let consumed = self.line.len() - self.rest.len();
+        GronError::Syntax {
+            line: self.number,
+            column: self
+                .line
+                .get(..consumed)
+                .unwrap_or_default()
+                .chars()
+                .count()
+                + 1,
+            expected,
+        }
+    }
+}
+
+/// Assign `value` at `path`, creating the containers leading to it.
+fn set(
+    root: &mut Option<Value>,
+    path: &Path,
+    value: Value,
+    line: usize,
+    padding: &mut usize,
+) -> Result<()> {
+    let segments = path.segments();
+    let mut slot = root.get_or_insert(Value::Null);
+    let mut parent = Path::root();
+
+    for (i, segment) in segments.iter().enumerate() {
+        if *slot == Value::Null {
+            *slot = match segment {
+                Segment::Key(_) => Value::Object(Vec::new()),
+                Segment::Index(_) => Value::Array(Vec::new()),
+            };
+        }
+        slot = match (slot, segment) {
+            (Value::Object(members), Segment::Key(key)) => {
+                let position = match members.iter().position(|(k, _)| k == key) {
+                    Some(position) => position,
+                    None => {
+                        members.push((key.clone(), Value::Null));
+                        members.len() - 1
+                    }
+                };
+                &mut members[position].1
+            }
+            (Value::Array(items), Segment::Index(index)) => {
+                if *index >= items.len() {
+                    *padding += index - items.len();
+                    if *padding > MAX_PADDING {
+                        Err(GronError::TooSparse {
+                            line,
+                            max: MAX_PADDING,
+                        })?
+                    }
+                    items.resize(index + 1, Value::Null);
+                }
+                &mut items[*index]
+            }
+            (slot, _) => Err(GronError::Conflict {
+                line,
+                path: render(path),
+                parent: render(&parent),
+                found: match slot {
+                    Value::Array(_) => "an array",
+                    Value::Object(_) => "an object",
+                    _ => "a scalar",
+                },
+            })?,
+        };
+        if i + 1 < segments.len() {
+            parent = parent.join(segment.clone());
+        }
+    }
+
+    *slot = value;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_flatten() {
+        let value = json!({
+            "items": [{ "name": "x", "tags": [] }],
+            "odd key": { "a\"b\n": 1.5, "": null, "1": true },
+        });
+        let flat = flatten(&value).unwrap();
+        assert_eq!(
+            flat,
+            r#"json = {};
+json.items = [];
+json.items[0] = {};
+json.items[0].name = "x";
+json.items[0].tags = [];
+json["odd key"] = {};
+json["odd key"]["a\"b\n"] = 1.5;
+json["odd key"][""] = null;
+json["odd key"]["1"] = true;
+"#
+        );
+        assert_eq!(unflatten(&flat).unwrap(), value);
+        assert_eq!(
+            unflatten(&flatten(&json!("x;")).unwrap()).unwrap(),
+            json!("x;")
+        );
+
+        // Unflattening would keep only the last one.
+        let value = crate::parse("{\"a\": [{\"b\": 1, \"b\": 2}]}").unwrap();
+        assert_eq!(
+            flatten(&value).unwrap_err().to_string(),
+            "$.a[0]: duplicate key \"b\" isn't allowed in gron."
+        );
+    }
+
+    #[test]
+    fn test_unflatten() {
+        // Lines picked out by grep, without the containers leading to them.
+        let value = unflatten("json.a[2].b = 1;\njson.c[\"]\"] = \"=\";\n").unwrap();
+        assert_eq!(
+            value,
+            json!({ "a": [null, null, { "b": 1 }], "c": { "]": "=" } })
+        );
+
+        let err = |input: &str| unflatten(input).unwrap_err().to_string();
+        assert_eq!(err(""), "No assignments to rebuild a document from.");
+        assert_eq!(
+            err("data.a = 1;"),
+            "[line 1, column 1] Invalid Syntax, Expected json."
+        );
+        assert_eq!(
+            err("json.1 = 1;"),
+            "[line 1, column 6] Invalid Syntax, Expected a key."
+        );
+        assert_eq!(
+            err("json[x] = 1;"),
+            "[line 1, column 6] Invalid Syntax, Expected an index or a string."
+        );
+        assert_eq!(
+            err("json.a = 1"),
+            "[line 1, column 11] Invalid Syntax, Expected ';'."
+        );
+        assert_eq!(
+            err("json = [];\njson.a = 1;"),
+            "[line 2] Cannot set json.a, json is an array."
+        );
+        assert_eq!(
+            err("json.a = 1;\njson.a.b[0] = 1;"),
+            "[line 2] Cannot set json.a.b[0], json.a is a scalar."
+        );
+        assert_eq!(err("json.a = {;"), "[line 1] Invalid value");
+        assert!(err(&format!("json{}", "[0]".repeat(200))).contains("deeper"));
+        assert_eq!(
+            err("json[99999999999] = 1;"),
+            "[line 1] Filling gaps in arrays takes more than 1048576 nulls."
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Parsing documents as their bytes arrive, e.g. from a socket.
+//!
+//! A `Feeder` keeps the bytes of the document it is waiting for and scans each byte
+//! once, tracking strings and nesting, to tell when it is complete. Only then is it
+//! parsed, so a body split over any number of chunks is parsed a single time.
+//! Documents may follow each other, one per line as in NDJSON or just concatenated.
+//!
+//! Input must be UTF-8. Error positions count from the start of the stream.
+
+use crate::{
+    errors::{Error, ErrorKind},
+    token::Position,
+    value::Value,
+};
+use alloc::vec::Vec;
+
+/// Incremental parser, see the module documentation.
+///
+/// ```
+/// use json_parser::incremental::Feeder;
+///
+/// let mut feeder = Feeder::new();
+/// feeder.feed(br#"{"a": [1, "#);
+/// assert_eq!(feeder.next_value()?, None);
+/// feeder.feed(br#"2]}"#);
+/// assert!(feeder.next_value()?.is_some());
+/// # Ok::<(), json_parser::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Feeder {
+    buffer: Vec<u8>,
+    /// Bytes of `buffer` already scanned.
+    scanned: usize,
+    /// Bytes of `buffer` no longer needed, dropped on the next `feed`.
+    consumed: usize,
+    /// Where the document being scanned starts in `buffer`, and in the stream.
+    start: Option<(usize, Position)>,
+    /// Position of `buffer[scanned]` in the stream.
+    position: Position,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// Numbers and keywords only end at the next delimiter.
+    in_scalar: bool,
+    max_len: Option<usize>,
+    /// The document is over `max_len`, its bytes are dropped until it ends.
+    skipping: bool,
+}
+
+impl Default for Feeder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Feeder {
+    pub fn new() -> Self {
+        Feeder {
+            buffer: Vec::new(),
+            scanned: 0,
+            consumed: 0,
+            start: None,
+            position: Position {
+                line: 1,
+                column: 1,
+                offset: 0,
+            },
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            in_scalar: false,
+            max_len: None,
+            skipping: false,
+        }
+    }
+
+    /// Fail with `ErrorKind::TooLarge` rather than buffer documents longer than
+    /// `max_len` bytes.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Add the next chunk of the stream.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if self.consumed > 0 {
+            self.buffer.drain(..self.consumed);
+            self.scanned -= self.consumed;
+            if let Some((start, _)) = self.start.as_mut() {
+                *start = start.saturating_sub(self.consumed);
+            }
+            self.consumed = 0;
+        }
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// The next complete document, `None` while more input is needed.
+    ///
+    /// An invalid document is skipped after its error is returned, so the ones
+    /// following it can still be read.
+    pub fn next_value(&mut self) -> Result<Option<Value>, Error> {
+        while let Some(&byte) = self.buffer.get(self.scanned) {
+            let i = self.scanned;
+            match self.start {
+                None if is_whitespace(byte) => self.consumed = i + 1,
+                None => {
+                    self.start = Some((i, self.position));
+                    match byte {
+                        b'{' | b'[' => self.depth = 1,
+                        b'"' => self.in_string = true,
+                        _ => self.in_scalar = true,
+                    }
+                }
+                Some(_) if self.in_string => {
+                    if self.escaped {
+                        self.escaped = false;
+                    } else if byte == b'\\' {
+                        self.escaped = true;
+                    } else if byte == b'"' {
+                        self.in_string = false;
+                        if self.depth == 0 {
+                            self.advance(byte);
+                            match self.complete(i + 1) {
+                                Some(result) => return result.map(Some),
+                                None => continue,
+                            }
+                        }
+                    }
+                }
+                // The delimiter belongs to whatever comes next.
+                Some(_) if self.in_scalar => {
+                    if is_whitespace(byte) || b"{}[],:\"".contains(&byte) {
+                        match self.complete(i) {
+                            Some(result) => return result.map(Some),
+                            None => continue,
+                        }
+                    }
+                }
+                Some(_) => match byte {
+                    b'"' => self.in_string = true,
+                    b'{' | b'[' => self.depth += 1,
+                    b'}' | b']' => {
+                        self.depth -= 1;
+                        if self.depth == 0 {
+                            self.advance(byte);
+                            match self.complete(i + 1) {
+                                Some(result) => return result.map(Some),
+                                None => continue,
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+            }
+            self.advance(byte);
+        }
+
+        if let (Some((start, position)), Some(max)) = (self.start, self.max_len) {
+            if self.skipping {
+                self.consumed = self.scanned;
+            } else if self.buffer.len() - start > max {
+                self.skipping = true;
+                self.consumed = self.scanned;
+                Err(Error::at(ErrorKind::TooLarge { max }, position))?
+            }
+        }
+        Ok(None)
+    }
+
+    /// The last document once the stream has ended, a number or keyword at the very
+    /// end has nothing after it to tell it is complete. Unfinished documents are
+    /// reported as the errors they are.
+    pub fn finish(&mut self) -> Result<Option<Value>, Error> {
+        if let Some(value) = self.next_value()? {
+            return Ok(Some(value));
+        }
+        match self.start.and_then(|_| self.complete(self.buffer.len())) {
+            Some(result) => result.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn advance(&mut self, byte: u8) {
+        self.scanned += 1;
+        self.position.offset += 1;
+        if byte == b'\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else if byte & 0xC0 != 0x80 {
+            // Continuation bytes don't start a char.
+            self.position.column += 1;
+        }
+    }
+
+    /// Parse the document ending at `end` and get ready for the next one, `None` for
+    /// a skipped one.
+    fn complete(&mut self, end: usize) -> Option<Result<Value, Error>> {
+        let (start, position) = self.start?;
+        let result = match self.skipping {
+            true => None,
+            false => {
+                let bytes = self.buffer.get(start..end).unwrap_or_default();
+                Some(crate::parse_bytes(bytes).map_err(|e| e.shifted(position)))
+            }
+        };
+        self.reset(end);
+        result
+    }
+
+    fn reset(&mut self, consumed: usize) {
+        while self.scanned < consumed {
+            let byte = self.buffer.get(self.scanned).copied().unwrap_or_default();
+            self.advance(byte);
+        }
+        self.consumed = consumed;
+        self.start = None;
+        self.depth = 0;
+        self.in_string = false;
+        self.escaped = false;
+        self.in_scalar = false;
+        self.skipping = false;
+    }
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    /// Every value of `input` fed in chunks of `size` bytes.
+    fn values(input: &str, size: usize) -> Vec<Result<Value, String>> {
+        let mut feeder = Feeder::new();
+        let mut values = Vec::new();
+        for chunk in input.as_bytes().chunks(size) {
+            feeder.feed(chunk);
+            while let Some(value) = feeder.next_value().map_err(|e| e.to_string()).transpose() {
+                values.push(value);
+            }
+        }
+        values.extend(feeder.finish().map_err(|e| e.to_string()).transpose());
+        values
+    }
+
+    #[test]
+    fn test_chunks() {
+        let input = "{\"a\": [1, \"}\\\"]\"]}\n[]\n\"x\" 12 true\n-1.5e3";
+        let expected = [
+            json!({ "a": [1, "}\"]"] }),
+            json!([]),
+            json!("x"),
+            json!(12),
+            json!(true),
+            json!(-1500.0),
+        ];
+        for size in 1..input.len() {
+            let values: Vec<Value> = values(input, size).into_iter().flatten().collect();
+            assert_eq!(values, expected, "chunks of {size}");
+        }
+        assert!(values("  \n", 1).is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        // Errors point into the stream and the documents after them still come out.
+        assert_eq!(
+            values("[1]\n[1,]\n{\"é\": nul}\n2", 3),
+            [
+                Ok(json!([1])),
+                Err(String::from(
+                    "[line 2, column 3] E0003 trailing-comma: Trailing Comma."
+                )),
+                Err(String::from(
+                    "[line 3, column 7] E0006 invalid-keyword: Invalid keyword \"nul\", Maybe you mean \"null\"."
+                )),
+                Ok(json!(2)),
+            ]
+        );
+        assert_eq!(
+            values("[1, {", 2),
+            [Err(String::from(
+                "[line 1, column 6] E0004 unclosed-object: Expected closing curly braces '}'"
+            ))]
+        );
+
+        let mut feeder = Feeder::new().with_max_len(8);
+        feeder.feed(b" [\"0123456789");
+        let err = feeder.next_value().unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::TooLarge { max: 8 });
+        assert_eq!(err.span().start.column, 2);
+        // The rest of it is dropped.
+        feeder.feed(b"\"] 7");
+        assert_eq!(feeder.finish().unwrap(), Some(json!(7)));
+    }
+}
@@ -0,0 +1,108 @@
+//! Getting the bytes of a document without copying them when possible.
+//!
+//! Regular files are memory mapped, so validating or querying a multi-GB dump doesn't
+//! need as much memory. Pipes, stdin and anything else that can't be mapped are read
+//! into a buffer instead.
+
+use crate::encoding;
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, Read},
+    ops::Deref,
+    path::Path,
+};
+
+/// The raw bytes of a document.
+pub enum Input {
+    Mapped(Mmap),
+    Buffered(Vec<u8>),
+}
+
+impl Input {
+    /// Map the file at `path`, `-` reads stdin.
+    ///
+    /// The file must not be changed by other processes while it is mapped.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if path == Path::new("-") {
+            return Self::read(io::stdin().lock());
+        }
+
+        let file = File::open(path).context("open json file")?;
+        let metadata = file.metadata().context("read file metadata")?;
+
+        // Empty files can't be mapped on every platform.
+        if metadata.is_file() && metadata.len() > 0 {
+            // SAFETY: the map is read only, the file being modified or truncated
+            // underneath it is documented as unsupported.
+            if let Ok(map) = unsafe { Mmap::map(&file) } {
+                return Ok(Input::Mapped(map));
+            }
+        }
+
+        Self::read(file)
+    }
+
+    /// Read everything from `reader` into a buffer.
+    pub fn read(mut reader: impl Read) -> Result<Self> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).context("read file contents")?;
+        Ok(Input::Buffered(buf))
+    }
+
+    /// Whether the bytes are mapped rather than copied.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self, Input::Mapped(_))
+    }
+
+    /// Decode the bytes, see `encoding::decode`. UTF-8 is borrowed as is.
+    pub fn text(&self, lossy: bool) -> Result<Cow<'_, str>> {
+        Ok(encoding::decode_bytes(self, lossy)?)
+    }
+}
+
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Input::Mapped(map) => map,
+            Input::Buffered(buf) => buf,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_open() {
+        let path = std::env::temp_dir().join(format!("json-parser-input-{}", std::process::id()));
+        File::create(&path)
+            .unwrap()
+            .write_all(b"\xEF\xBB\xBF[1,\n2]")
+            .unwrap();
+
+        let input = Input::open(&path).unwrap();
+        assert!(input.is_mapped());
+        assert!(matches!(
+            input.text(false).unwrap(),
+            Cow::Borrowed("[1,\n2]")
+        ));
+
+        File::create(&path).unwrap();
+        let input = Input::open(&path).unwrap();
+        assert!(!input.is_mapped());
+        assert_eq!(input.text(false).unwrap(), "");
+
+        std::fs::remove_file(&path).unwrap();
+
+        let input = Input::read(&b"[true]"[..]).unwrap();
+        assert_eq!(&*input, b"[true]");
+    }
+}
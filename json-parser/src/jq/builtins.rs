@@ -0,0 +1,645 @@
+use super::{
+    eval::{
+        add, compare, describe, eval, index, index_error, insert, is_truthy, iterate, number,
+        sorted_keys, split, take, to_text, Outputs, Scope,
+    },
+    parser::Expr,
+};
+use crate::{
+    errors::JqError,
+    lexer::{is_valid_number, to_number},
+    parser::parse_str,
+    value::{Number, Value},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Builtin {
+    Empty,
+    Error,
+    ErrorWith,
+    Not,
+    Length,
+    Keys,
+    KeysUnsorted,
+    Values,
+    Has,
+    Contains,
+    Add,
+    Any,
+    AnyOf,
+    All,
+    AllOf,
+    Flatten,
+    FlattenDepth,
+    Range,
+    RangeFrom,
+    Floor,
+    Ceil,
+    Round,
+    Sqrt,
+    ToString,
+    ToNumber,
+    ToJson,
+    FromJson,
+    Type,
+    Sort,
+    SortBy,
+    GroupBy,
+    Unique,
+    UniqueBy,
+    Min,
+    Max,
+    MinBy,
+    MaxBy,
+    Reverse,
+    ToEntries,
+    FromEntries,
+    WithEntries,
+    AsciiDowncase,
+    AsciiUpcase,
+    First,
+    Last,
+    FirstOf,
+    LastOf,
+    Limit,
+    Recurse,
+    RecurseBy,
+    Map,
+    MapValues,
+    Select,
+    Walk,
+    StartsWith,
+    EndsWith,
+    LtrimStr,
+    RtrimStr,
+    Split,
+    Join,
+}
+
+/// Every builtin by name and number of arguments.
+const BUILTINS: &[(&str, usize, Builtin)] = &[
+    ("empty", 0, Builtin::Empty),
+    ("error", 0, Builtin::Error),
+    ("error", 1, Builtin::ErrorWith),
+    ("not", 0, Builtin::Not),
+    ("length", 0, Builtin::Length),
+    ("keys", 0, Builtin::Keys),
+    ("keys_unsorted", 0, Builtin::KeysUnsorted),
+    ("values", 0, Builtin::Values),
+    ("has", 1, Builtin::Has),
+    ("contains", 1, Builtin::Contains),
+    ("add", 0, Builtin::Add),
+    ("any", 0, Builtin::Any),
+    ("any", 1, Builtin::AnyOf),
+    ("all", 0, Builtin::All),
+    ("all", 1, Builtin::AllOf),
+    ("flatten", 0, Builtin::Flatten),
+    ("flatten", 1, Builtin::FlattenDepth),
+    ("range", 1, Builtin::Range),
+    ("range", 2, Builtin::RangeFrom),
+    ("floor", 0, Builtin::Floor),
+    ("ceil", 0, Builtin::Ceil),
+    ("round", 0, Builtin::Round),
+    ("sqrt", 0, Builtin::Sqrt),
+    ("tostring", 0, Builtin::ToString),
+    ("tonumber", 0, Builtin::ToNumber),
+    ("tojson", 0, Builtin::ToJson),
+    ("fromjson", 0, Builtin::FromJson),
+    ("type", 0, Builtin::Type),
+    ("sort", 0, Builtin::Sort),
+    ("sort_by", 1, Builtin::SortBy),
+    ("group_by", 1, Builtin::GroupBy),
+    ("unique", 0, Builtin::Unique),
+    ("unique_by", 1, Builtin::UniqueBy),
+    ("min", 0, Builtin::Min),
+    ("max", 0, Builtin::Max),
+    ("min_by", 1, Builtin::MinBy),
+    ("max_by", 1, Builtin::MaxBy),
+    ("reverse", 0, Builtin::Reverse),
+    ("to_entries", 0, Builtin::ToEntries),
+    ("from_entries", 0, Builtin::FromEntries),
+    ("with_entries", 1, Builtin::WithEntries),
+    ("ascii_downcase", 0, Builtin::AsciiDowncase),
+    ("ascii_upcase", 0, Builtin::AsciiUpcase),
+    ("first", 0, Builtin::First),
+    ("last", 0, Builtin::Last),
+    ("first", 1, Builtin::FirstOf),
+    ("last", 1, Builtin::LastOf),
+    ("limit", 2, Builtin::Limit),
+    ("recurse", 0, Builtin::Recurse),
+    ("recurse", 1, Builtin::RecurseBy),
+    ("map", 1, Builtin::Map),
+    ("map_values", 1, Builtin::MapValues),
+    ("select", 1, Builtin::Select),
+    ("walk", 1, Builtin::Walk),
+    ("startswith", 1, Builtin::StartsWith),
+    ("endswith", 1, Builtin::EndsWith),
+    ("ltrimstr", 1, Builtin::LtrimStr),
+    ("rtrimstr", 1, Builtin::RtrimStr),
+    ("split", 1, Builtin::Split),
+    ("join", 1, Builtin::Join),
+];
+
+impl Builtin {
+    pub(super) fn lookup(name: &str, arity: usize) -> Option<Self> {
+        BUILTINS
+            .iter()
+            .find(|(n, a, _)| *n == name && *a == arity)
+            .map(|(_, _, builtin)| *builtin)
+    }
+
+    fn name(self) -> &'static str {
+        BUILTINS
+            .iter()
+            .find(|(_, _, builtin)| *builtin == self)
+            .map_or("builtin", |(name, _, _)| name)
+    }
+}
+
+/// Stands in for missing arguments, the parser checks arities so it never runs.
+static IDENTITY: Expr = Expr::Identity;
+
+/// How many numbers one `range` may output when nothing like `limit` asks for fewer.
+const MAX_RANGE: usize = 1 << 24;
+
+/// Run `builtin`. Generators stop after `max` outputs, callers only wanting that
+/// many truncate the rest.
+pub(super) fn call(
+    builtin: Builtin,
+    args: &[Expr],
+    input: &Value,
+    scope: &Scope,
+    max: usize,
+) -> Outputs {
+    let arg = |i: usize| args.get(i).unwrap_or(&IDENTITY);
+    // Outputs of the first argument, for builtins taking plain values.
+    let values = || eval(arg(0), input, scope);
+    // Outputs of the first argument run against `value`, for builtins taking a filter.
+    let apply = |value: &Value| eval(arg(0), value, scope);
+    let unsupported = |value: &Value| JqError::Unsupported {
+        function: builtin.name(),
+        value: describe(value),
+    };
+    let one = |value: Value| Ok(vec![value]);
+
+    match builtin {
+        Builtin::Empty => Ok(Vec::new()),
+        Builtin::Error => Err(JqError::Raised(input.clone())),
+        Builtin::ErrorWith => match values()?.into_iter().next() {
+            Some(value) => Err(JqError::Raised(value)),
+            None => Ok(Vec::new()),
+        },
+        Builtin::Not => one(Value::Bool(!is_truthy(input))),
+        Builtin::Length => one(match input {
+            Value::Null => Value::from(0),
+            Value::Number(Number::Integer(n)) => Value::from(n.unsigned_abs()),
+            Value::Number(n) => number(n.as_f64().abs()),
+            Value::String(s) => Value::from(s.chars().count()),
+            Value::Array(items) => Value::from(items.len()),
+            Value::Object(members) => Value::from(members.len()),
+            Value::Bool(_) => Err(unsupported(input))?,
+        }),
+        Builtin::Keys | Builtin::KeysUnsorted => one(match input {
+            Value::Object(_) if builtin == Builtin::Keys => Value::from(
+                sorted_keys(input)
+                    .into_iter()
+                    .map(Value::from)
+                    .collect::<Vec<_>>(),
+            ),
+            Value::Object(members) => Value::from(
+                members
+                    .iter()
+                    .map(|(key, _)| Value::from(key.as_str()))
+                    .collect::<Vec<_>>(),
+            ),
+            Value::Array(items) => Value::from((0..items.len()).collect::<Vec<_>>()),
+            _ => Err(unsupported(input))?,
+        }),
+        Builtin::Values => Ok(match input {
+            Value::Null => Vec::new(),
+            value => vec![value.clone()],
+        }),
+        Builtin::Has => values()?
+            .iter()
+            .map(|key| match (input, key) {
+                (Value::Object(_), Value::String(key)) => Ok(Value::Bool(input.get(key).is_some())),
+                (Value::Array(items), Value::Number(n)) => {
+                    let n = n.as_f64();
+                    Ok(Value::Bool(n >= 0.0 && n < items.len() as f64))
+                }
+                _ => Err(index_error(input, key)),
+            })
+            .collect(),
+        Builtin::Contains => values()?
+            .iter()
+            .map(|other| contains(input, other).map(Value::Bool))
+            .collect(),
+        Builtin::Add => one(iterate(input)?.into_iter().try_fold(Value::Null, add)?),
+        Builtin::Any => one(Value::Bool(iterate(input)?.iter().any(is_truthy))),
+        Builtin::All => one(Value::Bool(iterate(input)?.iter().all(is_truthy))),
+        Builtin::AnyOf | Builtin::AllOf => {
+            let mut outputs = Vec::new();
+            for item in iterate(input)? {
+                outputs.extend(apply(&item)?);
+            }
+            one(Value::Bool(match builtin {
+                Builtin::AnyOf => outputs.iter().any(is_truthy),
+                _ => outputs.iter().all(is_truthy),
+            }))
+        }
+        Builtin::Flatten => one(Value::Array(flatten(input, f64::INFINITY)?)),
+        Builtin::FlattenDepth => values()?
+            .iter()
+            .map(|depth| match depth {
+                Value::Number(n) if n.as_f64() >= 0.0 => {
+                    Ok(Value::Array(flatten(input, n.as_f64())?))
+                }
+                _ => Err(unsupported(depth)),
+            })
+            .collect(),
+        Builtin::Range => {
+            let mut outputs = Vec::new();
+            for upto in values()? {
+                let max = max.saturating_sub(outputs.len());
+                outputs.extend(range(&Value::from(0), &upto, max)?);
+            }
+            Ok(outputs)
+        }
+        Builtin::RangeFrom => {
+            let uptos = eval(arg(1), input, scope)?;
+            let mut outputs = Vec::new();
+            for from in values()? {
+                for upto in &uptos {
+                    let max = max.saturating_sub(outputs.len());
+                    outputs.extend(range(&from, upto, max)?);
+                }
+            }
+            Ok(outputs)
+        }
+        Builtin::Floor | Builtin::Ceil | Builtin::Round | Builtin::Sqrt => {
+            let Value::Number(n) = input else {
+                Err(unsupported(input))?
+            };
+            let n = n.as_f64();
+            one(number(match builtin {
+                Builtin::Floor => n.floor(),
+                Builtin::Ceil => n.ceil(),
+                Builtin::Round => n.round(),
+                _ => n.sqrt(),
+            }))
+        }
+        Builtin::ToString => one(Value::String(to_text(input))),
+        Builtin::ToNumber => one(match input {
+            Value::Number(_) => input.clone(),
+            Value::String(s) if is_valid_number(s) => match to_number(s) {
+                Some(n) => Value::Number(n),
+                None => Err(unsupported(input))?,
+            },
+            _ => Err(unsupported(input))?,
+        }),
+        Builtin::ToJson => one(Value::String(input.to_string())),
+        Builtin::FromJson => match input {
+            Value::String(s) => match parse_str(s) {
+                Ok(value) => one(value),
+                Err(e) => Err(JqError::Raised(Value::String(format!(
+                    "{e} (while parsing {s:?})"
+                )))),
+            },
+            _ => Err(unsupported(input)),
+        },
+        Builtin::Type => one(Value::from(input.type_name())),
+        Builtin::Sort
+        | Builtin::SortBy
+        | Builtin::GroupBy
+        | Builtin::Unique
+        | Builtin::UniqueBy => {
+            let Value::Array(items) = input else {
+                Err(unsupported(input))?
+            };
+            let mut keyed = Vec::with_capacity(items.len());
+            for item in items {
+                let key = match builtin {
+                    Builtin::Sort | Builtin::Unique => item.clone(),
+                    _ => Value::Array(apply(item)?),
+                };
+                keyed.push((key, item.clone()));
+            }
+            keyed.sort_by(|(a, _), (b, _)| compare(a, b));
+
+            // Runs of items with equal keys.
+            let mut groups = Vec::<(Value, Vec<Value>)>::new();
+            for (key, item) in keyed {
+                match groups.last_mut() {
+                    Some((last, group)) if compare(last, &key).is_eq() => group.push(item),
+                    _ => groups.push((key, vec![item])),
+                }
+            }
+
+            let groups = groups.into_iter().map(|(_, group)| group);
+            one(Value::Array(match builtin {
+                Builtin::Sort | Builtin::SortBy => groups.flatten().collect(),
+                Builtin::GroupBy => groups.map(Value::Array).collect(),
+                _ => groups
+                    .filter_map(|group| group.into_iter().next())
+                    .collect(),
+            }))
+        }
+        Builtin::Min | Builtin::Max | Builtin::MinBy | Builtin::MaxBy => {
+            let Value::Array(items) = input else {
+                Err(unsupported(input))?
+            };
+            let mut keyed = Vec::with_capacity(items.len());
+            for item in items {
+                let key = match builtin {
+                    Builtin::Min | Builtin::Max => item.clone(),
+                    _ => Value::Array(apply(item)?),
+                };
+                keyed.push((key, item));
+            }
+            let keyed = keyed.iter();
+            let by_key = |(a, _): &&(Value, &Value), (b, _): &&(Value, &Value)| compare(a, b);
+            let found = match builtin {
+                Builtin::Min | Builtin::MinBy => keyed.min_by(by_key),
+                _ => keyed.max_by(by_key),
+            };
+            one(found.map_or(Value::Null, |(_, item)| (*item).clone()))
+        }
+        Builtin::Reverse => one(match input {
+            Value::Null => Value::Array(Vec::new()),
+            Value::String(s) => Value::String(s.chars().rev().collect()),
+            Value::Array(items) => Value::Array(items.iter().rev().cloned().collect()),
+            _ => Err(unsupported(input))?,
+        }),
+        Builtin::ToEntries => one(to_entries(input).ok_or_else(|| unsupported(input))?),
+        Builtin::FromEntries => one(from_entries(input)?),
+        Builtin::WithEntries => {
+            let entries = to_entries(input).ok_or_else(|| unsupported(input))?;
+            let mut mapped = Vec::new();
+            for entry in iterate(&entries)? {
+                mapped.extend(apply(&entry)?);
+            }
+            one(from_entries(&Value::Array(mapped))?)
+        }
+        Builtin::AsciiDowncase | Builtin::AsciiUpcase => match input {
+            Value::String(s) if builtin == Builtin::AsciiDowncase => {
+                one(Value::String(s.to_ascii_lowercase()))
+            }
+            Value::String(s) => one(Value::String(s.to_ascii_uppercase())),
+            _ => Err(unsupported(input)),
+        },
+        Builtin::First => one(index(input, &Value::from(0))?),
+        Builtin::Last => one(index(input, &Value::from(-1))?),
+        Builtin::FirstOf => take(arg(0), input, scope, 1),
+        Builtin::LastOf => Ok(values()?.pop().into_iter().collect()),
+        Builtin::Limit => {
+            let mut limited = Vec::new();
+            for n in values()? {
+                let Value::Number(n) = n else {
+                    Err(unsupported(&n))?
+                };
+                let n = n.as_f64().max(0.0) as usize;
+                limited.extend(take(arg(1), input, scope, n)?);
+            }
+            Ok(limited)
+        }
+        Builtin::Recurse => recurse(input, |value| {
+            Ok(match value {
+                Value::Array(_) | Value::Object(_) => iterate(value)?,
+                _ => Vec::new(),
+            })
+        }),
+        Builtin::RecurseBy => recurse(input, apply),
+        Builtin::Map => {
+            let mut mapped = Vec::new();
+            for item in iterate(input)? {
+                mapped.extend(apply(&item)?);
+            }
+            one(Value::Array(mapped))
+        }
+        Builtin::MapValues => one(map_values(input, &apply)?),
+        Builtin::Select => Ok(apply(input)?
+            .iter()
+            .filter(|value| is_truthy(value))
+            .map(|_| input.clone())
+            .collect()),
+        Builtin::Walk => walk(input, &apply),
+        Builtin::StartsWith | Builtin::EndsWith => values()?
+            .iter()
+            .map(|affix| match (input, affix) {
+                (Value::String(s), Value::String(affix)) => Ok(Value::Bool(match builtin {
+                    Builtin::StartsWith => s.starts_with(affix.as_str()),
+                    _ => s.ends_with(affix.as_str()),
+                })),
+                (Value::String(_), _) => Err(unsupported(affix)),
+                _ => Err(unsupported(input)),
+            })
+            .collect(),
+        Builtin::LtrimStr | Builtin::RtrimStr => Ok(values()?
+            .iter()
+            .map(|affix| {
+                let trimmed = match (input, affix) {
+                    (Value::String(s), Value::String(affix)) if builtin == Builtin::LtrimStr => {
+                        s.strip_prefix(affix.as_str())
+                    }
+                    (Value::String(s), Value::String(affix)) => s.strip_suffix(affix.as_str()),
+                    _ => None,
+                };
+                trimmed.map_or_else(|| input.clone(), Value::from)
+            })
+            .collect()),
+        Builtin::Split => values()?
+            .iter()
+            .map(|separator| match (input, separator) {
+                (Value::String(s), Value::String(separator)) => Ok(split(s, separator)),
+                (Value::String(_), _) => Err(unsupported(separator)),
+                _ => Err(unsupported(input)),
+            })
+            .collect(),
+        Builtin::Join => values()?
+            .iter()
+            .map(|separator| {
+                let Value::String(separator) = separator else {
+                    Err(unsupported(separator))?
+                };
+                let mut joined = String::new();
+                for (i, item) in iterate(input)?.iter().enumerate() {
+                    if i > 0 {
+                        joined.push_str(separator);
+                    }
+                    match item {
+                        Value::Null => {}
+                        Value::Bool(_) | Value::Number(_) | Value::String(_) => {
+                            joined.push_str(&to_text(item))
+                        }
+                        _ => Err(unsupported(item))?,
+                    }
+                }
+                Ok(Value::String(joined))
+            })
+            .collect(),
+    }
+}
+
+/// Whether `b` is contained in `a`: substrings, subsets of arrays and objects,
+/// recursively, and equal scalars. Only the outermost values must share a type.
+fn contains(a: &Value, b: &Value) -> Result<bool, JqError> {
+    if a.type_name() != b.type_name() {
+        Err(JqError::Operands {
+            left: describe(a),
+            right: describe(b),
+            op: "checked for containment",
+        })?
+    }
+    Ok(contained(a, b))
+}
+
+fn contained(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Object(_), Value::Object(members)) => members
+            .iter()
+            .all(|(key, value)| a.get(key).is_some_and(|inner| contained(inner, value))),
+        (Value::Array(items), Value::Array(others)) => others
+            .iter()
+            .all(|other| items.iter().any(|item| contained(item, other))),
+        (Value::String(a), Value::String(b)) => a.contains(b.as_str()),
+        _ => a.type_name() == b.type_name() && compare(a, b).is_eq(),
+    }
+}
+
+fn flatten(value: &Value, depth: f64) -> Result<Vec<Value>, JqError> {
+    let Value::Array(items) = value else {
+        Err(JqError::Unsupported {
+            function: "flatten",
+            value: describe(value),
+        })?
+    };
+
+    let mut flat = Vec::new();
+    for item in items {
+        match item {
+            Value::Array(_) if depth > 0.0 => flat.extend(flatten(item, depth - 1.0)?),
+            item => flat.push(item.clone()),
+        }
+    }
+    Ok(flat)
+}
+
+/// `from`, `from + 1`, ... up to `upto` excluded, at most `max` of them.
+///
+/// Past 2^53 adding one no longer changes a float, so only the count can end the
+/// loop, and past `MAX_RANGE` it is an error rather than a hang.
+fn range(from: &Value, upto: &Value, max: usize) -> Outputs {
+    match (from, upto) {
+        (Value::Number(from), Value::Number(upto)) => {
+            let (mut n, upto) = (from.as_f64(), upto.as_f64());
+            let mut outputs = Vec::new();
+            while n < upto && outputs.len() < max {
+                if outputs.len() == MAX_RANGE {
+                    Err(JqError::TooMany {
+                        function: "range",
+                        max: MAX_RANGE,
+                    })?
+                }
+                outputs.push(number(n));
+                n += 1.0;
+            }
+            Ok(outputs)
+        }
+        (Value::Number(_), _) => Err(JqError::Unsupported {
+            function: "range",
+            value: describe(upto),
+        }),
+        _ => Err(JqError::Unsupported {
+            function: "range",
+            value: describe(from),
+        }),
+    }
+}
+
+/// `input` followed by everything reachable through `children`, depth first.
+fn recurse(input: &Value, children: impl Fn(&Value) -> Outputs) -> Outputs {
+    let mut outputs = Vec::new();
+    let mut stack = vec![input.clone()];
+    while let Some(value) = stack.pop() {
+        stack.extend(children(&value)?.into_iter().rev());
+        outputs.push(value);
+    }
+    Ok(outputs)
+}
+
+/// Replace every value of an array or object with the first output of `f`, values
+/// without any output are dropped.
+fn map_values(input: &Value, f: &dyn Fn(&Value) -> Outputs) -> Result<Value, JqError> {
+    Ok(match input {
+        Value::Array(items) => {
+            let mut mapped = Vec::new();
+            for item in items {
+                mapped.extend(f(item)?.into_iter().next());
+            }
+            Value::Array(mapped)
+        }
+        Value::Object(members) => {
+            let mut mapped = Vec::new();
+            for (key, value) in members {
+                if let Some(value) = f(value)?.into_iter().next() {
+                    mapped.push((key.clone(), value));
+                }
+            }
+            Value::Object(mapped)
+        }
+        _ => Err(JqError::Iterate {
+            target: describe(input),
+        })?,
+    })
+}
+
+/// Apply `f` to every value, children before their parent.
+fn walk(input: &Value, f: &dyn Fn(&Value) -> Outputs) -> Outputs {
+    let walked = match input {
+        Value::Array(items) => {
+            let mut walked = Vec::new();
+            for item in items {
+                walked.extend(walk(item, f)?);
+            }
+            Value::Array(walked)
+        }
+        Value::Object(_) => map_values(input, &|value| walk(value, f))?,
+        value => value.clone(),
+    };
+    f(&walked)
+}
+
+fn to_entries(input: &Value) -> Option<Value> {
+    let Value::Object(members) = input else {
+        return None;
+    };
+    let entries = members
+        .iter()
+        .map(|(key, value)| {
+            Value::Object(vec![
+                (String::from("key"), Value::from(key.as_str())),
+                (String::from("value"), value.clone()),
+            ])
+        })
+        .collect();
+    Some(Value::Array(entries))
+}
+
+/// Build an object out of `{"key": k, "value": v}` entries, `k`, `name` and `v` work
+/// too. Keys that aren't strings are converted.
+fn from_entries(input: &Value) -> Result<Value, JqError> {
+    let mut members = Vec::new();
+    for entry in iterate(input)? {
+        let key = ["key", "k", "name", "Name", "K", "Key"]
+            .iter()
+            .find_map(|name| entry.get(name).filter(|key| is_truthy(key)))
+            .map_or_else(|| String::from("null"), to_text);
+        let value = ["value", "v", "Value", "V"]
+            .iter()
+            .find_map(|name| entry.get(name))
+            .cloned()
+            .unwrap_or(Value::Null);
+        insert(&mut members, key, value);
+    }
+    Ok(Value::Object(members))
+}
@@ -0,0 +1,680 @@
+use super::{
+    builtins,
+    parser::{BinOp, Expr, Segment},
+};
+use crate::{
+    errors::JqError,
+    value::{Number, Value},
+};
+use std::cmp::Ordering;
+
+pub(super) type Outputs = Result<Vec<Value>, JqError>;
+
+/// Variables bound by `as` and `reduce`, innermost first.
+pub(super) enum Scope<'a> {
+    Root,
+    Bound {
+        name: &'a str,
+        value: Value,
+        parent: &'a Scope<'a>,
+    },
+}
+
+impl Scope<'_> {
+    fn get(&self, name: &str) -> Option<&Value> {
+        let mut scope = self;
+        while let Scope::Bound {
+            name: bound,
+            value,
+            parent,
+        } = scope
+        {
+            if *bound == name {
+                return Some(value);
+            }
+            scope = parent;
+        }
+        None
+    }
+}
+
+/// Every output of `expr` run against `input`.
+///
+/// Operators and constructors taking several generators output every combination,
+/// the rightmost operand varying slowest like jq does.
+pub(super) fn eval(expr: &Expr, input: &Value, scope: &Scope) -> Outputs {
+    let eval_one = |expr: &Expr| eval(expr, input, scope);
+
+    Ok(match expr {
+        Expr::Identity => vec![input.clone()],
+        Expr::Literal(value) => vec![value.clone()],
+        Expr::Interpolated(segments) => {
+            let mut strings = vec![String::new()];
+            for segment in segments {
+                match segment {
+                    Segment::Literal(s) => strings.iter_mut().for_each(|out| out.push_str(s)),
+                    Segment::Expr(expr) => {
+                        strings = eval_one(expr)?
+                            .iter()
+                            .map(to_text)
+                            .flat_map(|text| strings.iter().map(move |s| format!("{s}{text}")))
+                            .collect();
+                    }
+                }
+            }
+            strings.into_iter().map(Value::String).collect()
+        }
+        // The parser only lets bound variables through.
+        Expr::Var(name) => vec![scope.get(name).cloned().unwrap_or(Value::Null)],
+        Expr::Index(target, key) => {
+            let keys = eval_one(key)?;
+            let mut outputs = Vec::new();
+            for target in eval_one(target)? {
+                for key in &keys {
+                    outputs.push(index(&target, key)?);
+                }
+            }
+            outputs
+        }
+        Expr::Slice(target, from, to) => {
+            let bound = |expr: &Option<Box<Expr>>| match expr {
+                Some(expr) => eval_one(expr),
+                None => Ok(vec![Value::Null]),
+            };
+            let (froms, tos) = (bound(from)?, bound(to)?);
+            let mut outputs = Vec::new();
+            for target in eval_one(target)? {
+                for to in &tos {
+                    for from in &froms {
+                        outputs.push(slice(&target, from, to)?);
+                    }
+                }
+            }
+            outputs
+        }
+        Expr::Iterate(target) => {
+            let mut outputs = Vec::new();
+            for target in eval_one(target)? {
+                outputs.extend(iterate(&target)?);
+            }
+            outputs
+        }
+        Expr::Try(body, handler) => match (eval_one(body), handler) {
+            (Ok(outputs), _) => outputs,
+            (Err(_), None) => Vec::new(),
+            (Err(e), Some(handler)) => eval(handler, &e.value(), scope)?,
+        },
+        Expr::Pipe(lhs, rhs) => {
+            let mut outputs = Vec::new();
+            for value in eval_one(lhs)? {
+                outputs.extend(eval(rhs, &value, scope)?);
+            }
+            outputs
+        }
+        Expr::Comma(lhs, rhs) => {
+            let mut outputs = eval_one(lhs)?;
+            outputs.extend(eval_one(rhs)?);
+            outputs
+        }
+        Expr::Neg(expr) => eval_one(expr)?
+            .into_iter()
+            .map(|value| match value {
+                Value::Number(Number::Integer(n)) => Ok(match n.checked_neg() {
+                    Some(n) => Value::from(n),
+                    None => number(-(n as f64)),
+                }),
+                Value::Number(Number::Float(n)) => Ok(number(-n)),
+                value => Err(JqError::Unsupported {
+                    function: "negation",
+                    value: describe(&value),
+                }),
+            })
+            .collect::<Result<_, _>>()?,
+        Expr::Binary(op, lhs, rhs) => {
+            let lhs = eval_one(lhs)?;
+            let mut outputs = Vec::new();
+            for r in eval_one(rhs)? {
+                for l in &lhs {
+                    outputs.push(binary(*op, l.clone(), r.clone())?);
+                }
+            }
+            outputs
+        }
+        Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+            let and = matches!(expr, Expr::And(..));
+            let mut outputs = Vec::new();
+            for l in eval_one(lhs)? {
+                // Short circuits on false for `and`, on true for `or`.
+                if is_truthy(&l) != and {
+                    outputs.push(Value::Bool(!and));
+                    continue;
+                }
+                for r in eval_one(rhs)? {
+                    outputs.push(Value::Bool(is_truthy(&r)));
+                }
+            }
+            outputs
+        }
+        Expr::Alt(lhs, rhs) => {
+            let truthy = eval_one(lhs)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(is_truthy)
+                .collect::<Vec<_>>();
+            match truthy.is_empty() {
+                true => eval_one(rhs)?,
+                false => truthy,
+            }
+        }
+        Expr::Array(None) => vec![Value::Array(Vec::new())],
+        Expr::Array(Some(items)) => vec![Value::Array(eval_one(items)?)],
+        Expr::Object(entries) => {
+            let mut objects = vec![Vec::new()];
+            for (key, value) in entries {
+                let (keys, values) = (eval_one(key)?, eval_one(value)?);
+                let mut next = Vec::new();
+                for object in &objects {
+                    for key in &keys {
+                        let Value::String(key) = key else {
+                            Err(JqError::ObjectKey { key: describe(key) })?
+                        };
+                        for value in &values {
+                            let mut object = object.clone();
+                            insert(&mut object, key.clone(), value.clone());
+                            next.push(object);
+                        }
+                    }
+                }
+                objects = next;
+            }
+            objects.into_iter().map(Value::Object).collect()
+        }
+        Expr::If(branches, otherwise) => eval_if(branches, otherwise, input, scope)?,
+        Expr::Bind(source, name, body) => {
+            let mut outputs = Vec::new();
+            for value in eval_one(source)? {
+                let scope = Scope::Bound {
+                    name,
+                    value,
+                    parent: scope,
+                };
+                outputs.extend(eval(body, input, &scope)?);
+            }
+            outputs
+        }
+        Expr::Reduce {
+            source,
+            name,
+            init,
+            update,
+        } => {
+            let items = eval_one(source)?;
+            let mut outputs = Vec::new();
+            for mut acc in eval_one(init)? {
+                for value in &items {
+                    let scope = Scope::Bound {
+                        name,
+                        value: value.clone(),
+                        parent: scope,
+                    };
+                    acc = eval(update, &acc, &scope)?.pop().unwrap_or(Value::Null);
+                }
+                outputs.push(acc);
+            }
+            outputs
+        }
+        Expr::Call(builtin, args) => builtins::call(*builtin, args, input, scope, usize::MAX)?,
+    })
+}
+
+/// The first `n` outputs of `expr`.
+///
+/// Evaluation stops early through `,`, the right side of `|` and generating builtins,
+/// so `first(range(1e300))` returns right away. Everything else is run in full.
+pub(super) fn take(expr: &Expr, input: &Value, scope: &Scope, n: usize) -> Outputs {
+    let mut outputs = Vec::new();
+    match expr {
+        _ if n == 0 => {}
+        Expr::Comma(lhs, rhs) => {
+            outputs = take(lhs, input, scope, n)?;
+            outputs.extend(take(rhs, input, scope, n.saturating_sub(outputs.len()))?);
+        }
+        Expr::Pipe(lhs, rhs) => {
+            for value in eval(lhs, input, scope)? {
+                outputs.extend(take(rhs, &value, scope, n - outputs.len())?);
+                if outputs.len() >= n {
+                    break;
+                }
+            }
+        }
+        Expr::Call(builtin, args) => outputs = builtins::call(*builtin, args, input, scope, n)?,
+        _ => outputs = eval(expr, input, scope)?,
+    }
+    outputs.truncate(n);
+    Ok(outputs)
+}
+
+fn eval_if(branches: &[(Expr, Expr)], otherwise: &Expr, input: &Value, scope: &Scope) -> Outputs {
+    let Some(((condition, then), rest)) = branches.split_first() else {
+        return eval(otherwise, input, scope);
+    };
+
+    let mut outputs = Vec::new();
+    for value in eval(condition, input, scope)? {
+        outputs.extend(match is_truthy(&value) {
+            true => eval(then, input, scope)?,
+            false => eval_if(rest, otherwise, input, scope)?,
+        });
+    }
+    Ok(outputs)
+}
+
+fn binary(op: BinOp, lhs: Value, rhs: Value) -> Result<Value, JqError> {
+    let ordering = || compare(&lhs, &rhs);
+    Ok(match op {
+        BinOp::Add => add(lhs, rhs)?,
+        BinOp::Sub => subtract(lhs, rhs)?,
+        BinOp::Mul => multiply(lhs, rhs)?,
+        BinOp::Div => divide(lhs, rhs)?,
+        BinOp::Rem => remainder(lhs, rhs)?,
+        BinOp::Eq => Value::Bool(ordering().is_eq()),
+        BinOp::Ne => Value::Bool(ordering().is_ne()),
+        BinOp::Lt => Value::Bool(ordering().is_lt()),
+        BinOp::Le => Value::Bool(ordering().is_le()),
+        BinOp::Gt => Value::Bool(ordering().is_gt()),
+        BinOp::Ge => Value::Bool(ordering().is_ge()),
+    })
+}
+
+pub(super) fn add(lhs: Value, rhs: Value) -> Result<Value, JqError> {
+    Ok(match (lhs, rhs) {
+        (Value::Null, value) | (value, Value::Null) => value,
+        (Value::Number(a), Value::Number(b)) => arithmetic(a, b, i64::checked_add, |a, b| a + b),
+        (Value::String(a), Value::String(b)) => Value::String(a + &b),
+        (Value::Array(mut a), Value::Array(b)) => {
+            a.extend(b);
+            Value::Array(a)
+        }
+        (Value::Object(mut a), Value::Object(b)) => {
+            for (key, value) in b {
+                insert(&mut a, key, value);
+            }
+            Value::Object(a)
+        }
+        (lhs, rhs) => Err(operands(&lhs, &rhs, "added"))?,
+    })
+}
+
+fn subtract(lhs: Value, rhs: Value) -> Result<Value, JqError> {
+    Ok(match (lhs, rhs) {
+        (Value::Number(a), Value::Number(b)) => arithmetic(a, b, i64::checked_sub, |a, b| a - b),
+        (Value::Array(mut a), Value::Array(b)) => {
+            a.retain(|item| !b.iter().any(|other| compare(item, other).is_eq()));
+            Value::Array(a)
+        }
+        (lhs, rhs) => Err(operands(&lhs, &rhs, "subtracted"))?,
+    })
+}
+
+fn multiply(lhs: Value, rhs: Value) -> Result<Value, JqError> {
+    Ok(match (lhs, rhs) {
+        (Value::Number(a), Value::Number(b)) => arithmetic(a, b, i64::checked_mul, |a, b| a * b),
+        (Value::String(s), Value::Number(n)) | (Value::Number(n), Value::String(s)) => {
+            let times = n.as_f64();
+            if times <= 0.0 {
+                return Ok(Value::Null);
+            }
+            // Saturates, absurd counts fail the check below.
+            let times = times as usize;
+            match s.len().checked_mul(times) {
+                Some(len) if len <= isize::MAX as usize => Value::String(s.repeat(times)),
+                _ => Err(operands(&Value::String(s), &Value::Number(n), "multiplied"))?,
+            }
+        }
+        (Value::Object(a), Value::Object(b)) => Value::Object(deep_merge(a, b)),
+        (lhs, rhs) => Err(operands(&lhs, &rhs, "multiplied"))?,
+    })
+}
+
+fn deep_merge(mut a: Vec<(String, Value)>, b: Vec<(String, Value)>) -> Vec<(String, Value)> {
+    for (key, value) in b {
+        let existing = a.iter_mut().find(|(k, _)| *k == key);
+        match (existing, value) {
+            (Some((_, Value::Object(inner))), Value::Object(value)) => {
+                *inner = deep_merge(std::mem::take(inner), value);
+            }
+            (_, value) => insert(&mut a, key, value),
+        }
+    }
+    a
+}
+
+fn divide(lhs: Value, rhs: Value) -> Result<Value, JqError> {
+    Ok(match (lhs, rhs) {
+        (Value::Number(a), Value::Number(b)) => {
+            if b.as_f64() == 0.0 {
+                Err(JqError::DivisionByZero {
+                    left: describe(&Value::Number(a)),
+                    right: describe(&Value::Number(b)),
+                })?
+            }
+            // Integers stay integers when they divide evenly.
+            let exact = |a: i64, b: i64| a.checked_rem(b).filter(|r| *r == 0).and(a.checked_div(b));
+            arithmetic(a, b, exact, |a, b| a / b)
+        }
+        (Value::String(a), Value::String(b)) => split(&a, &b),
+        (lhs, rhs) => Err(operands(&lhs, &rhs, "divided"))?,
+    })
+}
+
+/// Truncates both sides to integers, like jq.
+fn remainder(lhs: Value, rhs: Value) -> Result<Value, JqError> {
+    match (&lhs, &rhs) {
+        (Value::Number(a), Value::Number(b)) => {
+            let (a, b) = (a.as_f64() as i64, b.as_f64() as i64);
+            if b == 0 {
+                Err(JqError::DivisionByZero {
+                    left: describe(&lhs),
+                    right: describe(&rhs),
+                })?
+            }
+            Ok(Value::from(a.checked_rem(b).unwrap_or(0)))
+        }
+        _ => Err(operands(&lhs, &rhs, "divided")),
+    }
+}
+
+fn arithmetic(
+    a: Number,
+    b: Number,
+    integer: impl Fn(i64, i64) -> Option<i64>,
+    float: impl Fn(f64, f64) -> f64,
+) -> Value {
+    if let (Number::Integer(a), Number::Integer(b)) = (a, b) {
+        if let Some(n) = integer(a, b) {
+            return Value::from(n);
+        }
+    }
+    number(float(a.as_f64(), b.as_f64()))
+}
+
+/// A number value, whole floats that fit become integers so `3 / 1.5` prints `2`.
+///
+/// Infinities saturate to the largest finite float like jq, rather than printing as
+/// `null`.
+pub(super) fn number(n: f64) -> Value {
+    let n = n.clamp(-f64::MAX, f64::MAX);
+    // Beyond 2^53 not every integer has an exact float.
+    if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+        return Value::from(n as i64);
+    }
+    Value::Number(Number::Float(n))
+}
+
+pub(super) fn split(s: &str, separator: &str) -> Value {
+    if s.is_empty() {
+        return Value::Array(Vec::new());
+    }
+    let parts = match separator.is_empty() {
+        true => s.chars().map(|c| Value::from(c.to_string())).collect(),
+        false => s.split(separator).map(Value::from).collect(),
+    };
+    Value::Array(parts)
+}
+
+/// `.[key]`
+pub(super) fn index(target: &Value, key: &Value) -> Result<Value, JqError> {
+    Ok(match (target, key) {
+        (Value::Object(_), Value::String(key)) => target.get(key).cloned().unwrap_or(Value::Null),
+        (Value::Array(items), Value::Number(n)) => {
+            let i = n.as_f64().floor();
+            let i = match i < 0.0 {
+                true => items.len() as f64 + i,
+                false => i,
+            };
+            match i < 0.0 {
+                true => Value::Null,
+                false => items.get(i as usize).cloned().unwrap_or(Value::Null),
+            }
+        }
+        (Value::Null, Value::String(_) | Value::Number(_) | Value::Null) => Value::Null,
+        _ => Err(index_error(target, key))?,
+    })
+}
+
+pub(super) fn index_error(target: &Value, key: &Value) -> JqError {
+    JqError::Index {
+        target: String::from(target.type_name()),
+        key: match key {
+            Value::String(_) => key.to_string(),
+            _ => String::from(key.type_name()),
+        },
+    }
+}
+
+/// `.[from:to]` of an array or string, negative bounds count from the end.
+fn slice(target: &Value, from: &Value, to: &Value) -> Result<Value, JqError> {
+    let len = match target {
+        Value::Array(items) => items.len(),
+        Value::String(s) => s.chars().count(),
+        Value::Null => return Ok(Value::Null),
+        _ => Err(index_error(target, &Value::Object(Vec::new())))?,
+    };
+
+    let bound = |value: &Value, default: usize, round: fn(f64) -> f64| match value {
+        Value::Null => Ok(default),
+        Value::Number(n) => {
+            let n = round(n.as_f64());
+            let n = if n < 0.0 { len as f64 + n } else { n };
+            Ok(n.clamp(0.0, len as f64) as usize)
+        }
+        _ => Err(index_error(target, value)),
+    };
+    let from = bound(from, 0, f64::floor)?;
+    let to = bound(to, len, f64::ceil)?.max(from);
+
+    Ok(match target {
+        Value::String(s) => Value::String(s.chars().skip(from).take(to - from).collect()),
+        Value::Array(items) => Value::Array(items.get(from..to).unwrap_or_default().to_vec()),
+        _ => Value::Null,
+    })
+}
+
+/// `.[]`
+pub(super) fn iterate(target: &Value) -> Outputs {
+    match target {
+        Value::Array(items) => Ok(items.clone()),
+        Value::Object(members) => Ok(members.iter().map(|(_, value)| value.clone()).collect()),
+        _ => Err(JqError::Iterate {
+            target: describe(target),
+        }),
+    }
+}
+
+/// Set `key`, replacing its current value if there is one.
+pub(super) fn insert(members: &mut Vec<(String, Value)>, key: String, value: Value) {
+    match members.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, existing)) => *existing = value,
+        None => members.push((key, value)),
+    }
+}
+
+pub(super) fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+/// Strings as they are, anything else as compact JSON.
+pub(super) fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// jq's total order: null < false < true < numbers < strings < arrays < objects.
+///
+/// Objects compare their sorted keys first, then their values key by key.
+pub(super) fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+
+    match (a, b) {
+        (Value::Number(Number::Integer(x)), Value::Number(Number::Integer(y))) => x.cmp(y),
+        (Value::Number(x), Value::Number(y)) => x
+            .as_f64()
+            .partial_cmp(&y.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| compare(x, y))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(x.len().cmp(&y.len())),
+        (Value::Object(_), Value::Object(_)) => {
+            let (x_keys, y_keys) = (sorted_keys(a), sorted_keys(b));
+            x_keys.cmp(&y_keys).then_with(|| {
+                x_keys
+                    .iter()
+                    .map(|key| match (a.get(key), b.get(key)) {
+                        (Some(x), Some(y)) => compare(x, y),
+                        _ => Ordering::Equal,
+                    })
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Keys of an object, sorted and without duplicates.
+pub(super) fn sorted_keys(value: &Value) -> Vec<&str> {
+    let mut keys = match value {
+        Value::Object(members) => members.iter().map(|(k, _)| k.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    keys.sort_unstable();
+    keys.dedup();
+    keys
+}
+
+/// A value for error messages, `number (1)`, long values are cut short.
+pub(super) fn describe(value: &Value) -> String {
+    const MAX: usize = 11;
+    let mut text = value.to_string();
+    if text.len() > MAX {
+        let mut end = MAX;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        text.push_str("...");
+    }
+    format!("{} ({text})", value.type_name())
+}
+
+fn operands(lhs: &Value, rhs: &Value, op: &'static str) -> JqError {
+    JqError::Operands {
+        left: describe(lhs),
+        right: describe(rhs),
+        op,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    fn run(filter: &str, input: Value) -> Outputs {
+        eval(&super::super::parser::parse(filter)?, &input, &Scope::Root)
+    }
+
+    #[test]
+    fn test_number() {
+        assert_eq!(number(2.0), json!(2));
+        assert_eq!(number(2.5), json!(2.5));
+        assert_eq!(number(1e300 * 1e300), json!(f64::MAX));
+        assert_eq!(number(f64::NEG_INFINITY), json!(-f64::MAX));
+        assert_eq!(
+            number(9_007_199_254_740_992.0),
+            json!(9_007_199_254_740_992.0)
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(run("1e1000", json!(null)).unwrap(), [json!(f64::MAX)]);
+        assert_eq!(run("-1e1000", json!(null)).unwrap(), [json!(-f64::MAX)]);
+        assert_eq!(run(". * 10", json!(1e308)).unwrap(), [json!(f64::MAX)]);
+        // Integers overflow into floats instead of wrapping.
+        assert_eq!(
+            run(". + 1", json!(i64::MAX)).unwrap(),
+            [json!(9_223_372_036_854_775_808.0)]
+        );
+        assert_eq!(
+            run("7 / 2, 6 / 3, 7 % 3", json!(null)).unwrap(),
+            [json!(3.5), json!(2), json!(1)]
+        );
+        assert_eq!(
+            run("1 / 0", json!(null)).unwrap_err().to_string(),
+            "number (1) and number (0) cannot be divided because the divisor is zero."
+        );
+        assert_eq!(
+            run(". + {b: 2}", json!({ "a": 1, "b": 1 })).unwrap(),
+            [json!({ "a": 1, "b": 2 })]
+        );
+    }
+
+    #[test]
+    fn test_generators() {
+        // The rightmost operand varies slowest.
+        assert_eq!(
+            run("(1, 2) + (10, 20)", json!(null)).unwrap(),
+            [json!(11), json!(12), json!(21), json!(22)]
+        );
+        assert_eq!(
+            run(".[] as $x | $x * 2", json!([1, 2])).unwrap(),
+            [json!(2), json!(4)]
+        );
+        assert_eq!(
+            run("reduce .[] as $x (0; . + $x)", json!([1, 2, 3])).unwrap(),
+            [json!(6)]
+        );
+        assert_eq!(
+            run(".a?, .b // \"none\"", json!([])).unwrap(),
+            [json!("none")]
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let ordered = [
+            json!(null),
+            json!(false),
+            json!(true),
+            json!(-1),
+            json!(0.5),
+            json!("a"),
+            json!([]),
+            json!({}),
+        ];
+        for pair in ordered.windows(2) {
+            assert_eq!(compare(&pair[0], &pair[1]), Ordering::Less, "{pair:?}");
+        }
+        assert_eq!(
+            compare(&json!({ "a": 1, "b": 2 }), &json!({ "b": 2, "a": 1 })),
+            Ordering::Equal
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use super::parser::MAX_DEPTH;
+use crate::errors::JqError;
+use std::{iter::Peekable, str::Chars};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Token {
+    Dot,
+    DotDot,
+    /// `.name`
+    Field(String),
+    /// Names, keywords included.
+    Ident(String),
+    /// `$name`
+    Var(String),
+    Number(f64),
+    Str(Vec<Part>),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Pipe,
+    Comma,
+    Colon,
+    Semicolon,
+    Question,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `//`
+    Alt,
+}
+
+/// A piece of a string literal.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Part {
+    Literal(String),
+    /// The tokens of a `\(...)` interpolation.
+    Interpolation(Vec<(Token, usize)>),
+}
+
+impl Token {
+    /// How the token reads in error messages.
+    pub(super) fn describe(&self) -> String {
+        let s = match self {
+            Token::Dot => ".",
+            Token::DotDot => "..",
+            Token::Field(name) => return format!(".{name}"),
+            Token::Ident(name) => return name.clone(),
+            Token::Var(name) => return format!("${name}"),
+            Token::Number(n) => return n.to_string(),
+            Token::Str(_) => "string",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::Pipe => "|",
+            Token::Comma => ",",
+            Token::Colon => ":",
+            Token::Semicolon => ";",
+            Token::Question => "?",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Eq => "==",
+            Token::Ne => "!=",
+            Token::Lt => "<",
+            Token::Le => "<=",
+            Token::Gt => ">",
+            Token::Ge => ">=",
+            Token::Alt => "//",
+        };
+        String::from(s)
+    }
+}
+
+pub(super) struct Lexer<'a> {
+    input: Peekable<Chars<'a>>,
+    /// 1-based column of the next char.
+    column: usize,
+    /// How many `\(...)` interpolations the next char is inside of.
+    depth: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(filter: &'a str) -> Self {
+        Lexer {
+            input: filter.chars().peekable(),
+            column: 1,
+            depth: 0,
+        }
+    }
+
+    /// Every token of the filter along with the column it starts at.
+    pub fn lex(mut self) -> Result<Vec<(Token, usize)>, JqError> {
+        self.tokens(false)
+    }
+
+    /// Read tokens until the end of input, or the `)` closing an interpolation.
+    fn tokens(&mut self, interpolation: bool) -> Result<Vec<(Token, usize)>, JqError> {
+        let mut tokens = Vec::new();
+        let mut depth = 0_usize;
+
+        loop {
+            self.skip_whitespace();
+            let column = self.column;
+            let Some(c) = self.bump() else {
+                if interpolation {
+                    Err(self.syntax_error("')' closing the interpolation"))?
+                }
+                return Ok(tokens);
+            };
+
+            let token = match c {
+                '.' => match self.peek() {
+                    Some('.') => {
+                        self.bump();
+                        Token::DotDot
+                    }
+                    Some(c) if is_ident_start(c) => Token::Field(self.read_ident()),
+                    _ => Token::Dot,
+                },
+                '$' => match self.peek() {
+                    Some(c) if is_ident_start(c) => Token::Var(self.read_ident()),
+                    _ => Err(self.syntax_error("variable name"))?,
+                },
+                '"' => self.read_string()?,
+                '(' => {
+                    depth += 1;
+                    Token::LParen
+                }
+                ')' if interpolation && depth == 0 => return Ok(tokens),
+                ')' => {
+                    depth = depth.saturating_sub(1);
+                    Token::RParen
+                }
+                '[' => Token::LBracket,
+                ']' => Token::RBracket,
+                '{' => Token::LBrace,
+                '}' => Token::RBrace,
+                '|' => Token::Pipe,
+                ',' => Token::Comma,
+                ':' => Token::Colon,
+                ';' => Token::Semicolon,
+                '?' => Token::Question,
+                '+' => Token::Plus,
+                '-' => Token::Minus,
+                '*' => Token::Star,
+                '%' => Token::Percent,
+                '/' if self.eat('/') => Token::Alt,
+                '/' => Token::Slash,
+                '=' if self.eat('=') => Token::Eq,
+                '!' if self.eat('=') => Token::Ne,
+                '<' if self.eat('=') => Token::Le,
+                '<' => Token::Lt,
+                '>' if self.eat('=') => Token::Ge,
+                '>' => Token::Gt,
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                    continue;
+                }
+                c if c.is_ascii_digit() => self.read_number(c)?,
+                c if is_ident_start(c) => {
+                    let mut name = String::from(c);
+                    name.push_str(&self.read_ident());
+                    Token::Ident(name)
+                }
+                c => Err(JqError::Syntax {
+                    column,
+                    expected: String::from("token"),
+                    found: format!("{c:?}"),
+                })?,
+            };
+            tokens.push((token, column));
+        }
+    }
+
+    /// Read a string literal, the opening quote is already consumed.
+    fn read_string(&mut self) -> Result<Token, JqError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+
+        loop {
+            match self.bump() {
+                Some('"') => break,
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('(') => {
+                            if !literal.is_empty() {
+                                parts.push(Part::Literal(std::mem::take(&mut literal)));
+                            }
+                            // Each level recurses, like nesting in the parser.
+                            self.depth += 1;
+                            if self.depth > MAX_DEPTH {
+                                Err(JqError::TooDeep {
+                                    column: self.column - 2,
+                                    max: MAX_DEPTH,
+                                })?
+                            }
+                            parts.push(Part::Interpolation(self.tokens(true)?));
+                            self.depth -= 1;
+                            continue;
+                        }
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{08}',
+                        Some('f') => '\u{0C}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let mut code = 0;
+                            for _ in 0..4 {
+                                let digit = self
+                                    .bump()
+                                    .and_then(|c| c.to_digit(16))
+                                    .ok_or_else(|| self.syntax_error("4 hex digits"))?;
+                                code = code * 16 + digit;
+                            }
+                            char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        _ => Err(self.syntax_error("escape sequence"))?,
+                    };
+                    literal.push(escaped);
+                }
+                Some(c) => literal.push(c),
+                None => Err(self.syntax_error("closing quote"))?,
+            }
+        }
+
+        if !literal.is_empty() || parts.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Token::Str(parts))
+    }
+
+    fn read_number(&mut self, initial_char: char) -> Result<Token, JqError> {
+        let mut s = String::from(initial_char);
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_digit() || *c == '.') {
+            s.push(c);
+            self.bump();
+        }
+        if let Some(e) = self.peek().filter(|c| matches!(c, 'e' | 'E')) {
+            s.push(e);
+            self.bump();
+            if let Some(sign) = self.peek().filter(|c| matches!(c, '+' | '-')) {
+                s.push(sign);
+                self.bump();
+            }
+            while let Some(c) = self.peek().filter(char::is_ascii_digit) {
+                s.push(c);
+                self.bump();
+            }
+        }
+
+        match s.parse() {
+            Ok(n) => Ok(Token::Number(n)),
+            Err(_) => Err(self.syntax_error("number")),
+        }
+    }
+
+    fn read_ident(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self
+            .peek()
+            .filter(|c| is_ident_start(*c) || c.is_ascii_digit())
+        {
+            name.push(c);
+            self.bump();
+        }
+        name
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.input.next()?;
+        self.column += 1;
+        Some(c)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.input.peek().copied()
+    }
+
+    /// Consume the next char if it is `expected`.
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            return true;
+        }
+        false
+    }
+
+    fn syntax_error(&mut self, expected: &str) -> JqError {
+        JqError::Syntax {
+            column: self.column,
+            expected: String::from(expected),
+            found: self
+                .peek()
+                .map_or(String::from("end of filter"), |c| format!("{c:?}")),
+        }
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tokens(filter: &str) -> Vec<Token> {
+        Lexer::new(filter)
+            .lex()
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            tokens(".a[0] | $x // .. != 1.5e2"),
+            [
+                Token::Field(String::from("a")),
+                Token::LBracket,
+                Token::Number(0.0),
+                Token::RBracket,
+                Token::Pipe,
+                Token::Var(String::from("x")),
+                Token::Alt,
+                Token::DotDot,
+                Token::Ne,
+                Token::Number(150.0),
+            ]
+        );
+        assert_eq!(tokens("1e1000"), [Token::Number(f64::INFINITY)]);
+
+        // Columns count chars, starting at 1.
+        let columns: Vec<usize> = Lexer::new("\"é\" |  $b")
+            .lex()
+            .unwrap()
+            .into_iter()
+            .map(|(_, column)| column)
+            .collect();
+        assert_eq!(columns, [1, 5, 8]);
+        assert_eq!(
+            Lexer::new(". é").lex().unwrap_err().to_string(),
+            "[column 3] Invalid Syntax, Expected token, found 'é'."
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(
+            tokens(r#""a\n\(.b + 1)c""#),
+            [Token::Str(vec![
+                Part::Literal(String::from("a\n")),
+                Part::Interpolation(vec![
+                    (Token::Field(String::from("b")), 7),
+                    (Token::Plus, 10),
+                    (Token::Number(1.0), 12),
+                ]),
+                Part::Literal(String::from("c")),
+            ])]
+        );
+        let nested = |depth: usize| format!("{}1{}", "\"\\(".repeat(depth), ")\"".repeat(depth));
+        assert!(Lexer::new(&nested(MAX_DEPTH)).lex().is_ok());
+        assert_eq!(
+            Lexer::new(&nested(20_000)).lex().unwrap_err().to_string(),
+            "[column 194] Nesting is deeper than 64 levels."
+        );
+        assert!(Lexer::new("\"open").lex().is_err());
+        assert!(Lexer::new("\"\\q\"").lex().is_err());
+    }
+}
@@ -0,0 +1,79 @@
+//! A jq compatible subset for reshaping documents.
+//!
+//! Supported: `.`, `..`, `.foo`, `."foo"`, `.[i]`, `.[from:to]`, `.[]`, the `?` suffix,
+//! `|`, `,`, arithmetic, comparisons, `and`/`or`/`//`, array and object construction
+//! (`{a, "b": .c, (.d): 1, $e}`), string interpolation, `if`/`elif`/`else`, `try`/`catch`,
+//! `reduce`, `... as $x | ...` and most of the builtins a reshaping filter needs, see
+//! `builtins::BUILTINS`.
+//!
+//! Not supported: function definitions, assignments (`|=`, `+=`, ...), paths (`path`,
+//! `del`, `getpath`), regexes, `@format` strings, destructuring and `$ENV`.
+//!
+//! Filters are evaluated eagerly, each step collects all its outputs before the next
+//! one runs.
+
+mod builtins;
+mod eval;
+mod lexer;
+mod parser;
+
+use crate::value::Value;
+use anyhow::Result;
+use eval::Scope;
+
+/// A parsed filter, ready to run against any number of inputs.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expr: parser::Expr,
+}
+
+impl Filter {
+    /// Parse `filter`. Unknown functions and variables are reported here rather than
+    /// when running it.
+    pub fn new(filter: &str) -> Result<Self> {
+        Ok(Filter {
+            expr: parser::parse(filter)?,
+        })
+    }
+
+    /// Every output of the filter for `input`, in order.
+    pub fn run(&self, input: &Value) -> Result<Vec<Value>> {
+        Ok(eval::eval(&self.expr, input, &Scope::Root)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_errors() {
+        let err = |filter: &str| Filter::new(filter).unwrap_err().to_string();
+        assert_eq!(
+            err(".a |"),
+            "[column 5] Invalid Syntax, Expected a filter, found end of filter."
+        );
+        assert_eq!(
+            err("map(.) | nope(1)"),
+            "[column 10] nope/1 is not defined."
+        );
+        assert_eq!(err(". as $x | $y"), "[column 11] $y is not defined.");
+        assert_eq!(err("\"\\(.a"), "[column 6] Invalid Syntax, Expected ')' closing the interpolation, found end of filter.");
+        assert!(Filter::new(&"(".repeat(200)).is_err());
+
+        let run = |filter: &str, input: Value| Filter::new(filter).unwrap().run(&input);
+        assert_eq!(
+            run(".a", json!(1)).unwrap_err().to_string(),
+            "Cannot index number with \"a\"."
+        );
+        assert_eq!(
+            run(".[] + 1", json!(["x"])).unwrap_err().to_string(),
+            "string (\"x\") and number (1) cannot be added."
+        );
+        assert_eq!(
+            run("error({a: 1})", json!(null)).unwrap_err().to_string(),
+            "{\"a\":1} (not a string)"
+        );
+    }
+}
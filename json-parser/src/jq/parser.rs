@@ -0,0 +1,626 @@
+use super::{
+    builtins::Builtin,
+    eval::number,
+    lexer::{Lexer, Part, Token},
+};
+use crate::{errors::JqError, value::Value};
+
+/// How deep parentheses, brackets and bodies may nest. Each level recurses through
+/// every precedence level, so this is lower than the document parser's limit.
+pub(super) const MAX_DEPTH: usize = 64;
+
+/// A parsed filter.
+#[derive(Debug, Clone)]
+pub(super) enum Expr {
+    Identity,
+    Literal(Value),
+    /// A string with `\(...)` interpolations.
+    Interpolated(Vec<Segment>),
+    Var(String),
+    Index(Box<Expr>, Box<Expr>),
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>),
+    Iterate(Box<Expr>),
+    /// `try body catch handler`, `body?` has no handler.
+    Try(Box<Expr>, Option<Box<Expr>>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    /// `a // b`
+    Alt(Box<Expr>, Box<Expr>),
+    Array(Option<Box<Expr>>),
+    Object(Vec<(Expr, Expr)>),
+    /// Conditions along with their branch, then the `else` branch.
+    If(Vec<(Expr, Expr)>, Box<Expr>),
+    /// `source as $name | body`
+    Bind(Box<Expr>, String, Box<Expr>),
+    /// `reduce source as $name (init; update)`
+    Reduce {
+        source: Box<Expr>,
+        name: String,
+        init: Box<Expr>,
+        update: Box<Expr>,
+    },
+    Call(Builtin, Vec<Expr>),
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Segment {
+    Literal(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Parse a whole filter.
+pub(super) fn parse(filter: &str) -> Result<Expr, JqError> {
+    let tokens = Lexer::new(filter).lex()?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: filter.chars().count() + 1,
+        vars: Vec::new(),
+        depth: 0,
+    };
+    parser.parse_all()
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    /// Column reported for errors at the end of input.
+    end: usize,
+    /// Variables in scope, innermost last.
+    vars: Vec<String>,
+    depth: usize,
+}
+
+impl Parser {
+    fn parse_all(&mut self) -> Result<Expr, JqError> {
+        let expr = self.parse_pipe()?;
+        if self.peek().is_some() {
+            Err(self.syntax_error("end of filter"))?
+        }
+        Ok(expr)
+    }
+
+    /// `a | b | c`, the loosest binding operator.
+    fn parse_pipe(&mut self) -> Result<Expr, JqError> {
+        let mut stages = vec![self.parse_comma()?];
+        while self.eat(&Token::Pipe) {
+            stages.push(self.parse_comma()?);
+        }
+        Ok(fold_right(stages, Expr::Pipe))
+    }
+
+    fn parse_comma(&mut self) -> Result<Expr, JqError> {
+        let mut expr = self.parse_alt()?;
+        while self.eat(&Token::Comma) {
+            expr = Expr::Comma(Box::new(expr), Box::new(self.parse_alt()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_alt(&mut self) -> Result<Expr, JqError> {
+        let mut alternatives = vec![self.parse_or()?];
+        while self.eat(&Token::Alt) {
+            alternatives.push(self.parse_or()?);
+        }
+        Ok(fold_right(alternatives, Expr::Alt))
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, JqError> {
+        let mut expr = self.parse_and()?;
+        while self.eat_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, JqError> {
+        let mut expr = self.parse_comparison()?;
+        while self.eat_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_comparison()?));
+        }
+        Ok(expr)
+    }
+
+    /// Comparisons don't chain, `a < b < c` is an error.
+    fn parse_comparison(&mut self) -> Result<Expr, JqError> {
+        let lhs = self.parse_additive()?;
+        let op = match self.peek() {
+            Some(Token::Eq) => BinOp::Eq,
+            Some(Token::Ne) => BinOp::Ne,
+            Some(Token::Lt) => BinOp::Lt,
+            Some(Token::Le) => BinOp::Le,
+            Some(Token::Gt) => BinOp::Gt,
+            Some(Token::Ge) => BinOp::Ge,
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        let rhs = self.parse_additive()?;
+        Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs)))
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, JqError> {
+        let mut expr = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinOp::Add,
+                Some(Token::Minus) => BinOp::Sub,
+                _ => return Ok(expr),
+            };
+            self.pos += 1;
+            let rhs = self.parse_multiplicative()?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(rhs));
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, JqError> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => BinOp::Mul,
+                Some(Token::Slash) => BinOp::Div,
+                Some(Token::Percent) => BinOp::Rem,
+                _ => return Ok(expr),
+            };
+            self.pos += 1;
+            let rhs = self.parse_unary()?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(rhs));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, JqError> {
+        let mut negate = false;
+        while self.eat(&Token::Minus) {
+            negate = !negate;
+        }
+        let expr = self.parse_postfix(true)?;
+        Ok(match negate {
+            true => Expr::Neg(Box::new(expr)),
+            false => expr,
+        })
+    }
+
+    /// A term followed by indexing, `?` and, when `bind` is set, `as $name | body`.
+    fn parse_postfix(&mut self, bind: bool) -> Result<Expr, JqError> {
+        let mut expr = self.nested(Self::parse_term)?;
+
+        loop {
+            expr = match (self.peek(), self.tokens.get(self.pos + 1).map(|(t, _)| t)) {
+                (Some(Token::Field(name)), _) => {
+                    let key = Expr::Literal(Value::from(name.as_str()));
+                    self.pos += 1;
+                    Expr::Index(Box::new(expr), Box::new(key))
+                }
+                (Some(Token::Dot), Some(Token::Str(_))) => {
+                    self.pos += 1;
+                    let key = self.parse_term()?;
+                    Expr::Index(Box::new(expr), Box::new(key))
+                }
+                (Some(Token::Dot), Some(Token::LBracket)) | (Some(Token::LBracket), _) => {
+                    self.eat(&Token::Dot);
+                    self.pos += 1;
+                    self.parse_brackets(expr)?
+                }
+                (Some(Token::Question), _) => {
+                    self.pos += 1;
+                    Expr::Try(Box::new(expr), None)
+                }
+                _ => break,
+            };
+        }
+
+        if bind && self.eat_keyword("as") {
+            let name = self.parse_var_name()?;
+            self.expect(&Token::Pipe)?;
+            self.vars.push(name.clone());
+            let body = self.nested(Self::parse_pipe);
+            self.vars.pop();
+            return Ok(Expr::Bind(Box::new(expr), name, Box::new(body?)));
+        }
+
+        Ok(expr)
+    }
+
+    /// `[]`, `[i]` or a `[from:to]` slice, the opening bracket is already consumed.
+    fn parse_brackets(&mut self, target: Expr) -> Result<Expr, JqError> {
+        let target = Box::new(target);
+        if self.eat(&Token::RBracket) {
+            return Ok(Expr::Iterate(target));
+        }
+
+        let from = match self.peek() {
+            Some(Token::Colon) => None,
+            _ => Some(Box::new(self.parse_pipe()?)),
+        };
+        if !self.eat(&Token::Colon) {
+            self.expect(&Token::RBracket)?;
+            return Ok(match from {
+                Some(key) => Expr::Index(target, key),
+                None => Expr::Iterate(target),
+            });
+        }
+
+        let to = match self.peek() {
+            Some(Token::RBracket) => None,
+            _ => Some(Box::new(self.parse_pipe()?)),
+        };
+        self.expect(&Token::RBracket)?;
+        Ok(Expr::Slice(target, from, to))
+    }
+
+    fn parse_term(&mut self) -> Result<Expr, JqError> {
+        let column = self.column();
+        let Some((token, _)) = self.tokens.get(self.pos).cloned() else {
+            Err(self.syntax_error("a filter"))?
+        };
+        self.pos += 1;
+
+        Ok(match token {
+            Token::Dot => match self.peek() {
+                Some(Token::Str(_)) => {
+                    let key = self.parse_term()?;
+                    Expr::Index(Box::new(Expr::Identity), Box::new(key))
+                }
+                _ => Expr::Identity,
+            },
+            Token::DotDot => Expr::Call(Builtin::Recurse, Vec::new()),
+            Token::Field(name) => Expr::Index(
+                Box::new(Expr::Identity),
+                Box::new(Expr::Literal(Value::from(name))),
+            ),
+            Token::Number(n) => Expr::Literal(number(n)),
+            Token::Str(parts) => self.parse_string(parts, column)?,
+            Token::Var(name) => match self.vars.contains(&name) {
+                true => Expr::Var(name),
+                false => Err(JqError::UndefinedVariable { column, name })?,
+            },
+            Token::LParen => {
+                let expr = self.parse_pipe()?;
+                self.expect(&Token::RParen)?;
+                expr
+            }
+            Token::LBracket => {
+                if self.eat(&Token::RBracket) {
+                    return Ok(Expr::Array(None));
+                }
+                let items = self.parse_pipe()?;
+                self.expect(&Token::RBracket)?;
+                Expr::Array(Some(Box::new(items)))
+            }
+            Token::LBrace => self.parse_object()?,
+            Token::Ident(name) => match name.as_str() {
+                "null" => Expr::Literal(Value::Null),
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "if" => self.parse_if()?,
+                "try" => {
+                    let body = self.parse_postfix(false)?;
+                    let handler = match self.eat_keyword("catch") {
+                        true => Some(Box::new(self.parse_postfix(false)?)),
+                        false => None,
+                    };
+                    Expr::Try(Box::new(body), handler)
+                }
+                "reduce" => self.parse_reduce()?,
+                "then" | "elif" | "else" | "end" | "as" | "catch" | "and" | "or" | "def" => {
+                    self.pos -= 1;
+                    Err(self.syntax_error("a filter"))?
+                }
+                _ => self.parse_call(name, column)?,
+            },
+            _ => {
+                self.pos -= 1;
+                Err(self.syntax_error("a filter"))?
+            }
+        })
+    }
+
+    /// A string literal, interpolations are parsed in the current scope.
+    fn parse_string(&mut self, parts: Vec<Part>, column: usize) -> Result<Expr, JqError> {
+        if let [Part::Literal(s)] = parts.as_slice() {
+            return Ok(Expr::Literal(Value::from(s.as_str())));
+        }
+
+        let mut segments = Vec::new();
+        for part in parts {
+            segments.push(match part {
+                Part::Literal(s) => Segment::Literal(s),
+                Part::Interpolation(tokens) => {
+                    let mut parser = Parser {
+                        tokens,
+                        pos: 0,
+                        end: column,
+                        vars: self.vars.clone(),
+                        depth: self.depth,
+                    };
+                    Segment::Expr(parser.parse_all()?)
+                }
+            });
+        }
+        Ok(Expr::Interpolated(segments))
+    }
+
+    /// `{a, "b": 1, (.c): 2, $d}`, the opening brace is already consumed.
+    fn parse_object(&mut self) -> Result<Expr, JqError> {
+        let mut entries = Vec::new();
+        if self.eat(&Token::RBrace) {
+            return Ok(Expr::Object(entries));
+        }
+
+        loop {
+            let column = self.column();
+            let (key, shorthand) = match self.tokens.get(self.pos).cloned() {
+                Some((Token::Ident(name), _)) => {
+                    self.pos += 1;
+                    let key = Expr::Literal(Value::from(name));
+                    (
+                        key.clone(),
+                        Some(Expr::Index(Box::new(Expr::Identity), Box::new(key))),
+                    )
+                }
+                Some((Token::Var(name), _)) => {
+                    let value = self.parse_term()?;
+                    (Expr::Literal(Value::from(name)), Some(value))
+                }
+                Some((Token::Str(parts), _)) => {
+                    self.pos += 1;
+                    let key = self.parse_string(parts, column)?;
+                    (
+                        key.clone(),
+                        Some(Expr::Index(Box::new(Expr::Identity), Box::new(key))),
+                    )
+                }
+                Some((Token::LParen, _)) => {
+                    self.pos += 1;
+                    let key = self.parse_pipe()?;
+                    self.expect(&Token::RParen)?;
+                    (key, None)
+                }
+                _ => Err(self.syntax_error("object key"))?,
+            };
+
+            let value = match shorthand {
+                _ if self.eat(&Token::Colon) => self.parse_object_value()?,
+                Some(value) => value,
+                None => Err(self.syntax_error(":"))?,
+            };
+            entries.push((key, value));
+
+            if self.eat(&Token::RBrace) {
+                return Ok(Expr::Object(entries));
+            }
+            self.expect(&Token::Comma)?;
+        }
+    }
+
+    /// Member values can't contain a bare `,`, which separates members.
+    fn parse_object_value(&mut self) -> Result<Expr, JqError> {
+        let mut stages = vec![self.parse_alt()?];
+        while self.eat(&Token::Pipe) {
+            stages.push(self.parse_alt()?);
+        }
+        Ok(fold_right(stages, Expr::Pipe))
+    }
+
+    /// `if c then a elif c then b else d end`, `else` is optional.
+    fn parse_if(&mut self) -> Result<Expr, JqError> {
+        let mut branches = Vec::new();
+        loop {
+            let condition = self.parse_pipe()?;
+            self.expect_keyword("then")?;
+            branches.push((condition, self.parse_pipe()?));
+            if !self.eat_keyword("elif") {
+                break;
+            }
+        }
+
+        let otherwise = match self.eat_keyword("else") {
+            true => self.parse_pipe()?,
+            false => Expr::Identity,
+        };
+        self.expect_keyword("end")?;
+        Ok(Expr::If(branches, Box::new(otherwise)))
+    }
+
+    fn parse_reduce(&mut self) -> Result<Expr, JqError> {
+        let source = self.parse_postfix(false)?;
+        self.expect_keyword("as")?;
+        let name = self.parse_var_name()?;
+        self.expect(&Token::LParen)?;
+        let init = self.parse_pipe()?;
+        self.expect(&Token::Semicolon)?;
+
+        self.vars.push(name.clone());
+        let update = self.parse_pipe();
+        self.vars.pop();
+        let update = update?;
+        self.expect(&Token::RParen)?;
+
+        Ok(Expr::Reduce {
+            source: Box::new(source),
+            name,
+            init: Box::new(init),
+            update: Box::new(update),
+        })
+    }
+
+    /// `name` or `name(a; b)`.
+    fn parse_call(&mut self, name: String, column: usize) -> Result<Expr, JqError> {
+        let mut args = Vec::new();
+        if self.eat(&Token::LParen) {
+            loop {
+                args.push(self.parse_pipe()?);
+                if self.eat(&Token::RParen) {
+                    break;
+                }
+                self.expect(&Token::Semicolon)?;
+            }
+        }
+
+        match Builtin::lookup(&name, args.len()) {
+            Some(builtin) => Ok(Expr::Call(builtin, args)),
+            None => Err(JqError::UnknownFunction {
+                column,
+                name,
+                arity: args.len(),
+            }),
+        }
+    }
+
+    fn parse_var_name(&mut self) -> Result<String, JqError> {
+        match self.tokens.get(self.pos).cloned() {
+            Some((Token::Var(name), _)) => {
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.syntax_error("variable")),
+        }
+    }
+
+    /// Run `parse` one nesting level deeper, guarding against stack overflows.
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Expr, JqError>) -> Result<Expr, JqError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            Err(JqError::TooDeep {
+                column: self.column(),
+                max: MAX_DEPTH,
+            })?
+        }
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |(_, column)| *column)
+    }
+
+    /// Consume the next token if it is `expected`.
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(name)) if name == keyword) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), JqError> {
+        if !self.eat(expected) {
+            Err(self.syntax_error(&expected.describe()))?
+        }
+        Ok(())
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), JqError> {
+        if !self.eat_keyword(keyword) {
+            Err(self.syntax_error(keyword))?
+        }
+        Ok(())
+    }
+
+    fn syntax_error(&self, expected: &str) -> JqError {
+        JqError::Syntax {
+            column: self.column(),
+            expected: String::from(expected),
+            found: self
+                .peek()
+                .map_or(String::from("end of filter"), Token::describe),
+        }
+    }
+}
+
+/// Nest `exprs` to the right, `a op (b op c)`.
+fn fold_right(mut exprs: Vec<Expr>, op: fn(Box<Expr>, Box<Expr>) -> Expr) -> Expr {
+    let mut expr = exprs.pop().unwrap_or(Expr::Identity);
+    while let Some(lhs) = exprs.pop() {
+        expr = op(Box::new(lhs), Box::new(expr));
+    }
+    expr
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_precedence() {
+        let Ok(Expr::Binary(BinOp::Add, lhs, rhs)) = parse("1 + 2 * 3") else {
+            panic!("not an addition");
+        };
+        assert!(matches!(*lhs, Expr::Literal(ref n) if *n == json!(1)));
+        assert!(matches!(*rhs, Expr::Binary(BinOp::Mul, ..)));
+
+        // `|` binds loosest, then `,`, then `//`.
+        let Ok(Expr::Pipe(_, rhs)) = parse(".a | .b, .c // 1") else {
+            panic!("not a pipe");
+        };
+        let Expr::Comma(_, rhs) = *rhs else {
+            panic!("not a comma");
+        };
+        assert!(matches!(*rhs, Expr::Alt(..)));
+    }
+
+    #[test]
+    fn test_literals() {
+        assert!(matches!(parse("1e1000"), Ok(Expr::Literal(n)) if n == json!(f64::MAX)));
+        assert!(matches!(parse("\"a\""), Ok(Expr::Literal(s)) if s == json!("a")));
+        assert!(
+            matches!(parse("\"a\\(.b)\""), Ok(Expr::Interpolated(segments)) if segments.len() == 2)
+        );
+        assert!(matches!(parse("[]"), Ok(Expr::Array(None))));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |filter: &str| parse(filter).unwrap_err().to_string();
+        assert_eq!(err("$x"), "[column 1] $x is not defined.");
+        assert_eq!(
+            err("1 +"),
+            "[column 4] Invalid Syntax, Expected a filter, found end of filter."
+        );
+        assert_eq!(
+            err("if . then 1"),
+            "[column 12] Invalid Syntax, Expected end, found end of filter."
+        );
+        assert!(parse(&"[".repeat(MAX_DEPTH + 1)).is_err());
+        assert!(parse(&format!(
+            "{}1{}",
+            "[".repeat(MAX_DEPTH - 1),
+            "]".repeat(MAX_DEPTH - 1)
+        ))
+        .is_ok());
+    }
+}
@@ -0,0 +1,419 @@
+//! On-demand access to a document without building a full `Value` tree.
+//!
+//! `Document::new` makes a single pass over the input recording where every token
+//! starts and which brackets match, navigating by key or index then only looks at the
+//! tokens on the way and skips whole subtrees in one step. Values are only decoded
+//! when asked for, so syntax errors inside untouched subtrees go unnoticed.
+
+use crate::{
+    errors::{Error, ErrorKind},
+    parser::parse_str,
+    stage1::{self, Kernel},
+    token::Position,
+    value::Value,
+};
+use anyhow::Result;
+
+/// An indexed document borrowing its input.
+pub struct Document<'a> {
+    input: &'a str,
+    /// Byte offset where each token starts, strings are indexed by their opening quote.
+    tokens: Vec<usize>,
+    /// For tokens opening an array or object the index of the closing token,
+    /// for every other token its own index.
+    ends: Vec<usize>,
+}
+
+/// A value inside a `Document`.
+#[derive(Clone, Copy)]
+pub struct Node<'a> {
+    doc: &'a Document<'a>,
+    /// Index of the value's first token.
+    token: usize,
+}
+
+impl<'a> Document<'a> {
+    /// Index `input`, only brackets and strings are checked at this point.
+    pub fn new(input: &'a str) -> Result<Self> {
+        let tokens = stage1::index_tokens(input.as_bytes(), Kernel::detect())?;
+        let ends = match_brackets(input, &tokens)?;
+
+        if tokens.is_empty() {
+            Err(Error::at(
+                ErrorKind::EmptyFile,
+                Position::at(input.as_bytes(), input.len()),
+            ))?
+        }
+
+        let doc = Document {
+            input,
+            tokens,
+            ends,
+        };
+        if doc.end(0) + 1 != doc.tokens.len() {
+            Err(doc.invalid_syntax(doc.end(0) + 1, "end of input"))?
+        }
+        Ok(doc)
+    }
+
+    pub fn root(&self) -> Node<'_> {
+        Node {
+            doc: self,
+            token: 0,
+        }
+    }
+
+    /// Byte of the input the `token`th token starts with.
+    fn byte(&self, token: usize) -> Option<u8> {
+        self.tokens
+            .get(token)
+            .and_then(|&offset| self.input.as_bytes().get(offset).copied())
+    }
+
+    /// Index of the value's last token.
+    fn end(&self, token: usize) -> usize {
+        self.ends.get(token).copied().unwrap_or(token)
+    }
+
+    /// Source text of the value starting at `token`.
+    fn raw(&self, token: usize) -> &'a str {
+        let start = self.tokens.get(token).copied().unwrap_or(self.input.len());
+        let end = match self.byte(token) {
+            Some(b'{' | b'[') => self
+                .tokens
+                .get(self.end(token))
+                .map_or(start, |end| end + 1),
+            // Scalars run until the next token.
+            _ => self
+                .tokens
+                .get(token + 1)
+                .copied()
+                .unwrap_or(self.input.len()),
+        };
+        self.input
+            .get(start..end)
+            .unwrap_or_default()
+            .trim_end_matches([' ', '\t', '\n', '\r'])
+    }
+
+    /// Make sure a value starts at `token`.
+    fn expect_value(&self, token: usize) -> Result<(), Error> {
+        match self.byte(token) {
+            None | Some(b'}' | b']' | b':' | b',') => Err(self.invalid_syntax(token, "value")),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn expect(&self, token: usize, expected: u8) -> Result<(), Error> {
+        if self.byte(token) != Some(expected) {
+            Err(self.invalid_syntax(token, &(expected as char).to_string()))?
+        }
+        Ok(())
+    }
+
+    fn invalid_syntax(&self, token: usize, expected: &str) -> Error {
+        let offset = self.tokens.get(token).copied().unwrap_or(self.input.len());
+        let expected = String::from(expected);
+        Error::at(
+            ErrorKind::InvalidSyntax { expected },
+            Position::at(self.input.as_bytes(), offset),
+        )
+    }
+}
+
+impl<'a> Node<'a> {
+    /// The value's JSON type, judged from its first byte.
+    pub fn type_name(&self) -> &'static str {
+        match self.doc.byte(self.token) {
+            Some(b'{') => "object",
+            Some(b'[') => "array",
+            Some(b'"') => "string",
+            Some(b't' | b'f') => "boolean",
+            Some(b'n') => "null",
+            _ => "number",
+        }
+    }
+
+    /// Source text of the value, untouched.
+    pub fn raw(&self) -> &'a str {
+        self.doc.raw(self.token)
+    }
+
+    /// Decode the value and everything below it, errors point into the document.
+    pub fn value(&self) -> Result<Value> {
+        let doc = self.doc;
+        let start = doc
+            .tokens
+            .get(self.token)
+            .copied()
+            .unwrap_or(doc.input.len());
+        let origin = Position::at(doc.input.as_bytes(), start);
+        Ok(parse_str(self.raw()).map_err(|e| e.shifted(origin))?)
+    }
+
+    /// Member named `key`, `None` if missing or this is not an object.
+    pub fn get(&self, key: &str) -> Result<Option<Node<'a>>> {
+        for member in self.members() {
+            let (name, value) = member?;
+            if name == key {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Item at `index`, `None` if out of bounds or this is not an array.
+    pub fn index(&self, index: usize) -> Result<Option<Node<'a>>> {
+        match self.items().nth(index) {
+            Some(item) => Ok(Some(item?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Follow an RFC 6901 JSON Pointer such as `/items/0/name`.
+    pub fn pointer(&self, pointer: &str) -> Result<Option<Node<'a>>> {
+        if !pointer.is_empty() && !pointer.starts_with('/') {
+            return Ok(None);
+        }
+        let mut node = *self;
+        for segment in pointer.split('/').skip(1) {
+            let segment = segment.replace("~1", "/").replace("~0", "~");
+            let next = match node.type_name() {
+                "array" => match segment.parse::<usize>() {
+                    Ok(i) => node.index(i)?,
+                    Err(_) => None,
+                },
+                _ => node.get(&segment)?,
+            };
+            match next {
+                Some(next) => node = next,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(node))
+    }
+
+    /// Items of an array, empty for every other type.
+    pub fn items(&self) -> Items<'a> {
+        let doc = self.doc;
+        let next = match doc.byte(self.token) {
+            Some(b'[') if doc.byte(self.token + 1) == Some(b']') => None,
+            Some(b'[') => Some(self.token + 1),
+            _ => None,
+        };
+        Items { doc, next }
+    }
+
+    /// `(key, value)` pairs of an object, empty for every other type.
+    pub fn members(&self) -> Members<'a> {
+        let doc = self.doc;
+        let next = match doc.byte(self.token) {
+            Some(b'{') if doc.byte(self.token + 1) == Some(b'}') => None,
+            Some(b'{') => Some(self.token + 1),
+            _ => None,
+        };
+        Members { doc, next }
+    }
+}
+
+/// Iterator over the items of an array `Node`.
+pub struct Items<'a> {
+    doc: &'a Document<'a>,
+    /// Token of the next item.
+    next: Option<usize>,
+}
+
+impl<'a> Iterator for Items<'a> {
+    type Item = Result<Node<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.next.take()?;
+        let doc = self.doc;
+
+        if let Err(e) = doc.expect_value(token) {
+            return Some(Err(e.into()));
+        }
+
+        // Jump over the item's subtree to the separator.
+        let after = doc.end(token) + 1;
+        match doc.byte(after) {
+            Some(b',') => self.next = Some(after + 1),
+            Some(b']') => {}
+            _ => return Some(Err(doc.invalid_syntax(after, ", or ]").into())),
+        }
+
+        Some(Ok(Node { doc, token }))
+    }
+}
+
+/// Iterator over the members of an object `Node`.
+pub struct Members<'a> {
+    doc: &'a Document<'a>,
+    /// Token of the next key.
+    next: Option<usize>,
+}
+
+impl<'a> Members<'a> {
+    fn read_member(&mut self, key: usize) -> Result<(String, Node<'a>)> {
+        let doc = self.doc;
+
+        doc.expect(key, b'"')?;
+        doc.expect(key + 1, b':')?;
+        let name = match parse_str(doc.raw(key))? {
+            Value::String(name) => name,
+            _ => Err(doc.invalid_syntax(key, "key"))?,
+        };
+
+        let value = key + 2;
+        doc.expect_value(value)?;
+
+        let after = doc.end(value) + 1;
+        match doc.byte(after) {
+            Some(b',') => self.next = Some(after + 1),
+            Some(b'}') => {}
+            _ => Err(doc.invalid_syntax(after, ", or }"))?,
+        }
+
+        Ok((name, Node { doc, token: value }))
+    }
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = Result<(String, Node<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.next.take()?;
+        Some(self.read_member(key))
+    }
+}
+
+/// Pair every opening bracket with its closing one.
+fn match_brackets(input: &str, tokens: &[usize]) -> Result<Vec<usize>, Error> {
+    let bytes = input.as_bytes();
+    let mut ends = (0..tokens.len()).collect::<Vec<_>>();
+    let mut open = Vec::<usize>::new();
+
+    for (token, &offset) in tokens.iter().enumerate() {
+        let byte = bytes.get(offset).copied().unwrap_or_default();
+        match byte {
+            b'{' | b'[' => open.push(token),
+            b'}' | b']' => {
+                let opening = open.pop().and_then(|o| Some((o, *tokens.get(o)?)));
+                match opening.and_then(|(o, offset)| Some((o, *bytes.get(offset)?))) {
+                    Some((o, b'{')) if byte == b'}' => ends[o] = token,
+                    Some((o, b'[')) if byte == b']' => ends[o] = token,
+                    _ => Err(Error::at(
+                        ErrorKind::UnexpectedToken {
+                            token: byte as char,
+                        },
+                        Position::at(bytes, offset),
+                    ))?,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let end = Position::at(bytes, bytes.len());
+    match open.last().and_then(|&o| bytes.get(*tokens.get(o)?)) {
+        Some(b'{') => Err(Error::at(ErrorKind::UnclosedObject, end)),
+        Some(_) => Err(Error::at(ErrorKind::UnclosedArray, end)),
+        None => Ok(ends),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"{
+        "id": 42,
+        "skip": {"deep": [[1, 2], {"x": "]"}], "s": "\"{"},
+        "items": [true, {"name": "abc"}, null],
+        "a/b": -1.5e3
+    }"#;
+
+    #[test]
+    fn test_navigate() {
+        let doc = Document::new(INPUT).unwrap();
+        let root = doc.root();
+
+        assert_eq!(root.type_name(), "object");
+        assert_eq!(root.get("id").unwrap().unwrap().raw(), "42");
+        assert_eq!(
+            root.get("skip").unwrap().unwrap().raw(),
+            r#"{"deep": [[1, 2], {"x": "]"}], "s": "\"{"}"#
+        );
+        assert!(root.get("missing").unwrap().is_none());
+
+        let items = root.get("items").unwrap().unwrap();
+        assert_eq!(items.items().count(), 3);
+        assert_eq!(
+            items
+                .index(1)
+                .unwrap()
+                .unwrap()
+                .get("name")
+                .unwrap()
+                .unwrap()
+                .value()
+                .unwrap(),
+            Value::String(String::from("abc"))
+        );
+        assert!(items.index(3).unwrap().is_none());
+
+        assert_eq!(root.pointer("/a~1b").unwrap().unwrap().raw(), "-1.5e3");
+        assert_eq!(
+            root.pointer("/items/2").unwrap().unwrap().type_name(),
+            "null"
+        );
+        assert_eq!(root.pointer("").unwrap().unwrap().type_name(), "object");
+        assert!(root.pointer("id").unwrap().is_none());
+        assert!(root.pointer("/items/x").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_raw_keeps_non_json_whitespace() {
+        // U+00A0 isn't json whitespace, it is part of the invalid number.
+        let doc = Document::new("[1\u{a0}, 2 \r\n]").unwrap();
+        let first = doc.root().index(0).unwrap().unwrap();
+        assert_eq!(first.raw(), "1\u{a0}");
+        assert!(first.value().is_err());
+        assert_eq!(doc.root().index(1).unwrap().unwrap().raw(), "2");
+    }
+
+    #[test]
+    fn test_value_matches_parser() {
+        let doc = Document::new(INPUT).unwrap();
+
+        assert_eq!(doc.root().value().unwrap(), parse_str(INPUT).unwrap());
+    }
+
+    #[test]
+    fn test_value_error_position() {
+        let input = "{\"a\": 1,\n \"b\": [1,]}";
+        let doc = Document::new(input).unwrap();
+        let err = doc.root().get("b").unwrap().unwrap().value().unwrap_err();
+        let err = err.downcast_ref::<Error>().unwrap();
+        assert_eq!(err, &parse_str(input).unwrap_err());
+        assert_eq!(
+            err.to_string(),
+            "[line 2, column 9] E0003 trailing-comma: Trailing Comma."
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Document::new("").is_err());
+        assert!(Document::new("[1, 2").is_err());
+        assert!(Document::new("{\"a\": [}").is_err());
+        assert!(Document::new("\"open").is_err());
+        assert!(Document::new("[] []").is_err());
+
+        let doc = Document::new("{\"a\" 1}").unwrap();
+        assert!(doc.root().get("a").is_err());
+
+        let doc = Document::new("[1,]").unwrap();
+        assert!(doc.root().index(1).is_err());
+    }
+}
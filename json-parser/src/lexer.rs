@@ -1,247 +1,300 @@
-use crate::{errors::LexError, token::Token};
-use std::{iter::Peekable, str::Chars};
+use crate::{
+    errors::{Error, ErrorKind},
+    token::{Position, Span, Token},
+    value::Number,
+};
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{iter::Peekable, str::Chars};
 
 pub struct Lexer<'a> {
+    /// The whole input, strings without escapes are sliced out of it.
+    source: &'a str,
     input: Peekable<Chars<'a>>,
-    line: i16,
+    line: usize,
+    column: usize,
+    offset: usize,
+    /// Where the token being read starts.
+    start: Position,
 }
 
 impl<'a> Lexer<'a> {
-    /// Parse "key":"value".
-    fn parse_kv(&mut self) -> Result<Vec<Token>, LexError> {
-        let mut tokens = Vec::<Token>::new();
-
-        // Parse key.
-        let key = self.read_string()?;
-        tokens.push(key);
-
-        // Parse Colon.
-        if self
-            .read()
-            .is_some_and(|c| c.to_string() == Token::Colon.literal())
-        {
-            tokens.push(Token::Colon);
-        } else {
-            Err(LexError::InvalidSyntax {
-                line: self.line,
-                expected: String::from(":"),
-            })?
-        };
-
-        // Parse Value.
-        if let Some(char) = self.read() {
-            match char {
-                '"' => tokens.push(self.read_string()?),
-                'n' => tokens.push(self.read_null()?),
-                't' => tokens.push(self.read_boolean_true()?),
-                'f' => tokens.push(self.read_boolean_false()?),
-                '{' => {
-                    tokens.push(Token::LBraces);
-                    tokens.append(&mut self.parse_kv()?)
-                }
-                '[' => tokens.push(self.read_array()?),
-                c => {
-                    if is_number(c) {
-                        tokens.push(self.read_number(c)?);
-                    } else {
-                        Err(LexError::InvalidSyntax {
-                            line: self.line,
-                            expected: String::from("value"),
-                        })?
-                    }
-                }
-            }
-        };
-        Ok(tokens)
-    }
-
+    /// Read the rest of a keyword and make sure it spells `expected_token`.
     fn read_keyword(
         &mut self,
-        mut buf: String,
-        stop_at: char,
-        expected_token: Token,
-    ) -> Result<Token, LexError> {
-        let mut ended = false;
-
-        for c in self.input.by_ref() {
-            if c == stop_at {
-                ended = true;
-                break;
-            } else {
-                buf.push(c)
-            }
-        }
-
-        if !ended {
-            Err(LexError::InvalidSyntax {
-                line: self.line,
-                expected: String::from(","),
-            })?
+        initial_char: char,
+        expected_token: Token<'a>,
+    ) -> Result<Token<'a>, Error> {
+        let mut buf = String::from(initial_char);
+
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_alphabetic()) {
+            buf.push(c);
+            self.bump();
         }
 
         if buf != expected_token.literal() {
-            Err(LexError::InvalidKeyword {
-                line: self.line,
+            Err(self.error(ErrorKind::InvalidKeyword {
                 keyword: buf,
                 maybe: expected_token.literal(),
-            })?
+            }))?
         }
 
         Ok(expected_token)
     }
 
-    /// Read string between double quotes.
+    /// Read string between double quotes, the opening quote is already consumed.
     ///
     /// ## Errors
     /// - If string is not terminated.
-    /// - If `:` is encountered before closing quote.
-    fn read_string(&mut self) -> Result<Token, LexError> {
-        let mut buf = String::new();
+    /// - If a newline is encountered before closing quote.
+    /// - If it contains other unescaped control characters.
+    fn read_string(&mut self) -> Result<Token<'a>, Error> {
+        let start = self.offset;
+        // Only allocated once an escape shows up.
+        let mut buf: Option<String> = None;
         let mut ended = false;
 
-        for c in self.input.by_ref() {
+        loop {
+            let at = self.position();
+            let Some(c) = self.bump() else { break };
             match c {
                 '"' => {
                     ended = true;
                     break;
                 }
-                ':' => Err(LexError::MissingClosingQuote { line: self.line })?,
-                ch => buf.push(ch),
+                '\\' => {
+                    let before = self.source.get(start..self.offset - 1);
+                    let buf = buf.get_or_insert_with(|| before.unwrap_or_default().to_string());
+                    self.read_escape(buf)?
+                }
+                '\n' => Err(Error::new(
+                    ErrorKind::MissingClosingQuote,
+                    Span {
+                        start: self.start,
+                        end: at,
+                    },
+                ))?,
+                ch if ch < '\u{20}' => Err(Error::new(
+                    ErrorKind::ControlCharacter { token: ch },
+                    Span {
+                        start: at,
+                        end: self.position(),
+                    },
+                ))?,
+                ch => {
+                    if let Some(buf) = buf.as_mut() {
+                        buf.push(ch)
+                    }
+                }
             }
         }
 
         if !ended {
-            Err(LexError::UnterminatedString { line: self.line })?
+            Err(self.error(ErrorKind::UnterminatedString))?
         }
-        Ok(Token::Literal(buf))
+        Ok(Token::Literal(match buf {
+            Some(buf) => Cow::Owned(buf),
+            None => Cow::Borrowed(self.source.get(start..self.offset - 1).unwrap_or_default()),
+        }))
     }
 
-    fn read_number(&mut self, initial_char: char) -> Result<Token, LexError> {
-        let mut s = String::from(initial_char);
-        let mut ended = false;
-
-        for c in self.input.by_ref() {
-            if is_number(c) {
-                s.push(c);
-            } else {
-                ended = true;
-                break;
+    /// Read the escape following a backslash inside a string into `buf`.
+    ///
+    /// Lone surrogates can't be held by a `String` and become U+FFFD.
+    fn read_escape(&mut self, buf: &mut String) -> Result<(), Error> {
+        let escaped = match self.bump() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{08}',
+            Some('f') => '\u{0C}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let high = self.read_hex()?;
+                if !(0xD800..0xDC00).contains(&high) || self.peek() != Some('\\') {
+                    char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER)
+                } else {
+                    // A surrogate pair is spelled as two consecutive escapes.
+                    self.bump();
+                    if self.peek() != Some('u') {
+                        buf.push(char::REPLACEMENT_CHARACTER);
+                        return self.read_escape(buf);
+                    }
+                    self.bump();
+                    let low = self.read_hex()?;
+                    if (0xDC00..0xE000).contains(&low) {
+                        let c = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                        char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER)
+                    } else {
+                        buf.push(char::REPLACEMENT_CHARACTER);
+                        char::from_u32(low).unwrap_or(char::REPLACEMENT_CHARACTER)
+                    }
+                }
             }
-        }
-
-        if !ended {
-            Err(LexError::UnterminatedNumber { line: self.line })?
-        }
-
-        // TODO handle parse error
-        Ok(Token::Number(s.parse::<usize>().unwrap()))
-    }
+            _ => Err(self.invalid_syntax("escape sequence"))?,
+        };
 
-    fn read_null(&mut self) -> Result<Token, LexError> {
-        self.read_keyword(String::from("n"), ',', Token::Null)
+        buf.push(escaped);
+        Ok(())
     }
 
-    fn read_boolean_true(&mut self) -> Result<Token, LexError> {
-        self.read_keyword(String::from("t"), ',', Token::True)
+    /// Read the 4 hex digits of a `\u` escape.
+    fn read_hex(&mut self) -> Result<u32, Error> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .bump()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.invalid_syntax("4 hex digits"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
     }
 
-    fn read_boolean_false(&mut self) -> Result<Token, LexError> {
-        self.read_keyword(String::from("f"), ',', Token::False)
-    }
+    fn read_number(&mut self, initial_char: char) -> Result<Token<'a>, Error> {
+        let mut s = String::from(initial_char);
 
-    fn read_array(&mut self) -> Result<Token, LexError> {
-        let mut ended = true;
+        while let Some(c) = self.peek().filter(|c| is_number(*c)) {
+            s.push(c);
+            self.bump();
+        }
 
-        for ch in self.input.by_ref() {
-            match ch {
-                ']' => {
-                    ended = true;
-                    break;
-                }
-                _ => todo!(),
-            }
+        if !is_valid_number(&s) {
+            return Err(self.error(ErrorKind::InvalidNumber { number: s }));
         }
 
-        if !ended {
-            Err(LexError::UnterminatedArray { line: self.line })?
+        match to_number(&s) {
+            Some(n) => Ok(Token::Number(n)),
+            None => Err(self.error(ErrorKind::NumberOutOfRange { number: s })),
         }
+    }
 
-        Ok(Token::Array)
+    fn invalid_syntax(&self, expected: &str) -> Error {
+        self.error(ErrorKind::InvalidSyntax {
+            expected: String::from(expected),
+        })
+    }
+
+    /// An error spanning the token read so far.
+    fn error(&self, kind: ErrorKind) -> Error {
+        Error::new(
+            kind,
+            Span {
+                start: self.start,
+                end: self.position(),
+            },
+        )
     }
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(input: Chars<'a>) -> Self {
+    pub fn new(input: &'a str) -> Self {
         Lexer {
-            input: input.peekable(),
+            source: input,
+            input: input.chars().peekable(),
             line: 1,
+            column: 1,
+            offset: 0,
+            start: Position {
+                line: 1,
+                column: 1,
+                offset: 0,
+            },
         }
     }
 
-    pub fn lex(&mut self) -> Result<Vec<Token>, LexError> {
-        let mut tokens = Vec::<Token>::new();
-
-        // File is empty.
-        if self.peek().is_none() {
-            Err(LexError::EmptyFile)?
-        }
+    pub fn lex(&mut self) -> Result<Vec<(Token<'a>, Span)>, Error> {
+        let mut tokens = Vec::<(Token<'a>, Span)>::new();
 
-        while let Some(char) = self.read() {
-            match char {
-                '{' => tokens.push(Token::LBraces),
-                '}' => {
-                    if tokens[tokens.len() - 1] == Token::Coma {
-                        Err(LexError::TrailingComa { line: self.line })?
-                    }
-                    tokens.push(Token::RBraces);
-                }
-                '"' => tokens.append(&mut self.parse_kv()?),
-                ',' => tokens.push(Token::Coma),
-                c => Err(LexError::UnexpectedToken {
-                    line: self.line,
-                    token: c,
-                })?,
+        while let Some((token, span)) = self.next_token()? {
+            if matches!(token, Token::RBraces | Token::RBracket)
+                && tokens.last().is_some_and(|(t, _)| *t == Token::Coma)
+            {
+                let comma = tokens.last().map_or(span, |(_, comma)| *comma);
+                Err(Error::new(ErrorKind::TrailingComma, comma))?
             }
+            tokens.push((token, span));
         }
 
-        // Ensure proper start.
-        if tokens[0] != Token::LBraces {
-            Err(LexError::OpeningCurly)?
-        }
-
-        // Ensure proper ending.
-        if tokens[tokens.len() - 1] != Token::RBraces {
-            Err(LexError::ClosingCurly)?
+        // File is empty.
+        if tokens.is_empty() {
+            Err(Error::at(ErrorKind::EmptyFile, self.position()))?
         }
 
         Ok(tokens)
     }
 
-    /// Strip newline and whitespace and return the next valid char.
-    fn read(&mut self) -> Option<char> {
-        loop {
-            if let Some(ch) = self.peek() {
-                if is_newline(ch) {
-                    self.line += 1;
-                    self.input.next();
-                    continue;
-                }
-                if is_whitespace(ch) {
-                    self.input.next();
-                    continue;
-                }
-                break;
-            }
-            break;
+    /// Read the next token along with where it was found, `None` at the end of input.
+    pub fn next_token(&mut self) -> Result<Option<(Token<'a>, Span)>, Error> {
+        self.skip_whitespace();
+        self.start = self.position();
+
+        let Some(char) = self.bump() else {
+            return Ok(None);
+        };
+
+        let token = match char {
+            '{' => Token::LBraces,
+            '}' => Token::RBraces,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            ':' => Token::Colon,
+            ',' => Token::Coma,
+            '"' => self.read_string()?,
+            'n' => self.read_keyword(char, Token::Null)?,
+            't' => self.read_keyword(char, Token::True)?,
+            'f' => self.read_keyword(char, Token::False)?,
+            c if is_number(c) => self.read_number(c)?,
+            c => Err(self.error(ErrorKind::UnexpectedToken { token: c }))?,
+        };
+
+        let span = Span {
+            start: self.start,
+            end: self.position(),
+        };
+        Ok(Some((token, span)))
+    }
+
+    /// Strip newline and whitespace.
+    fn skip_whitespace(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|c| is_newline(c) || is_whitespace(c))
+        {
+            self.bump();
         }
+    }
 
-        self.input.next()
+    /// Consume the next char, keeping track of where we are in the input.
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.input.next()?;
+        self.offset += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
     }
 
     /// Peak ahead into the input.
     fn peek(&mut self) -> Option<char> {
         self.input.peek().copied()
     }
+
+    pub(crate) fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+        }
+    }
 }
 
 /// Check if the char is '\n' or '\r'.
@@ -250,9 +303,52 @@ fn is_newline(char: char) -> bool {
 }
 
 fn is_whitespace(char: char) -> bool {
-    char == ' '
+    char == ' ' || char == '\t'
 }
 
+/// Chars that can make up a number literal.
 fn is_number(char: char) -> bool {
-    ['1', '2', '3', '4', '5', '6', '7', '8', '9', '0'].contains(&char)
+    char.is_ascii_digit() || ['-', '+', '.', 'e', 'E'].contains(&char)
+}
+
+/// Check `s` against the JSON number grammar:
+/// `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`
+pub(crate) fn is_valid_number(s: &str) -> bool {
+    let digits = |s: &str| s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+    let s = s.strip_prefix('-').unwrap_or(s);
+    let int = digits(s);
+    if int == 0 || (int > 1 && s.starts_with('0')) {
+        return false;
+    }
+
+    let mut rest = &s[int..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let n = digits(fraction);
+        if n == 0 {
+            return false;
+        }
+        rest = &fraction[n..];
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let n = digits(exponent);
+        if n == 0 {
+            return false;
+        }
+        rest = &exponent[n..];
+    }
+
+    rest.is_empty()
+}
+
+/// Convert a literal accepted by `is_valid_number`, `None` if it overflows an `f64`.
+pub(crate) fn to_number(s: &str) -> Option<Number> {
+    if let Ok(n) = s.parse::<i64>() {
+        return Some(Number::Integer(n));
+    }
+    s.parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite())
+        .map(Number::Float)
 }
@@ -1,29 +1,118 @@
+//! A json parser, along with tooling built on top of it.
+//!
+//! The lexer, the parser and `Value` only need `alloc`. Everything else, files, the
+//! cli and the tooling, is behind the default `std` feature, without it the crate
+//! is `no_std`.
+
+// Unit tests need the test harness, which needs std.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+// Malformed input must surface as an error, never as a panic.
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::todo,
+        clippy::unimplemented
+    )
+)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod binary;
+mod borrowed;
+#[cfg(feature = "std")]
+mod canonical;
+#[cfg(feature = "std")]
+mod cli;
+#[cfg(feature = "std")]
+pub mod codegen;
+#[cfg(feature = "std")]
+pub mod convert;
+#[cfg(feature = "std")]
+pub mod diff;
+pub mod encoding;
 mod errors;
+#[cfg(feature = "std")]
+pub mod gron;
+pub mod incremental;
+#[cfg(feature = "std")]
+pub mod input;
+#[cfg(feature = "std")]
+pub mod jq;
+#[cfg(feature = "std")]
+pub mod lazy;
 mod lexer;
+#[cfg(feature = "std")]
+pub mod lint;
+#[cfg(feature = "std")]
+pub mod lsp;
+mod macros;
+#[cfg(feature = "std")]
+pub mod ndjson;
+mod parser;
+pub mod path;
+#[cfg(feature = "std")]
+pub mod redact;
+#[cfg(feature = "std")]
+pub mod schema;
+pub mod stage1;
+mod stage2;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "async")]
+pub mod stream;
 mod token;
+mod value;
+pub mod visit;
 
-use anyhow::{Context, Result};
-use std::io::Read;
+pub use borrowed::BorrowedValue;
+#[cfg(feature = "std")]
+pub use canonical::canonicalize;
+#[cfg(feature = "std")]
+pub use cli::run;
+pub use errors::{Error, ErrorKind};
+pub use parser::Spans;
+pub use token::{Position, Span, Token};
+pub use value::{Number, Value};
 
-pub fn run() -> Result<()> {
-    let file_path = std::env::args().nth(1).context("get file_path")?;
-    let contents = read_file_contents(&file_path)?;
+use alloc::vec::Vec;
 
-    let mut lexer = lexer::Lexer::new(contents.chars());
-    let tokens = lexer.lex()?;
+/// What the `json!` macro expands to, it has to work without std too.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::{string::String, vec};
+}
 
-    dbg!(tokens);
+/// Split a json document into tokens, without checking how they are arranged.
+pub fn lex(input: &str) -> Result<Vec<(Token<'_>, Span)>, Error> {
+    lexer::Lexer::new(input).lex()
+}
 
-    Ok(())
+/// Parse a json document.
+pub fn parse(input: &str) -> Result<Value, Error> {
+    parser::parse_str(input)
 }
 
-fn read_file_contents(file_path: &str) -> Result<String> {
-    let mut file = std::fs::File::open(file_path).context("open json file")?;
+/// Parse a json document without copying its strings, see `BorrowedValue`.
+pub fn parse_borrowed(input: &str) -> Result<BorrowedValue<'_>, Error> {
+    parser::parse_borrowed_str(input)
+}
 
-    let mut contents = String::new();
+/// Parse a json document, also returning the source span of every value.
+pub fn parse_with_spans(input: &str) -> Result<(Value, Spans), Error> {
+    parser::parse_str_with_spans(input)
+}
 
-    file.read_to_string(&mut contents)
-        .context("read file contents")?;
+/// Parse the json file at `path`, memory mapped when possible, see `input::Input`.
+#[cfg(feature = "std")]
+pub fn parse_file(path: impl AsRef<std::path::Path>) -> anyhow::Result<Value> {
+    Ok(parse_bytes(&input::Input::open(path)?)?)
+}
 
-    Ok(contents)
+/// Parse a json document from raw bytes, see `encoding::decode`.
+pub fn parse_bytes(input: &[u8]) -> Result<Value, Error> {
+    parse(&encoding::decode(input)?)
 }
@@ -0,0 +1,725 @@
+//! Style rules for json documents, configured in a `.jsonlint.json` file.
+//!
+//! ```json
+//! {
+//!   "rules": {
+//!     "key-naming": { "severity": "warning", "convention": "camelCase" },
+//!     "max-depth": { "severity": "error", "max": 8 },
+//!     "no-duplicate-keys": "error",
+//!     "no-empty": "warning",
+//!     "indentation": { "severity": "warning", "indent": 2 },
+//!     "banned-keys": { "severity": "error", "keys": ["password", "*_secret"] }
+//!   }
+//! }
+//! ```
+//!
+//! Every rule is set to `"off"`, `"warning"` or `"error"`, either directly or
+//! through the `severity` of an object holding its options. Rules left out of the
+//! file are off, without a file only `no-duplicate-keys` is on, as an error.
+//!
+//! - `key-naming`: keys follow `convention`, one of `camelCase`, `PascalCase`,
+//!   `snake_case`, `kebab-case` or `SCREAMING_SNAKE_CASE`.
+//! - `max-depth`: containers nest at most `max` levels, the root's members being
+//!   on the first one.
+//! - `no-duplicate-keys`: a key appears once per object.
+//! - `no-empty`: no `{}` nor `[]`.
+//! - `indentation`: every line is indented by one `indent` per enclosing container,
+//!   `indent` is a number of spaces, `"tab"`, or `"auto"` (the default) to follow
+//!   the first indented line. Only where lines start is checked, so a document
+//!   on a single line just mustn't be indented.
+//! - `banned-keys`: no key matches one of the `keys` globs (`*` and `?`).
+//!
+//! Spans are those of `parse_with_spans`, which are keyed by path, so values under
+//! a duplicated key are all reported at the last of them.
+
+use crate::{
+    errors::LintError,
+    parser::Spans,
+    path::Path,
+    redact::glob_matches,
+    token::{Position, Span, Token},
+    value::Value,
+    visit::{walk_object, walk_value, Context, Visitor},
+    Error,
+};
+use anyhow::Result;
+use std::{collections::HashSet, fmt};
+
+/// Rules to check, see the module documentation for the format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    key_naming: Option<(Severity, Convention)>,
+    max_depth: Option<(Severity, usize)>,
+    no_duplicate_keys: Option<Severity>,
+    no_empty: Option<Severity>,
+    indentation: Option<(Severity, Indent)>,
+    banned_keys: Option<(Severity, Vec<String>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Convention {
+    Camel,
+    Pascal,
+    Snake,
+    Kebab,
+    ScreamingSnake,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Indent {
+    Auto,
+    Spaces(usize),
+    Tab,
+}
+
+/// A rule broken somewhere in a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// Value the diagnostic is about, `None` for rules about the text itself.
+    pub path: Option<Path>,
+    pub span: Span,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            key_naming: None,
+            max_depth: None,
+            no_duplicate_keys: Some(Severity::Error),
+            no_empty: None,
+            indentation: None,
+            banned_keys: None,
+        }
+    }
+}
+
+impl Config {
+    /// Read the json text of a `.jsonlint.json` file.
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::from_value(&crate::parse(input)?)
+    }
+
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let invalid = |path: &Path, reason: &str| LintError::InvalidConfig {
+            path: path.to_string(),
+            reason: String::from(reason),
+        };
+
+        let rules_path = Path::root().key("rules");
+        let Some(Value::Object(rules)) = value.get("rules") else {
+            Err(invalid(
+                &Path::root(),
+                "expected an object with a \"rules\" object",
+            ))?
+        };
+
+        let mut config = Config {
+            no_duplicate_keys: None,
+            ..Config::default()
+        };
+        for (name, setting) in rules {
+            let path = rules_path.key(name);
+            let (severity, options) = match setting {
+                Value::String(severity) => (severity.as_str(), &[][..]),
+                Value::Object(options) => match setting.get("severity") {
+                    Some(Value::String(severity)) => (severity.as_str(), options.as_slice()),
+                    _ => Err(invalid(&path.key("severity"), "expected a string"))?,
+                },
+                _ => Err(invalid(&path, "expected a severity or an object"))?,
+            };
+            let severity = match severity {
+                "off" => None,
+                "warning" => Some(Severity::Warning),
+                "error" => Some(Severity::Error),
+                _ => Err(invalid(
+                    &path,
+                    "expected a severity of \"off\", \"warning\" or \"error\"",
+                ))?,
+            };
+            let option = |key: &str| setting.get(key).map(|value| (path.key(key), value));
+
+            match name.as_str() {
+                "key-naming" => {
+                    let convention = match option("convention") {
+                        Some((_, Value::String(convention))) => match convention.as_str() {
+                            "camelCase" => Convention::Camel,
+                            "PascalCase" => Convention::Pascal,
+                            "snake_case" => Convention::Snake,
+                            "kebab-case" => Convention::Kebab,
+                            "SCREAMING_SNAKE_CASE" => Convention::ScreamingSnake,
+                            _ => Err(invalid(&path.key("convention"), "unknown convention"))?,
+                        },
+                        _ => Err(invalid(&path.key("convention"), "expected a string"))?,
+                    };
+                    config.key_naming = severity.map(|severity| (severity, convention));
+                }
+                "max-depth" => {
+                    let max = match option("max") {
+                        Some((_, Value::Number(crate::Number::Integer(max)))) if *max >= 0 => {
+                            usize::try_from(*max).unwrap_or(usize::MAX)
+                        }
+                        _ => Err(invalid(&path.key("max"), "expected a positive integer"))?,
+                    };
+                    config.max_depth = severity.map(|severity| (severity, max));
+                }
+                "no-duplicate-keys" => config.no_duplicate_keys = severity,
+                "no-empty" => config.no_empty = severity,
+                "indentation" => {
+                    let indent = match option("indent") {
+                        None => Indent::Auto,
+                        Some((_, Value::String(s))) if s == "auto" => Indent::Auto,
+                        Some((_, Value::String(s))) if s == "tab" => Indent::Tab,
+                        Some((_, Value::Number(crate::Number::Integer(n)))) if *n > 0 => {
+                            Indent::Spaces(usize::try_from(*n).unwrap_or(usize::MAX))
+                        }
+                        Some((path, _)) => Err(invalid(
+                            &path,
+                            "expected a number of spaces, \"tab\" or \"auto\"",
+                        ))?,
+                    };
+                    config.indentation = severity.map(|severity| (severity, indent));
+                }
+                "banned-keys" => {
+                    let keys = match option("keys") {
+                        Some((path, Value::Array(keys))) => keys
+                            .iter()
+                            .enumerate()
+                            .map(|(i, key)| match key {
+                                Value::String(key) => Ok(key.clone()),
+                                _ => Err(invalid(&path.index(i), "expected a string")),
+                            })
+                            .collect::<Result<_, _>>()?,
+                        _ => Err(invalid(&path.key("keys"), "expected an array of globs"))?,
+                    };
+                    config.banned_keys = severity.map(|severity| (severity, keys));
+                }
+                _ => Err(invalid(&path, "unknown rule"))?,
+            }
+
+            // Typos such as `"conventions"` would otherwise silently fall back to
+            // the default.
+            let known: &[&str] = match name.as_str() {
+                "key-naming" => &["severity", "convention"],
+                "max-depth" => &["severity", "max"],
+                "indentation" => &["severity", "indent"],
+                "banned-keys" => &["severity", "keys"],
+                _ => &["severity"],
+            };
+            if let Some((key, _)) = options
+                .iter()
+                .find(|(key, _)| !known.contains(&key.as_str()))
+            {
+                Err(invalid(&path.key(key), "unknown option"))?
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Check `input` against `config`, diagnostics come out in the order of the text.
+///
+/// A document that doesn't parse gets a single error, the rule being the name of
+/// its `ErrorKind`, e.g. `trailing-comma`.
+pub fn lint(input: &str, config: &Config) -> Vec<Diagnostic> {
+    check(input, config).unwrap_or_else(|error| vec![Diagnostic::from(&error)])
+}
+
+fn check(input: &str, config: &Config) -> Result<Vec<Diagnostic>, Error> {
+    let (value, spans) = crate::parse_with_spans(input)?;
+
+    let mut linter = Linter {
+        config,
+        spans: &spans,
+        diagnostics: Vec::new(),
+    };
+    linter.visit_value(&value, &Context::root().with_spans(&spans));
+    let mut diagnostics = linter.diagnostics;
+
+    if let Some((severity, indent)) = config.indentation {
+        diagnostics.extend(indentation(input, severity, indent)?);
+    }
+    diagnostics.sort_by_key(|d| d.span.start.offset);
+    Ok(diagnostics)
+}
+
+struct Linter<'a> {
+    config: &'a Config,
+    spans: &'a Spans,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: &'static str, severity: Severity, message: String, cx: &Context) {
+        self.diagnostics.push(Diagnostic {
+            rule,
+            severity,
+            message,
+            path: Some(cx.path().clone()),
+            span: cx.span().unwrap_or_default(),
+        });
+    }
+}
+
+impl Visitor for Linter<'_> {
+    fn visit_value(&mut self, value: &Value, cx: &Context) {
+        if let Some((severity, max)) = self.config.max_depth {
+            let nested = match value {
+                Value::Object(members) => !members.is_empty(),
+                Value::Array(items) => !items.is_empty(),
+                _ => false,
+            };
+            // Reported once, on the container whose children are too deep.
+            if nested && cx.path().segments().len() == max {
+                let message = format!("Nesting is deeper than {max} levels.");
+                self.report("max-depth", severity, message, cx);
+            }
+        }
+        if let Some(severity) = self.config.no_empty {
+            match value {
+                Value::Object(members) if members.is_empty() => {
+                    self.report("no-empty", severity, String::from("Empty object."), cx)
+                }
+                Value::Array(items) if items.is_empty() => {
+                    self.report("no-empty", severity, String::from("Empty array."), cx)
+                }
+                _ => {}
+            }
+        }
+        walk_value(self, value, cx);
+    }
+
+    fn visit_object(&mut self, members: &[(String, Value)], cx: &Context) {
+        if let Some(severity) = self.config.no_duplicate_keys {
+            let mut seen = HashSet::new();
+            for (key, _) in members {
+                if !seen.insert(key.as_str()) {
+                    // Spans are keyed by path, so this is the last of the duplicates.
+                    let span = self
+                        .spans
+                        .get(&cx.path().key(key).pointer())
+                        .copied()
+                        .unwrap_or_default();
+                    self.diagnostics.push(Diagnostic {
+                        rule: "no-duplicate-keys",
+                        severity,
+                        message: format!("Duplicate key {key:?}."),
+                        path: Some(cx.path().clone()),
+                        span,
+                    });
+                }
+            }
+        }
+        walk_object(self, members, cx);
+    }
+
+    fn visit_key(&mut self, key: &str, cx: &Context) {
+        if let Some((severity, convention)) = self.config.key_naming {
+            if !convention.matches(key) {
+                let message = format!("Key {key:?} is not {convention}.");
+                self.report("key-naming", severity, message, cx);
+            }
+        }
+        if let Some((severity, banned)) = &self.config.banned_keys {
+            if banned.iter().any(|glob| glob_matches(glob, key)) {
+                let message = format!("Key {key:?} is banned.");
+                self.report("banned-keys", *severity, message, cx);
+            }
+        }
+    }
+}
+
+impl Convention {
+    fn matches(self, key: &str) -> bool {
+        let mut rest = key.chars();
+        let Some(first) = rest.next() else {
+            return false;
+        };
+        match self {
+            Convention::Camel => {
+                first.is_ascii_lowercase() && rest.all(|c| c.is_ascii_alphanumeric())
+            }
+            Convention::Pascal => {
+                first.is_ascii_uppercase() && rest.all(|c| c.is_ascii_alphanumeric())
+            }
+            Convention::Snake => {
+                first.is_ascii_lowercase()
+                    && rest.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            }
+            Convention::Kebab => {
+                first.is_ascii_lowercase()
+                    && rest.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            }
+            Convention::ScreamingSnake => {
+                first.is_ascii_uppercase()
+                    && rest.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+            }
+        }
+    }
+}
+
+impl fmt::Display for Convention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Convention::Camel => "camelCase",
+            Convention::Pascal => "PascalCase",
+            Convention::Snake => "snake_case",
+            Convention::Kebab => "kebab-case",
+            Convention::ScreamingSnake => "SCREAMING_SNAKE_CASE",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Lines not indented by one unit per enclosing container, the span of a
+/// diagnostic is the indentation of its line.
+fn indentation(input: &str, severity: Severity, indent: Indent) -> Result<Vec<Diagnostic>, Error> {
+    let lines: Vec<&str> = input.split('\n').collect();
+    let mut unit = match indent {
+        Indent::Auto => None,
+        Indent::Spaces(n) => Some(" ".repeat(n)),
+        Indent::Tab => Some(String::from("\t")),
+    };
+
+    let mut diagnostics = Vec::new();
+    let mut depth = 0usize;
+    let mut line = 0;
+    for (token, span) in crate::lex(input)? {
+        let closing = matches!(token, Token::RBraces | Token::RBracket);
+        // Only the first token of a line decides its indentation.
+        if span.start.line != line {
+            line = span.start.line;
+            let text = lines.get(line - 1).copied().unwrap_or_default();
+            let width = text.len() - text.trim_start_matches([' ', '\t']).len();
+            let found = text.get(..width).unwrap_or_default();
+            let level = depth - usize::from(closing && depth > 0);
+
+            if unit.is_none() && level > 0 {
+                unit = auto_unit(found, level);
+            }
+            let expected = match level {
+                0 => Some(String::new()),
+                _ => unit.as_deref().map(|unit| unit.repeat(level)),
+            };
+            if expected.as_deref() != Some(found) {
+                let message = match &expected {
+                    Some(expected) => {
+                        format!(
+                            "Expected {}, found {}.",
+                            describe(expected),
+                            describe(found)
+                        )
+                    }
+                    None => format!(
+                        "Expected indentation with one kind of whitespace, found {}.",
+                        describe(found)
+                    ),
+                };
+                let start = Position {
+                    column: 1,
+                    offset: span.start.offset - (span.start.column - 1),
+                    ..span.start
+                };
+                diagnostics.push(Diagnostic {
+                    rule: "indentation",
+                    severity,
+                    message,
+                    path: None,
+                    span: Span {
+                        start,
+                        end: span.start,
+                    },
+                });
+            }
+        }
+        match token {
+            Token::LBraces | Token::LBracket => depth += 1,
+            Token::RBraces | Token::RBracket => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    Ok(diagnostics)
+}
+
+/// The unit of an indentation `found` at `level`, if it is made of one kind of
+/// whitespace evenly split between levels.
+fn auto_unit(found: &str, level: usize) -> Option<String> {
+    let first = found.chars().next()?;
+    let evenly = found.len().is_multiple_of(level) && found.chars().all(|c| c == first);
+    evenly
+        .then(|| found.get(..found.len() / level))
+        .flatten()
+        .map(String::from)
+}
+
+/// e.g. `4 spaces`, `1 tab`, `no indentation`.
+fn describe(indentation: &str) -> String {
+    let spaces = indentation.chars().filter(|&c| c == ' ').count();
+    let tabs = indentation.len() - spaces;
+    let plural = |n: usize, what: &str| match n {
+        1 => format!("1 {what}"),
+        n => format!("{n} {what}s"),
+    };
+    match (spaces, tabs) {
+        (0, 0) => String::from("no indentation"),
+        (spaces, 0) => plural(spaces, "space"),
+        (0, tabs) => plural(tabs, "tab"),
+        (spaces, tabs) => format!("{} and {}", plural(spaces, "space"), plural(tabs, "tab")),
+    }
+}
+
+impl Diagnostic {
+    /// A file that couldn't be opened or decoded, as an `unreadable` error at its
+    /// start. Decoding errors keep their rule and position, like parse errors.
+    pub fn unreadable(error: &anyhow::Error) -> Self {
+        if let Some(error) = error.downcast_ref::<Error>() {
+            return Diagnostic::from(error);
+        }
+        let start = Position::at(b"", 0);
+        Diagnostic {
+            rule: "unreadable",
+            severity: Severity::Error,
+            message: format!("{error:#}"),
+            path: None,
+            span: Span { start, end: start },
+        }
+    }
+
+    /// Severity, rule, message, path and span, for tools reading the output of the cli.
+    pub fn to_json(&self) -> Value {
+        let position =
+            |p: Position| crate::json!({ "line": p.line, "column": p.column, "offset": p.offset });
+        let mut members = vec![
+            (
+                String::from("severity"),
+                Value::from(self.severity.to_string()),
+            ),
+            (String::from("rule"), Value::from(self.rule)),
+            (String::from("message"), Value::from(self.message.as_str())),
+        ];
+        if let Some(path) = &self.path {
+            members.push((String::from("path"), Value::from(path.to_string())));
+        }
+        members.push((
+            String::from("span"),
+            crate::json!({ "start": position(self.span.start), "end": position(self.span.end) }),
+        ));
+        Value::Object(members)
+    }
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(error: &Error) -> Self {
+        Diagnostic {
+            rule: error.kind().name(),
+            severity: Severity::Error,
+            message: error.kind().to_string(),
+            path: None,
+            span: error.span(),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Renders like `Error`, e.g. `[line 2, column 3] error no-duplicate-keys: ...`.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Position { line, column, .. } = self.span.start;
+        write!(
+            f,
+            "[line {line}, column {column}] {} {}: {}",
+            self.severity, self.rule, self.message
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::path::Segment;
+
+    fn check(config: &str, input: &str) -> Vec<String> {
+        let config = Config::parse(config).unwrap();
+        lint(input, &config)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_rules() {
+        let config = r#"{ "rules": {
+            "key-naming": { "severity": "warning", "convention": "snake_case" },
+            "max-depth": { "severity": "error", "max": 2 },
+            "no-duplicate-keys": "error",
+            "no-empty": "warning",
+            "banned-keys": { "severity": "error", "keys": ["*password*"] }
+        } }"#;
+        let input = "{\n  \"userName\": 1,\n  \"a\": {\"b\": [1]},\n  \"c\": [],\n  \"a\": 2,\n  \"db_password\": \"x\"\n}";
+        assert_eq!(
+            check(config, input),
+            [
+                "[line 2, column 15] warning key-naming: Key \"userName\" is not snake_case.",
+                "[line 3, column 14] error max-depth: Nesting is deeper than 2 levels.",
+                "[line 4, column 8] warning no-empty: Empty array.",
+                "[line 5, column 8] error no-duplicate-keys: Duplicate key \"a\".",
+                "[line 6, column 18] error banned-keys: Key \"db_password\" is banned.",
+            ]
+        );
+
+        // Off, and left out.
+        let config = r#"{ "rules": { "no-empty": "off" } }"#;
+        assert!(check(config, "{\"a\": {}, \"a\": []}").is_empty());
+        // Without a file.
+        let diagnostics = lint("{\"a\": 1, \"a\": 2}", &Config::default());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path, Some(Path::root()));
+
+        // Parse errors are diagnostics too.
+        assert_eq!(
+            check(config, "{\"a\": [1,]}"),
+            ["[line 1, column 9] error trailing-comma: Trailing Comma."]
+        );
+
+        // And so are files that can't be read.
+        let error = anyhow::anyhow!("No such file").context("open json file");
+        assert_eq!(
+            Diagnostic::unreadable(&error).to_string(),
+            "[line 1, column 1] error unreadable: open json file: No such file"
+        );
+        let input = crate::input::Input::read(&b"[\"\xff\"]"[..]).unwrap();
+        let error = input.text(false).unwrap_err();
+        assert_eq!(
+            Diagnostic::unreadable(&error).to_string(),
+            Diagnostic::from(error.downcast_ref::<Error>().unwrap()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_conventions() {
+        let cases = [
+            (Convention::Camel, ["fooBar", "a1"], ["FooBar", "foo_bar"]),
+            (Convention::Pascal, ["FooBar", "A"], ["fooBar", "Foo-Bar"]),
+            (Convention::Snake, ["foo_bar", "x2"], ["fooBar", "_foo"]),
+            (Convention::Kebab, ["foo-bar", "x"], ["foo_bar", "Foo"]),
+            (Convention::ScreamingSnake, ["FOO_BAR", "X"], ["Foo", "foo"]),
+        ];
+        for (convention, good, bad) in cases {
+            for key in good {
+                assert!(convention.matches(key), "{key} is {convention}");
+            }
+            for key in bad {
+                assert!(!convention.matches(key), "{key} isn't {convention}");
+            }
+        }
+        assert!(!Convention::Camel.matches(""));
+    }
+
+    #[test]
+    fn test_indentation() {
+        let auto = r#"{ "rules": { "indentation": "warning" } }"#;
+        let input = "{\n    \"a\": [\n        1,\n      2\n    ],\n\t\"b\": 3\n}";
+        assert_eq!(
+            check(auto, input),
+            [
+                "[line 4, column 1] warning indentation: Expected 8 spaces, found 6 spaces.",
+                "[line 6, column 1] warning indentation: Expected 4 spaces, found 1 tab.",
+            ]
+        );
+
+        let tabs = r#"{ "rules": { "indentation": { "severity": "error", "indent": "tab" } } }"#;
+        assert!(check(tabs, "[\n\t{\n\t\t\"a\": 1\n\t}\n]").is_empty());
+        assert_eq!(
+            check(tabs, "[\n  1]"),
+            ["[line 2, column 1] error indentation: Expected 1 tab, found 2 spaces."]
+        );
+        assert_eq!(
+            check(tabs, "  {\"a\": [1]}"),
+            ["[line 1, column 1] error indentation: Expected no indentation, found 2 spaces."]
+        );
+        // Nothing else of a single line document is checked.
+        assert!(check(tabs, "{\"a\": [1, {}]}").is_empty());
+
+        let spaces = r#"{ "rules": { "indentation": { "severity": "error", "indent": 2 } } }"#;
+        let diagnostic = &lint("{\n \t\"a\": 1\n}", &Config::parse(spaces).unwrap())[0];
+        assert_eq!(
+            diagnostic.message,
+            "Expected 2 spaces, found 1 space and 1 tab."
+        );
+        assert_eq!(
+            (diagnostic.span.start.offset, diagnostic.span.end.offset),
+            (2, 4)
+        );
+        assert_eq!(diagnostic.path, None);
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let err = |config: &str| Config::parse(config).unwrap_err().to_string();
+        assert_eq!(
+            err(r#"{ "rules": [] }"#),
+            "$: expected an object with a \"rules\" object."
+        );
+        assert_eq!(
+            err(r#"{ "rules": { "nope": "error" } }"#),
+            "$.rules.nope: unknown rule."
+        );
+        assert_eq!(
+            err(r#"{ "rules": { "no-empty": "fatal" } }"#),
+            "$.rules[\"no-empty\"]: expected a severity of \"off\", \"warning\" or \"error\"."
+        );
+        assert_eq!(
+            err(r#"{ "rules": { "key-naming": "error" } }"#),
+            "$.rules[\"key-naming\"].convention: expected a string."
+        );
+        assert_eq!(
+            err(r#"{ "rules": { "max-depth": { "severity": "error", "max": 3, "min": 1 } } }"#),
+            "$.rules[\"max-depth\"].min: unknown option."
+        );
+        assert_eq!(
+            err(r#"{ "rules": { "banned-keys": { "severity": "error", "keys": ["a", 1] } } }"#),
+            "$.rules[\"banned-keys\"].keys[1]: expected a string."
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let diagnostics = lint(
+            "{\"a\": []}",
+            &Config::parse(r#"{ "rules": { "no-empty": "error" } }"#).unwrap(),
+        );
+        assert_eq!(
+            diagnostics[0].to_json(),
+            crate::json!({
+                "severity": "error",
+                "rule": "no-empty",
+                "message": "Empty array.",
+                "path": "$.a",
+                "span": {
+                    "start": { "line": 1, "column": 7, "offset": 6 },
+                    "end": { "line": 1, "column": 9, "offset": 8 },
+                },
+            })
+        );
+        assert_eq!(
+            diagnostics[0].path,
+            Some(Path::root().join(Segment::Key(String::from("a"))))
+        );
+    }
+}
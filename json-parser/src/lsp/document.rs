@@ -0,0 +1,489 @@
+//! An open document and everything the server answers about it.
+//!
+//! Positions in the protocol are a 0-based line and a column counted in UTF-16 code
+//! units, they are converted from and to byte offsets here.
+
+use super::schema;
+use crate::{
+    errors::Error,
+    json,
+    lexer::Lexer,
+    parser::Spans,
+    path::{Path, Segment},
+    token::{Span, Token},
+    value::Value,
+};
+
+/// LSP `SymbolKind`s and `CompletionItemKind`s used below.
+const SYMBOL_STRING: i64 = 15;
+const SYMBOL_NUMBER: i64 = 16;
+const SYMBOL_BOOLEAN: i64 = 17;
+const SYMBOL_ARRAY: i64 = 18;
+const SYMBOL_OBJECT: i64 = 19;
+const SYMBOL_NULL: i64 = 21;
+const COMPLETION_PROPERTY: i64 = 10;
+const COMPLETION_VALUE: i64 = 12;
+
+pub(super) struct Document {
+    uri: String,
+    text: String,
+    /// The parsed text, or why it doesn't parse.
+    parsed: Result<(Value, Spans), Error>,
+    /// The `$schema` reference along with the loaded schema or why loading failed,
+    /// kept from the last time the text parsed.
+    schema: Option<(String, Result<Value, String>)>,
+}
+
+impl Document {
+    pub fn new(uri: &str, text: String) -> Self {
+        let mut document = Document {
+            uri: String::from(uri),
+            parsed: crate::parse_with_spans(&text),
+            text,
+            schema: None,
+        };
+        document.load_schema();
+        document
+    }
+
+    /// Replace the whole text.
+    pub fn update(&mut self, text: String) {
+        self.parsed = crate::parse_with_spans(&text);
+        self.text = text;
+        self.load_schema();
+    }
+
+    /// Load the schema `$schema` references, unless it is the one already loaded.
+    fn load_schema(&mut self) {
+        if let Ok((value, _)) = &self.parsed {
+            let reference = match value.get("$schema") {
+                Some(Value::String(reference)) => Some(reference),
+                _ => None,
+            };
+            if self.schema.as_ref().map(|(r, _)| r) != reference {
+                self.schema = reference.map(|reference| {
+                    let loaded = schema::load(&self.uri, reference).map_err(|e| format!("{e:#}"));
+                    (reference.clone(), loaded)
+                });
+            }
+        }
+    }
+
+    /// The syntax error if any, and a warning when the schema couldn't be loaded.
+    pub fn diagnostics(&self) -> Vec<Value> {
+        let mut diagnostics = Vec::new();
+        match &self.parsed {
+            Err(error) => diagnostics.push(json!({
+                "range": self.range(error.span()),
+                "severity": 1,
+                "code": error.kind().code(),
+                "source": "json-parser",
+                "message": error.kind().to_string(),
+            })),
+            Ok((_, spans)) => {
+                if let (Some((_, Err(reason))), Some(span)) = (&self.schema, spans.get("/$schema"))
+                {
+                    diagnostics.push(json!({
+                        "range": self.range(*span),
+                        "severity": 2,
+                        "source": "json-parser",
+                        "message": reason.as_str(),
+                    }));
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// Edits replacing the text with its pretty-printed form, `None` if it doesn't parse.
+    pub fn formatting(&self, indent: usize) -> Option<Vec<Value>> {
+        let (value, _) = self.parsed.as_ref().ok()?;
+        let pretty = format!("{}\n", value.to_pretty_string(indent));
+        if pretty == self.text {
+            return Some(Vec::new());
+        }
+
+        let range = json!({ "start": self.position(0), "end": self.position(self.text.len()) });
+        Some(vec![json!({ "range": range, "newText": pretty })])
+    }
+
+    /// Nested `DocumentSymbol`s for every member and item, empty if the text doesn't parse.
+    pub fn symbols(&self) -> Vec<Value> {
+        if self.parsed.is_err() {
+            return Vec::new();
+        }
+        let Ok(tokens) = crate::lex(&self.text) else {
+            return Vec::new();
+        };
+        let mut pos = 0;
+        self.outline(&tokens, &mut pos).0
+    }
+
+    /// Hover for the key or value at `offset`, from its schema.
+    pub fn hover(&self, offset: usize) -> Option<Value> {
+        let (_, Ok(root)) = self.schema.as_ref()? else {
+            return None;
+        };
+
+        // Only the tokens up to the hovered one have to be valid.
+        let mut lexer = Lexer::new(&self.text);
+        let mut context = Context::default();
+        let (token, span) = loop {
+            let (token, span) = lexer.next_token().ok()??;
+            if span.start.offset <= offset && offset < span.end.offset {
+                break (token, span);
+            }
+            context.step(&token);
+        };
+
+        let path = match token {
+            Token::Literal(key) if context.in_key() => context.container()?.key(&key),
+            Token::Colon | Token::Coma | Token::RBraces | Token::RBracket => return None,
+            _ => context.value_path()?,
+        };
+        let text = schema::describe(root, schema::resolve(root, path.segments())?)?;
+
+        Some(json!({
+            "contents": { "kind": "markdown", "value": text },
+            "range": self.range(span),
+        }))
+    }
+
+    /// Property names or values the schema allows at `offset`.
+    pub fn completion(&self, offset: usize) -> Vec<Value> {
+        let Some((_, Ok(root))) = self.schema.as_ref() else {
+            return Vec::new();
+        };
+        let Some(prefix) = self.text.get(..offset) else {
+            return Vec::new();
+        };
+
+        // The text before `offset` is usually incomplete, a string or keyword being typed
+        // is replaced by the completion.
+        let mut lexer = Lexer::new(prefix);
+        let mut context = Context::default();
+        let mut partial = None;
+        loop {
+            match lexer.next_token() {
+                Ok(Some((token, _))) => context.step(&token),
+                Ok(None) => break,
+                Err(e) if e.span().end.offset >= prefix.len() => {
+                    partial = Some(e.span().start.offset);
+                    break;
+                }
+                Err(_) => return Vec::new(),
+            }
+        }
+
+        let edit = |new_text: String| match partial {
+            Some(start) => {
+                // Swallow the closing quote an editor may have inserted along with the opening one.
+                let quoted = self.text.get(start..).is_some_and(|s| s.starts_with('"'));
+                let closed = self.text.get(offset..).is_some_and(|s| s.starts_with('"'));
+                let end = offset + usize::from(quoted && closed);
+                let range = json!({ "start": self.position(start), "end": self.position(end) });
+                ("textEdit", json!({ "range": range, "newText": new_text }))
+            }
+            None => ("insertText", Value::String(new_text)),
+        };
+
+        if context.in_key() {
+            let Some(path) = context.container() else {
+                return Vec::new();
+            };
+            let Some(schema) = schema::resolve(root, path.segments()) else {
+                return Vec::new();
+            };
+            schema::properties(root, schema)
+                .into_iter()
+                .filter(|(name, _)| !context.keys().iter().any(|key| key == name))
+                .map(|(name, property)| {
+                    let (field, edit) = edit(Value::from(name).to_string());
+                    let mut item = vec![
+                        (String::from("label"), Value::from(name)),
+                        (String::from("kind"), Value::from(COMPLETION_PROPERTY)),
+                        (String::from(field), edit),
+                    ];
+                    if let Some(text) = schema::describe(root, property) {
+                        let documentation = json!({ "kind": "markdown", "value": text });
+                        item.push((String::from("documentation"), documentation));
+                    }
+                    Value::Object(item)
+                })
+                .collect()
+        } else {
+            let Some(path) = context.value_path() else {
+                return Vec::new();
+            };
+            let Some(schema) = schema::resolve(root, path.segments()) else {
+                return Vec::new();
+            };
+            schema::values(root, schema)
+                .into_iter()
+                .map(|value| {
+                    let (field, edit) = edit(value.to_string());
+                    Value::Object(vec![
+                        (String::from("label"), Value::String(value.to_string())),
+                        (String::from("kind"), Value::from(COMPLETION_VALUE)),
+                        (String::from(field), edit),
+                    ])
+                })
+                .collect()
+        }
+    }
+
+    /// Byte offset of a protocol position, clamped to the end of its line.
+    pub fn offset(&self, line: usize, character: usize) -> usize {
+        let mut start = 0;
+        for _ in 0..line {
+            match self.text.get(start..).and_then(|rest| rest.find('\n')) {
+                Some(newline) => start += newline + 1,
+                None => return self.text.len(),
+            }
+        }
+
+        let mut units = 0;
+        let rest = self.text.get(start..).unwrap_or_default();
+        for (i, c) in rest.char_indices() {
+            if units >= character || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    /// Protocol position of a byte offset.
+    fn position(&self, offset: usize) -> Value {
+        let before = self.text.get(..offset).unwrap_or(&self.text);
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line = before.matches('\n').count();
+        let character = before
+            .get(line_start..)
+            .unwrap_or_default()
+            .encode_utf16()
+            .count();
+        json!({ "line": line, "character": character })
+    }
+
+    fn range(&self, span: Span) -> Value {
+        json!({
+            "start": self.position(span.start.offset),
+            "end": self.position(span.end.offset),
+        })
+    }
+
+    /// Symbols for the children of the value starting at `tokens[*pos]`, consuming it.
+    ///
+    /// Returns the children along with the span of the value's last token.
+    fn outline(&self, tokens: &[(Token, Span)], pos: &mut usize) -> (Vec<Value>, Span) {
+        let Some((token, span)) = tokens.get(*pos) else {
+            return (Vec::new(), Span::default());
+        };
+        *pos += 1;
+
+        let mut children = Vec::new();
+        let close = match token {
+            Token::LBraces => Token::RBraces,
+            Token::LBracket => Token::RBracket,
+            _ => return (children, *span),
+        };
+
+        while let Some((token, start)) = tokens.get(*pos) {
+            match token {
+                t if *t == close => {
+                    *pos += 1;
+                    return (children, *start);
+                }
+                Token::Coma => *pos += 1,
+                Token::Literal(key) if close == Token::RBraces => {
+                    // Skip the key and the colon.
+                    *pos += 2;
+                    children.push(self.symbol(key, *start, tokens, pos));
+                }
+                _ => {
+                    let name = children.len().to_string();
+                    children.push(self.symbol(&name, *start, tokens, pos));
+                }
+            }
+        }
+        (children, *span)
+    }
+
+    /// Symbol for a member named `name` or an item, whose value starts at `tokens[*pos]`.
+    fn symbol(&self, name: &str, start: Span, tokens: &[(Token, Span)], pos: &mut usize) -> Value {
+        let (kind, detail) = match tokens.get(*pos).map(|(token, _)| token) {
+            Some(Token::LBraces) => (SYMBOL_OBJECT, None),
+            Some(Token::LBracket) => (SYMBOL_ARRAY, None),
+            Some(Token::Literal(s)) => (SYMBOL_STRING, Some(Value::from(s.as_ref()).to_string())),
+            Some(Token::Number(n)) => (SYMBOL_NUMBER, Some(n.to_string())),
+            Some(token @ (Token::True | Token::False)) => (SYMBOL_BOOLEAN, Some(token.literal())),
+            _ => (SYMBOL_NULL, Some(String::from("null"))),
+        };
+        let (children, end) = self.outline(tokens, pos);
+
+        let range = Span {
+            start: start.start,
+            end: end.end,
+        };
+        let mut symbol = vec![
+            (String::from("name"), Value::from(name)),
+            (String::from("kind"), Value::from(kind)),
+            (String::from("range"), self.range(range)),
+            (String::from("selectionRange"), self.range(start)),
+        ];
+        if let Some(detail) = detail {
+            symbol.push((String::from("detail"), Value::from(detail)));
+        }
+        if !children.is_empty() {
+            symbol.push((String::from("children"), Value::Array(children)));
+        }
+        Value::Object(symbol)
+    }
+}
+
+/// The arrays and objects open at some point of a document, read off the tokens
+/// before it.
+#[derive(Default)]
+struct Context {
+    frames: Vec<Frame>,
+}
+
+enum Frame {
+    Object {
+        /// The key of the member being read.
+        key: Option<String>,
+        /// Keys seen so far.
+        keys: Vec<String>,
+        colon: bool,
+    },
+    Array {
+        index: usize,
+    },
+}
+
+impl Context {
+    fn step(&mut self, token: &Token) {
+        match (token, self.frames.last_mut()) {
+            (Token::LBraces, _) => self.frames.push(Frame::Object {
+                key: None,
+                keys: Vec::new(),
+                colon: false,
+            }),
+            (Token::LBracket, _) => self.frames.push(Frame::Array { index: 0 }),
+            (Token::RBraces | Token::RBracket, _) => {
+                self.frames.pop();
+            }
+            (Token::Colon, Some(Frame::Object { colon, .. })) => *colon = true,
+            (Token::Coma, Some(Frame::Object { key, colon, .. })) => {
+                *key = None;
+                *colon = false;
+            }
+            (Token::Coma, Some(Frame::Array { index })) => *index += 1,
+            (Token::Literal(s), Some(Frame::Object { key, keys, colon })) if !*colon => {
+                *key = Some(s.to_string());
+                keys.push(s.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether a key is expected next.
+    fn in_key(&self) -> bool {
+        matches!(self.frames.last(), Some(Frame::Object { colon: false, .. }))
+    }
+
+    /// Keys already present in the innermost object.
+    fn keys(&self) -> &[String] {
+        match self.frames.last() {
+            Some(Frame::Object { keys, .. }) => keys,
+            _ => &[],
+        }
+    }
+
+    /// Path of the innermost array or object.
+    fn container(&self) -> Option<Path> {
+        let parents = self.frames.len().checked_sub(1)?;
+        self.frames
+            .iter()
+            .take(parents)
+            .try_fold(Path::root(), |path, frame| {
+                Some(path.join(frame.segment()?))
+            })
+    }
+
+    /// Path of the value expected next.
+    fn value_path(&self) -> Option<Path> {
+        match self.frames.last() {
+            Some(frame) => Some(self.container()?.join(frame.segment()?)),
+            None => Some(Path::root()),
+        }
+    }
+}
+
+impl Frame {
+    fn segment(&self) -> Option<Segment> {
+        match self {
+            Frame::Object { key, .. } => Some(Segment::Key(key.clone()?)),
+            Frame::Array { index } => Some(Segment::Index(*index)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        let document = Document::new("untitled:1", String::from("{\n  \"😀\": 1,\n  \"b\": 2\n}"));
+        assert_eq!(document.offset(1, 0), 2);
+        // The emoji is two UTF-16 code units and four bytes.
+        assert_eq!(document.offset(1, 5), 9);
+        assert_eq!(document.offset(1, 99), 14);
+        assert_eq!(document.offset(9, 0), document.text.len());
+        assert_eq!(document.position(9), json!({ "line": 1, "character": 5 }));
+    }
+
+    #[test]
+    fn test_context() {
+        let context = |text: &str| {
+            let mut context = Context::default();
+            for (token, _) in crate::lex(text).unwrap() {
+                context.step(&token);
+            }
+            context
+        };
+
+        let c = context(r#"{"a": [1, {"b": 2,"#);
+        assert!(c.in_key());
+        assert_eq!(c.keys(), ["b"]);
+        assert_eq!(c.container(), Some(Path::root().key("a").index(1)));
+
+        let c = context(r#"{"a": [1, 2,"#);
+        assert!(!c.in_key());
+        assert_eq!(c.value_path(), Some(Path::root().key("a").index(2)));
+    }
+
+    #[test]
+    fn test_outline() {
+        let document = Document::new(
+            "untitled:1",
+            String::from(r#"{"a": {"b": [true]}, "c": 1}"#),
+        );
+        let symbols = Value::Array(document.symbols());
+        assert_eq!(
+            symbols.pointer("/0/children/0/children/0/detail"),
+            Some(&json!("true"))
+        );
+        assert_eq!(symbols.pointer("/0/children/0/name"), Some(&json!("b")));
+        assert_eq!(
+            symbols.pointer("/0/range"),
+            Some(&json!({
+                "start": { "line": 0, "character": 1 },
+                "end": { "line": 0, "character": 19 },
+            }))
+        );
+        assert_eq!(symbols.pointer("/1/kind"), Some(&json!(SYMBOL_NUMBER)));
+    }
+}
@@ -0,0 +1,233 @@
+//! A language server for json files, speaking LSP over stdin and stdout.
+//!
+//! Documents are synced in full on every change. The server publishes the parser's
+//! diagnostics, formats with the pretty printer, outlines keys as document symbols
+//! and, when a document's `$schema` names a local file, offers hover and completion
+//! from that schema.
+
+mod document;
+mod rpc;
+mod schema;
+
+use crate::{errors::LspError, json, value::Value};
+use anyhow::Result;
+use document::Document;
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+/// Answer requests read from `input` on `output` until the client sends `exit`.
+///
+/// Fails if the client exits, or closes `input`, without asking to shut down first.
+pub fn serve(mut input: impl BufRead, output: impl Write) -> Result<()> {
+    let mut server = Server {
+        output,
+        documents: HashMap::new(),
+        initialized: false,
+        shutdown: false,
+    };
+
+    while let Some(body) = rpc::read_message(&mut input)? {
+        let message = match crate::parse(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                let reason = e.to_string();
+                server.respond(Value::Null, Err(LspError::Parse { reason }))?;
+                continue;
+            }
+        };
+        let method = match message.get("method") {
+            Some(Value::String(method)) => method.as_str(),
+            // Responses to requests we never send.
+            _ => continue,
+        };
+        let params = message.get("params").unwrap_or(&Value::Null);
+
+        match message.get("id") {
+            Some(id) => {
+                let result = server.request(method, params);
+                server.respond(id.clone(), result)?;
+            }
+            None if method == "exit" => break,
+            None => server.notification(method, params)?,
+        }
+    }
+
+    if !server.shutdown {
+        anyhow::bail!("the client exited without a shutdown request");
+    }
+    Ok(())
+}
+
+struct Server<W> {
+    output: W,
+    /// Open documents by URI.
+    documents: HashMap<String, Document>,
+    initialized: bool,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, LspError> {
+        if method == "initialize" {
+            self.initialized = true;
+            return Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": { "openClose": true, "change": 1 },
+                    "documentFormattingProvider": true,
+                    "documentSymbolProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": ["\""] },
+                },
+                "serverInfo": { "name": "json-parser", "version": env!("CARGO_PKG_VERSION") },
+            }));
+        }
+        if !self.initialized {
+            Err(LspError::NotInitialized)?
+        }
+        if self.shutdown {
+            let reason = String::from("the server is shutting down");
+            Err(LspError::InvalidRequest { reason })?
+        }
+
+        Ok(match method {
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            }
+            "textDocument/formatting" => {
+                let indent = params
+                    .pointer("/options/tabSize")
+                    .and_then(as_usize)
+                    .unwrap_or(2);
+                self.document(params)?
+                    .formatting(indent)
+                    .map_or(Value::Null, Value::Array)
+            }
+            "textDocument/documentSymbol" => Value::Array(self.document(params)?.symbols()),
+            "textDocument/hover" => {
+                let (document, offset) = self.position(params)?;
+                document.hover(offset).unwrap_or(Value::Null)
+            }
+            "textDocument/completion" => {
+                let (document, offset) = self.position(params)?;
+                Value::Array(document.completion(offset))
+            }
+            method => Err(LspError::MethodNotFound {
+                method: String::from(method),
+            })?,
+        })
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Result<()> {
+        // Notifications get no answer, malformed ones are dropped.
+        let Ok(uri) = string(params, "/textDocument/uri") else {
+            return Ok(());
+        };
+
+        match method {
+            "textDocument/didOpen" => {
+                let Ok(text) = string(params, "/textDocument/text") else {
+                    return Ok(());
+                };
+                let document = Document::new(uri, String::from(text));
+                self.documents.insert(String::from(uri), document);
+            }
+            "textDocument/didChange" => {
+                // Full sync, the last change holds the whole text.
+                let Some(Value::String(text)) = params
+                    .pointer("/contentChanges")
+                    .and_then(|changes| match changes {
+                        Value::Array(changes) => changes.last(),
+                        _ => None,
+                    })
+                    .and_then(|change| change.get("text"))
+                else {
+                    return Ok(());
+                };
+                let Some(document) = self.documents.get_mut(uri) else {
+                    return Ok(());
+                };
+                document.update(text.clone());
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return self.publish(uri, Vec::new());
+            }
+            _ => return Ok(()),
+        }
+
+        let diagnostics = self
+            .documents
+            .get(uri)
+            .map(Document::diagnostics)
+            .unwrap_or_default();
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&mut self, uri: &str, diagnostics: Vec<Value>) -> Result<()> {
+        let params = json!({ "uri": uri, "diagnostics": diagnostics });
+        let message = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": params,
+        });
+        rpc::write_message(&mut self.output, &message)
+    }
+
+    fn respond(&mut self, id: Value, result: Result<Value, LspError>) -> Result<()> {
+        let message = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": e.code(), "message": e.to_string() },
+            }),
+        };
+        rpc::write_message(&mut self.output, &message)
+    }
+
+    /// The open document `params` refers to.
+    fn document(&self, params: &Value) -> Result<&Document, LspError> {
+        let uri = string(params, "/textDocument/uri")?;
+        self.documents
+            .get(uri)
+            .ok_or_else(|| LspError::InvalidParams {
+                reason: format!("{uri} is not open"),
+            })
+    }
+
+    /// The open document and the byte offset `params` point at.
+    fn position(&self, params: &Value) -> Result<(&Document, usize), LspError> {
+        let document = self.document(params)?;
+        let line = number(params, "/position/line")?;
+        let character = number(params, "/position/character")?;
+        Ok((document, document.offset(line, character)))
+    }
+}
+
+fn string<'a>(params: &'a Value, pointer: &str) -> Result<&'a str, LspError> {
+    match params.pointer(pointer) {
+        Some(Value::String(s)) => Ok(s),
+        _ => Err(LspError::InvalidParams {
+            reason: format!("expected a string at {pointer}"),
+        }),
+    }
+}
+
+fn number(params: &Value, pointer: &str) -> Result<usize, LspError> {
+    params
+        .pointer(pointer)
+        .and_then(as_usize)
+        .ok_or_else(|| LspError::InvalidParams {
+            reason: format!("expected a number at {pointer}"),
+        })
+}
+
+fn as_usize(value: &Value) -> Option<usize> {
+    match value {
+        Value::Number(crate::Number::Integer(n)) => usize::try_from(*n).ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,76 @@
+//! Base protocol framing, every message is a `Content-Length` header followed by a
+//! JSON body.
+
+use crate::value::Value;
+use anyhow::{Context, Result};
+use std::io::{BufRead, Write};
+
+/// Largest body accepted, a bogus header must not make us allocate gigabytes.
+const MAX_LENGTH: usize = 64 << 20;
+
+/// Read the body of the next message, `None` once the client closed the stream.
+pub(super) fn read_message(input: &mut impl BufRead) -> Result<Option<String>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let value = value.trim();
+                length = Some(
+                    value
+                        .parse::<usize>()
+                        .with_context(|| format!("parse Content-Length {value:?}"))?,
+                );
+            }
+        }
+    }
+
+    let length = length.context("missing Content-Length header")?;
+    if length > MAX_LENGTH {
+        anyhow::bail!("message of {length} bytes is larger than {MAX_LENGTH}");
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).context("read message body")?;
+    String::from_utf8(body)
+        .context("message body is not UTF-8")
+        .map(Some)
+}
+
+pub(super) fn write_message(output: &mut impl Write, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_framing() {
+        let mut out = Vec::new();
+        write_message(&mut out, &json!({ "id": 1 })).unwrap();
+        write_message(&mut out, &json!("é")).unwrap();
+        assert!(out.starts_with(b"Content-Length: 8\r\n\r\n{\"id\":1}"));
+
+        let mut input = out.as_slice();
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), "{\"id\":1}");
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), "\"é\"");
+        assert_eq!(read_message(&mut input).unwrap(), None);
+
+        let mut input = "Content-Type: x\r\n\r\n{}".as_bytes();
+        assert!(read_message(&mut input).is_err());
+        let mut input = "Content-Length: 10\r\n\r\n{}".as_bytes();
+        assert!(read_message(&mut input).is_err());
+    }
+}
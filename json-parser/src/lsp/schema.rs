@@ -0,0 +1,240 @@
+//! Looking up the JSON Schema a document references in its `$schema` member.
+//!
+//! Only local files are read, either a `file://` URI or a path relative to the
+//! document. Within a schema `properties`, `additionalProperties`, `items`,
+//! `prefixItems`, local `$ref`s and the branches of `allOf`, `anyOf` and `oneOf` are
+//! followed.
+
+use crate::{path::Segment, value::Value};
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+/// How many `$ref`s are followed in a row before giving up on a cycle.
+const MAX_REFS: usize = 32;
+
+/// Read the schema `reference` points to, relative to the document at `uri`.
+pub(super) fn load(uri: &str, reference: &str) -> Result<Value> {
+    let path = match reference.strip_prefix("file://") {
+        Some(_) => uri_to_path(reference),
+        None if reference.contains("://") => {
+            anyhow::bail!("only local schemas are supported, not {reference:?}")
+        }
+        None => uri_to_path(uri)
+            .and_then(|document| Some(document.parent()?.join(percent_decode(reference)?))),
+    }
+    .with_context(|| format!("resolve schema {reference:?}"))?;
+
+    crate::parse_file(&path).with_context(|| format!("load schema {}", path.display()))
+}
+
+/// The sub-schema describing the value at `path`.
+pub(super) fn resolve<'a>(root: &'a Value, path: &[Segment]) -> Option<&'a Value> {
+    path.iter().try_fold(deref(root, root)?, |schema, segment| {
+        let child = branches(root, schema).find_map(|branch| match segment {
+            Segment::Key(key) => branch
+                .get("properties")
+                .and_then(|properties| properties.get(key))
+                .or_else(|| branch.get("additionalProperties")),
+            Segment::Index(i) => match (branch.get("prefixItems"), branch.get("items")) {
+                (Some(Value::Array(prefix)), _) if *i < prefix.len() => prefix.get(*i),
+                // Before draft 2020-12 an array of `items` is what `prefixItems` is now.
+                (_, Some(Value::Array(items))) => items.get(*i),
+                (_, items) => items,
+            },
+        })?;
+        deref(root, child)
+    })
+}
+
+/// Property names the schema declares, along with their schema.
+pub(super) fn properties<'a>(root: &'a Value, schema: &'a Value) -> Vec<(&'a str, &'a Value)> {
+    let mut found: Vec<(&str, &Value)> = Vec::new();
+    for branch in branches(root, schema) {
+        if let Some(Value::Object(members)) = branch.get("properties") {
+            for (name, property) in members {
+                if !found.iter().any(|(seen, _)| seen == name) {
+                    found.push((name, deref(root, property).unwrap_or(property)));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Values worth suggesting, from `const`, `enum` and the `boolean` and `null` types.
+pub(super) fn values(root: &Value, schema: &Value) -> Vec<Value> {
+    let mut found = Vec::new();
+    for branch in branches(root, schema) {
+        if let Some(value) = branch.get("const") {
+            found.push(value.clone());
+        }
+        if let Some(Value::Array(values)) = branch.get("enum") {
+            found.extend(values.iter().cloned());
+        }
+        for name in types(branch) {
+            match name {
+                "boolean" => found.extend([Value::Bool(true), Value::Bool(false)]),
+                "null" => found.push(Value::Null),
+                _ => {}
+            }
+        }
+    }
+    found.dedup();
+    found
+}
+
+/// Markdown describing a schema, `None` when it has nothing to say.
+pub(super) fn describe(root: &Value, schema: &Value) -> Option<String> {
+    let mut sections = Vec::new();
+    for branch in branches(root, schema) {
+        if let Some(Value::String(title)) = branch.get("title") {
+            sections.push(format!("**{title}**"));
+        }
+        if let Some(Value::String(description)) = branch.get("description") {
+            sections.push(description.clone());
+        }
+        let types = types(branch);
+        if !types.is_empty() {
+            sections.push(format!("Type: `{}`", types.join(" | ")));
+        }
+    }
+    let values = values(root, schema);
+    if !values.is_empty() && values.iter().any(|value| !matches!(value, Value::Bool(_))) {
+        let values: Vec<_> = values.iter().map(|value| format!("`{value}`")).collect();
+        sections.push(format!("Allowed: {}", values.join(", ")));
+    }
+
+    sections.dedup();
+    (!sections.is_empty()).then(|| sections.join("\n\n"))
+}
+
+/// The schema and the branches of its `allOf`, `anyOf` and `oneOf`, with `$ref`s followed.
+fn branches<'a>(root: &'a Value, schema: &'a Value) -> impl Iterator<Item = &'a Value> {
+    let nested = ["allOf", "anyOf", "oneOf"]
+        .into_iter()
+        .filter_map(|keyword| match schema.get(keyword) {
+            Some(Value::Array(branches)) => Some(branches.iter()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|branch| deref(root, branch));
+    std::iter::once(schema).chain(nested)
+}
+
+/// Follow `$ref`s pointing inside the same schema, e.g. `#/$defs/item`.
+fn deref<'a>(root: &'a Value, mut schema: &'a Value) -> Option<&'a Value> {
+    for _ in 0..MAX_REFS {
+        let Some(Value::String(reference)) = schema.get("$ref") else {
+            return Some(schema);
+        };
+        schema = root.pointer(&percent_decode(reference.strip_prefix('#')?)?)?;
+    }
+    None
+}
+
+fn types(schema: &Value) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(name)) => vec![name.as_str()],
+        Some(Value::Array(names)) => names
+            .iter()
+            .filter_map(|name| match name {
+                Value::String(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Path of a `file://` URI.
+pub(super) fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    // `file://localhost/...` names the local host too.
+    let path = path.strip_prefix("localhost").unwrap_or(path);
+    Some(PathBuf::from(percent_decode(path)?))
+}
+
+/// Decode `%XX` escapes, `None` if they don't form valid UTF-8.
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .filter(|_| b == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = tail.get(2..).unwrap_or_default();
+            }
+            None => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{json, path::Path};
+
+    #[test]
+    fn test_resolve() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "description": "Who" },
+                "tags": { "type": "array", "items": { "$ref": "#/$defs/tag" } },
+                "pair": { "prefixItems": [{ "type": "integer" }, { "type": "string" }] },
+            },
+            "allOf": [{ "properties": { "debug": { "type": "boolean" } } }],
+            "$defs": { "tag": { "enum": ["a", "b"] } },
+        });
+
+        let at = |path: Path| resolve(&schema, path.segments());
+        assert_eq!(
+            at(Path::root().key("name")),
+            schema.pointer("/properties/name")
+        );
+        assert_eq!(
+            at(Path::root().key("tags").index(3)),
+            schema.pointer("/$defs/tag")
+        );
+        assert_eq!(
+            at(Path::root().key("pair").index(1)),
+            schema.pointer("/properties/pair/prefixItems/1")
+        );
+        assert_eq!(
+            at(Path::root().key("debug")),
+            schema.pointer("/allOf/0/properties/debug")
+        );
+        assert_eq!(at(Path::root().key("missing")), None);
+
+        let names: Vec<_> = properties(&schema, &schema)
+            .iter()
+            .map(|(n, _)| *n)
+            .collect();
+        assert_eq!(names, ["name", "tags", "pair", "debug"]);
+
+        let tag = at(Path::root().key("tags").index(0)).unwrap();
+        assert_eq!(values(&schema, tag), [json!("a"), json!("b")]);
+        assert_eq!(describe(&schema, tag).unwrap(), "Allowed: `\"a\"`, `\"b\"`");
+
+        let looping = json!({ "$ref": "#" });
+        assert_eq!(resolve(&looping, &[]), None);
+    }
+
+    #[test]
+    fn test_uri_to_path() {
+        assert_eq!(
+            uri_to_path("file:///tmp/my%20dir/a.json"),
+            Some(PathBuf::from("/tmp/my dir/a.json"))
+        );
+        assert_eq!(uri_to_path("untitled:1"), None);
+    }
+}
@@ -0,0 +1,135 @@
+/// Build a `Value` from JSON-like syntax.
+///
+/// Any Rust expression that converts `Into<Value>` can be interpolated, object keys are
+/// string literals or parenthesized expressions that convert `Into<String>`.
+///
+/// ```
+/// use json_parser::json;
+///
+/// let id = 7;
+/// let value = json!({ "a": [1, 2, null], "b": id, ("c".to_uppercase()): { "ok": true } });
+/// assert_eq!(value.to_string(), r#"{"a":[1,2,null],"b":7,"C":{"ok":true}}"#);
+/// ```
+#[macro_export]
+macro_rules! json {
+    // Arrays, `[$($elems,)*]` holds the items built so far.
+    (@array [$($elems:expr,)*]) => {
+        $crate::__private::vec![$($elems,)*]
+    };
+    (@array [$($elems:expr,)*] , $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)*] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] null $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(null),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!([$($array)*]),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] {$($object:tt)*} $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!({$($object)*}),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $next:expr , $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!($next),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $last:expr) => {
+        $crate::json!(@array [$($elems,)* $crate::json!($last),])
+    };
+
+    // Objects, `[$($members,)*]` holds the (key, value) pairs built so far.
+    (@object [$($members:expr,)*]) => {
+        $crate::__private::vec![$($members,)*]
+    };
+    (@object [$($members:expr,)*] , $($rest:tt)*) => {
+        $crate::json!(@object [$($members,)*] $($rest)*)
+    };
+    (@object [$($members:expr,)*] $key:tt : null $($rest:tt)*) => {
+        $crate::json!(@object [$($members,)* ($crate::json!(@key $key), $crate::json!(null)),] $($rest)*)
+    };
+    (@object [$($members:expr,)*] $key:tt : [$($array:tt)*] $($rest:tt)*) => {
+        $crate::json!(@object [$($members,)* ($crate::json!(@key $key), $crate::json!([$($array)*])),] $($rest)*)
+    };
+    (@object [$($members:expr,)*] $key:tt : {$($object:tt)*} $($rest:tt)*) => {
+        $crate::json!(@object [$($members,)* ($crate::json!(@key $key), $crate::json!({$($object)*})),] $($rest)*)
+    };
+    (@object [$($members:expr,)*] $key:tt : $value:expr , $($rest:tt)*) => {
+        $crate::json!(@object [$($members,)* ($crate::json!(@key $key), $crate::json!($value)),] $($rest)*)
+    };
+    (@object [$($members:expr,)*] $key:tt : $value:expr) => {
+        $crate::json!(@object [$($members,)* ($crate::json!(@key $key), $crate::json!($value)),])
+    };
+
+    (@key ($key:expr)) => {
+        $crate::__private::String::from($key)
+    };
+    (@key $key:literal) => {
+        $crate::__private::String::from($key)
+    };
+
+    (null) => {
+        $crate::Value::Null
+    };
+    ([$($tt:tt)*]) => {
+        $crate::Value::Array($crate::json!(@array [] $($tt)*))
+    };
+    ({$($tt:tt)*}) => {
+        $crate::Value::Object($crate::json!(@object [] $($tt)*))
+    };
+    ($other:expr) => {
+        $crate::Value::from($other)
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Number, Value};
+
+    #[test]
+    fn test_json_macro() {
+        let name = String::from("x");
+        let missing: Option<i32> = None;
+
+        let value = json!({
+            "a": [1, 2.5, null, [], {}],
+            "b": name,
+            "c": { "nested": [true, false, missing] },
+            "d": -3,
+        });
+
+        let expected = Value::Object(vec![
+            (
+                String::from("a"),
+                Value::Array(vec![
+                    Value::Number(Number::Integer(1)),
+                    Value::Number(Number::Float(2.5)),
+                    Value::Null,
+                    Value::Array(vec![]),
+                    Value::Object(vec![]),
+                ]),
+            ),
+            (String::from("b"), Value::String(String::from("x"))),
+            (
+                String::from("c"),
+                Value::Object(vec![(
+                    String::from("nested"),
+                    Value::Array(vec![Value::Bool(true), Value::Bool(false), Value::Null]),
+                )]),
+            ),
+            (String::from("d"), Value::Number(Number::Integer(-3))),
+        ]);
+
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn test_from_collections() {
+        assert_eq!(json!(Some("s")).to_string(), r#""s""#);
+        assert_eq!(json!(u64::MAX).to_string(), "1.8446744073709552e19");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_hash_map() {
+        let map = std::collections::HashMap::from([("b", vec![1u8]), ("a", vec![])]);
+        assert_eq!(json!(map).to_string(), r#"{"a":[],"b":[1]}"#);
+    }
+}
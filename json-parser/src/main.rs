@@ -1,7 +1,19 @@
 use json_parser::run;
+use std::process::ExitCode;
 
-fn main() {
-    if let Err(e) = run() {
-        eprintln!("{e}");
-    };
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        // The reader went away, like `| head`, there is nobody left to tell.
+        Err(e)
+            if e.downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) =>
+        {
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{e:#}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,362 @@
+//! Newline delimited JSON (JSON Lines), one document per line.
+//!
+//! `par_map` spreads the work of big files over a thread pool: the input is cut into
+//! line aligned chunks, each chunk is handled by one worker and the results are handed
+//! back to the calling thread, in input order or as they complete.
+
+use crate::{parser::parse_str, value::Value};
+use anyhow::Result;
+use rayon::prelude::*;
+use std::{collections::BTreeMap, fmt, sync::mpsc};
+
+/// Size chunks are cut at, before being extended to the end of their last line.
+const CHUNK: usize = 1 << 20;
+
+/// Chunks started ahead of the last one handed back in order.
+const IN_FLIGHT: usize = 64;
+
+/// Non blank lines of `input` along with their 1-based line number.
+pub fn lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty())
+}
+
+/// Parse every document, stopping at the first invalid one.
+pub fn parse(input: &str) -> Result<Vec<Value>> {
+    lines(input)
+        .map(|(line, text)| {
+            parse_str(text).map_err(|e| {
+                let error = e.into();
+                LineError { line, error }.into()
+            })
+        })
+        .collect()
+}
+
+/// Order `par_map` hands results back in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Order {
+    /// Same order as the input.
+    #[default]
+    Ordered,
+    /// As soon as they are ready, lines of a chunk stay together.
+    Unordered,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParOptions {
+    pub order: Order,
+    /// Worker threads, `None` uses one per core.
+    pub jobs: Option<usize>,
+}
+
+/// A line that failed, with its line number in the whole input.
+#[derive(Debug)]
+pub struct LineError {
+    pub line: usize,
+    pub error: anyhow::Error,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+impl std::error::Error for LineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// Run `map` over every non blank line in parallel, `emit` gets each result along
+/// with its 1-based line number, on the calling thread.
+///
+/// At most `IN_FLIGHT` chunks, or two per thread if there are more threads, are
+/// started ahead of the last one emitted, so a slow chunk holds back the rest
+/// instead of having their results pile up. The first error `emit` returns stops
+/// the run and is returned, chunks already started finish but are dropped.
+pub fn par_map<T, M, E>(input: &str, options: ParOptions, map: M, emit: E) -> Result<()>
+where
+    T: Send,
+    M: Fn(&str) -> Result<T> + Sync,
+    E: FnMut(usize, Result<T, LineError>) -> Result<()>,
+{
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.jobs.unwrap_or(0))
+        .build()?;
+    let window = IN_FLIGHT.max(pool.current_num_threads() * 2);
+    run(
+        &pool,
+        &split(input, CHUNK),
+        options.order,
+        window,
+        map,
+        emit,
+    )
+}
+
+fn run<T, M, E>(
+    pool: &rayon::ThreadPool,
+    chunks: &[&str],
+    order: Order,
+    window: usize,
+    map: M,
+    mut emit: E,
+) -> Result<()>
+where
+    T: Send,
+    M: Fn(&str) -> Result<T> + Sync,
+    E: FnMut(usize, Result<T, LineError>) -> Result<()>,
+{
+    let first_lines = pool.install(|| {
+        let newlines = chunks
+            .par_iter()
+            .map(|chunk| chunk.bytes().filter(|&b| b == b'\n').count())
+            .collect::<Vec<_>>();
+        newlines
+            .iter()
+            .scan(1, |line, n| {
+                let first = *line;
+                *line += n;
+                Some(first)
+            })
+            .collect::<Vec<_>>()
+    });
+
+    // The scope's body runs on the calling thread, which starts chunks as earlier
+    // ones are emitted.
+    pool.in_place_scope(|s| {
+        let (sender, receiver) = mpsc::channel();
+        let map = &map;
+        let mut work = chunks.iter().zip(first_lines).enumerate();
+        // Spawn the next chunk, false once there are none left.
+        let mut start = || {
+            let Some((i, (chunk, first_line))) = work.next() else {
+                return false;
+            };
+            let sender = sender.clone();
+            s.spawn(move |_| {
+                let results = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    lines(chunk)
+                        .map(|(line, text)| {
+                            let line = first_line + line - 1;
+                            (line, map(text).map_err(|error| LineError { line, error }))
+                        })
+                        .collect::<Vec<_>>()
+                }));
+                // The receiving end only goes away once everything is received.
+                let _ = sender.send((i, results));
+            });
+            true
+        };
+
+        // Chunks that completed before the ones preceding them, fewer than `window`.
+        let mut pending = BTreeMap::new();
+        let (mut started, mut emitted) = (0, 0);
+        loop {
+            while started < emitted + window.max(1) && start() {
+                started += 1;
+            }
+            if emitted == started {
+                break;
+            }
+            let Ok((i, results)) = receiver.recv() else {
+                break;
+            };
+            // A panic in `map` is the caller's, not the pool's.
+            let results = results.unwrap_or_else(|payload| std::panic::resume_unwind(payload));
+            match order {
+                Order::Unordered => {
+                    for (line, result) in results {
+                        emit(line, result)?;
+                    }
+                    emitted += 1;
+                }
+                Order::Ordered => {
+                    pending.insert(i, results);
+                    while let Some(results) = pending.remove(&emitted) {
+                        for (line, result) in results {
+                            emit(line, result)?;
+                        }
+                        emitted += 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Cut `input` into chunks of about `size` bytes ending right after a newline.
+fn split(input: &str, size: usize) -> Vec<&str> {
+    let mut chunks = Vec::with_capacity(input.len() / size + 1);
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let end = rest
+            .as_bytes()
+            .iter()
+            .skip(size)
+            .position(|&b| b == b'\n')
+            .map_or(rest.len(), |i| size + i + 1);
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_parse() {
+        let input = "{\"a\": 1}\n\n  \n[true]\r\n\"x\"";
+        assert_eq!(
+            parse(input).unwrap(),
+            vec![json!({ "a": 1 }), json!([true]), json!("x")]
+        );
+
+        let err = parse("1\n\n[1,]\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: [line 1, column 3] E0003 trailing-comma: Trailing Comma."
+        );
+    }
+
+    #[test]
+    fn test_par_map() {
+        // Several chunks, with a few invalid and blank lines in between.
+        let mut input = String::new();
+        for i in 0..100_000 {
+            match i % 1000 {
+                0 => input.push_str("[1,]\n"),
+                1 => input.push('\n'),
+                _ => input.push_str(&format!(
+                    "{{\"id\": {i}, \"pad\": \"{}\"}}\n",
+                    "x".repeat(i % 13)
+                )),
+            }
+        }
+        assert!(split(&input, CHUNK).len() > 2);
+
+        let map = |text: &str| Ok(parse_str(text)?.get("id").cloned());
+        let mut ordered = Vec::new();
+        let mut errors = Vec::new();
+        par_map(&input, ParOptions::default(), map, |line, result| {
+            match result {
+                Ok(id) => ordered.push((line, id)),
+                Err(e) => errors.push(e.to_string()),
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(ordered.len(), 99_800);
+        assert!(ordered.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(ordered
+            .iter()
+            .all(|(line, id)| *id == Some(json!(line - 1))));
+        assert_eq!(errors.len(), 100);
+        assert_eq!(
+            errors[1],
+            "line 1001: [line 1, column 3] E0003 trailing-comma: Trailing Comma."
+        );
+
+        let options = ParOptions {
+            order: Order::Unordered,
+            jobs: Some(3),
+        };
+        let mut unordered = Vec::new();
+        par_map(&input, options, map, |line, result| {
+            if let Ok(id) = result {
+                unordered.push((line, id))
+            }
+            Ok(())
+        })
+        .unwrap();
+        unordered.sort_by_key(|(line, _)| *line);
+        assert_eq!(unordered, ordered);
+    }
+
+    #[test]
+    fn test_window() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // One line per chunk, the first one is slow.
+        let input: String = (0..200).map(|i| format!("{i}\n")).collect();
+        let chunks = split(&input, 1);
+        assert_eq!(chunks.len(), 200);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+
+        for order in [Order::Ordered, Order::Unordered] {
+            let mapped = AtomicUsize::new(0);
+            let map = |text: &str| {
+                if text == "0" {
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                }
+                mapped.fetch_add(1, Ordering::SeqCst);
+                Ok(parse_str(text)?)
+            };
+            let mut lines = Vec::new();
+            run(&pool, &chunks, order, 8, map, |line, result| {
+                // Ordered, nothing past the window is started while the first chunk
+                // holds the rest back.
+                if line == 1 && order == Order::Ordered {
+                    assert!(mapped.load(Ordering::SeqCst) <= 8);
+                }
+                assert_eq!(result.unwrap(), json!(line - 1));
+                lines.push(line);
+                Ok(())
+            })
+            .unwrap();
+            if order == Order::Ordered {
+                assert!(lines.windows(2).all(|w| w[0] < w[1]));
+            }
+            lines.sort();
+            assert_eq!(lines, (1..=200).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_emit_error() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let input: String = (0..200).map(|i| format!("{i}\n")).collect();
+        let chunks = split(&input, 1);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+
+        for order in [Order::Ordered, Order::Unordered] {
+            let mapped = AtomicUsize::new(0);
+            let map = |text: &str| {
+                mapped.fetch_add(1, Ordering::SeqCst);
+                Ok(parse_str(text)?)
+            };
+            let mut emitted = 0;
+            let result = run(&pool, &chunks, order, 8, map, |_, _| {
+                emitted += 1;
+                match emitted {
+                    3 => anyhow::bail!("closed"),
+                    _ => Ok(()),
+                }
+            });
+            assert_eq!(result.unwrap_err().to_string(), "closed");
+            assert_eq!(emitted, 3);
+            // Nothing is started past the window of the last chunk emitted.
+            assert!(mapped.load(Ordering::SeqCst) <= 3 + 8);
+        }
+    }
+}
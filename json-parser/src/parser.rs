@@ -0,0 +1,209 @@
+use crate::{
+    borrowed::BorrowedValue,
+    errors::{Error, ErrorKind},
+    lexer::Lexer,
+    path::Path,
+    stage2,
+    token::{Span, Token},
+    value::Value,
+};
+use alloc::{string::String, vec::Vec};
+
+/// Deepest nesting of arrays and objects accepted, guards against stack overflows.
+pub(crate) const MAX_DEPTH: usize = 128;
+
+/// Source span of every value in a document, keyed by its JSON Pointer.
+#[cfg(feature = "std")]
+pub type Spans = std::collections::HashMap<String, Span>;
+
+/// Source span of every value in a document, keyed by its JSON Pointer.
+///
+/// A `BTreeMap` without std, which has no `HashMap`.
+#[cfg(not(feature = "std"))]
+pub type Spans = alloc::collections::BTreeMap<String, Span>;
+
+/// Builds a value tree out of the lexer's tokens.
+pub(crate) struct Parser<'a> {
+    tokens: alloc::vec::IntoIter<(Token<'a>, Span)>,
+    /// Span of the last token consumed.
+    last: Span,
+    depth: usize,
+    spans: Option<Spans>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(tokens: Vec<(Token<'a>, Span)>) -> Self {
+        Self {
+            tokens: tokens.into_iter(),
+            last: Span::default(),
+            depth: 0,
+            spans: None,
+        }
+    }
+
+    /// Record the span of every value while parsing.
+    pub fn with_spans(mut self) -> Self {
+        self.spans = Some(Spans::new());
+        self
+    }
+
+    /// Parse a single document, all tokens must be consumed.
+    pub fn parse(mut self) -> Result<(BorrowedValue<'a>, Spans), Error> {
+        let value = self.parse_value(&Path::root())?;
+
+        if self.tokens.next().is_some() {
+            Err(self.invalid_syntax("end of input"))?
+        }
+
+        Ok((value, self.spans.unwrap_or_default()))
+    }
+
+    fn parse_value(&mut self, path: &Path) -> Result<BorrowedValue<'a>, Error> {
+        let (token, start) = self.next().ok_or_else(|| self.invalid_syntax("value"))?;
+
+        let (value, end) = match token {
+            Token::LBraces | Token::LBracket => {
+                self.depth += 1;
+                if self.depth > MAX_DEPTH {
+                    Err(Error::new(ErrorKind::TooDeep { max: MAX_DEPTH }, start))?
+                }
+                let parsed = match token {
+                    Token::LBraces => self.parse_object(path)?,
+                    _ => self.parse_array(path)?,
+                };
+                self.depth -= 1;
+                parsed
+            }
+            Token::Literal(s) => (BorrowedValue::String(s), start),
+            Token::Number(n) => (BorrowedValue::Number(n), start),
+            Token::True => (BorrowedValue::Bool(true), start),
+            Token::False => (BorrowedValue::Bool(false), start),
+            Token::Null => (BorrowedValue::Null, start),
+            _ => Err(self.invalid_syntax("value"))?,
+        };
+
+        if let Some(spans) = self.spans.as_mut() {
+            let span = Span {
+                start: start.start,
+                end: end.end,
+            };
+            spans.insert(path.pointer(), span);
+        }
+
+        Ok(value)
+    }
+
+    /// Parse object members, the opening brace is already consumed.
+    ///
+    /// Returns the object along with the span of its closing brace.
+    fn parse_object(&mut self, path: &Path) -> Result<(BorrowedValue<'a>, Span), Error> {
+        let mut members = Vec::new();
+
+        loop {
+            let key = match self.next() {
+                Some((Token::RBraces, span)) if members.is_empty() => {
+                    return Ok((BorrowedValue::Object(members), span))
+                }
+                Some((Token::Literal(key), _)) => key,
+                Some(_) => Err(self.invalid_syntax("key"))?,
+                None => Err(self.end_of_input(ErrorKind::UnclosedObject))?,
+            };
+
+            match self.next() {
+                Some((Token::Colon, _)) => {}
+                _ => Err(self.invalid_syntax(":"))?,
+            }
+
+            let value = self.parse_value(&path.key(&key))?;
+            members.push((key, value));
+
+            match self.next() {
+                Some((Token::Coma, _)) => {}
+                Some((Token::RBraces, span)) => return Ok((BorrowedValue::Object(members), span)),
+                Some(_) => Err(self.invalid_syntax(", or }"))?,
+                None => Err(self.end_of_input(ErrorKind::UnclosedObject))?,
+            }
+        }
+    }
+
+    /// Parse array items, the opening bracket is already consumed.
+    ///
+    /// Returns the array along with the span of its closing bracket.
+    fn parse_array(&mut self, path: &Path) -> Result<(BorrowedValue<'a>, Span), Error> {
+        let mut items = Vec::new();
+
+        if let Some(span) = self.eat(Token::RBracket) {
+            return Ok((BorrowedValue::Array(items), span));
+        }
+
+        loop {
+            let value = self.parse_value(&path.index(items.len()))?;
+            items.push(value);
+
+            match self.next() {
+                Some((Token::Coma, _)) => {}
+                Some((Token::RBracket, span)) => return Ok((BorrowedValue::Array(items), span)),
+                Some(_) => Err(self.invalid_syntax(", or ]"))?,
+                None => Err(self.end_of_input(ErrorKind::UnclosedArray))?,
+            }
+        }
+    }
+
+    fn next(&mut self) -> Option<(Token<'a>, Span)> {
+        let (token, span) = self.tokens.next()?;
+        self.last = span;
+        Some((token, span))
+    }
+
+    /// Consume the next token if it is `expected`.
+    fn eat(&mut self, expected: Token<'a>) -> Option<Span> {
+        if self
+            .tokens
+            .as_slice()
+            .first()
+            .is_some_and(|(t, _)| *t == expected)
+        {
+            return self.next().map(|(_, span)| span);
+        }
+        None
+    }
+
+    fn invalid_syntax(&self, expected: &str) -> Error {
+        let expected = String::from(expected);
+        Error::new(ErrorKind::InvalidSyntax { expected }, self.last)
+    }
+
+    /// An error at the end of the last token, for input that ends too early.
+    fn end_of_input(&self, kind: ErrorKind) -> Error {
+        Error::at(kind, self.last.end)
+    }
+}
+
+/// Parse `input` into a `Value`.
+///
+/// Valid documents go through the stage 2 fast path, the lexer only runs to find out
+/// what is wrong with invalid ones.
+pub(crate) fn parse_str(input: &str) -> Result<Value, Error> {
+    parse_borrowed_str(input).map(BorrowedValue::into_owned)
+}
+
+/// Like `parse_str`, strings borrow from `input` unless they contain escapes.
+pub(crate) fn parse_borrowed_str(input: &str) -> Result<BorrowedValue<'_>, Error> {
+    match stage2::parse(input) {
+        Some(value) => Ok(value),
+        None => parse_tokens(input),
+    }
+}
+
+/// Lex and parse `input`, one char at a time.
+pub(crate) fn parse_tokens(input: &str) -> Result<BorrowedValue<'_>, Error> {
+    let tokens = Lexer::new(input).lex()?;
+    Parser::new(tokens).parse().map(|(value, _)| value)
+}
+
+/// Like `parse_str`, also returning the span of every value.
+pub(crate) fn parse_str_with_spans(input: &str) -> Result<(Value, Spans), Error> {
+    let tokens = Lexer::new(input).lex()?;
+    let (value, spans) = Parser::new(tokens).with_spans().parse()?;
+    Ok((value.into_owned(), spans))
+}
@@ -0,0 +1,637 @@
+//! Scrub secrets out of a document while keeping the rest of its text as is.
+//!
+//! Rules are read from a json file, the first rule matching a value decides what
+//! happens to it and nothing below a matched value is looked at.
+//!
+//! ```json
+//! {
+//!   "rules": [
+//!     { "glob": "*password*", "action": "remove" },
+//!     { "regex": "(?i)^api_?key$", "action": "mask", "with": "[redacted]" },
+//!     { "path": "$.users[*].email", "action": "hash" },
+//!     { "glob": "card_number", "action": "partial", "keep": 4 }
+//!   ]
+//! }
+//! ```
+//!
+//! `glob` (`*` and `?`) and `regex` match the keys of object members, `path` is a
+//! JSONPath matched against the whole path: `.key`, `['key']`, `[0]`, `.*`, `[*]`
+//! and `..` for any depth.
+//!
+//! - `remove` drops the member or array item along with its comma.
+//! - `mask` replaces the value with a string, `"***"` by default.
+//! - `hash` replaces the value with an HMAC-SHA256 of its compact json, the same
+//!   value always gets the same hash for a given key, so values can still be
+//!   correlated across the document.
+//! - `partial` keeps the last `keep` chars, 4 by default, and masks the rest.
+//!
+//! Only the text of redacted values is rewritten, indentation, key order and the
+//! spelling of numbers and escapes elsewhere stay untouched.
+
+use crate::{
+    errors::RedactError,
+    path::{Path, Segment},
+    token::{Span, Token},
+    value::Value,
+};
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use sha2::Sha256;
+use std::ops::Range;
+
+/// Redaction rules, see the module documentation for the format.
+#[derive(Debug)]
+pub struct Rules(Vec<Rule>);
+
+#[derive(Debug)]
+struct Rule {
+    matcher: Matcher,
+    action: Action,
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Glob(String),
+    Regex(Regex),
+    Path(Vec<Step>),
+}
+
+#[derive(Debug, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    /// `*`, any key or index.
+    Any,
+    /// `..`, any number of segments.
+    Descendants,
+}
+
+#[derive(Debug, PartialEq)]
+enum Action {
+    Remove,
+    Mask(String),
+    Hash,
+    Partial { keep: usize },
+}
+
+impl Rules {
+    /// Read rules from the json text of a rules file.
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::from_value(&crate::parse(input)?)
+    }
+
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let root = Path::root();
+        let invalid = |path: &Path, reason: &str| RedactError::InvalidRule {
+            path: path.to_string(),
+            reason: String::from(reason),
+        };
+
+        let Some(Value::Array(items)) = value.get("rules") else {
+            Err(invalid(&root, "expected an object with a \"rules\" array"))?
+        };
+        let mut rules = Vec::with_capacity(items.len());
+        for (i, item) in items.iter().enumerate() {
+            let path = root.key("rules").index(i);
+            let Value::Object(members) = item else {
+                Err(invalid(&path, "expected an object"))?
+            };
+            let string = |key: &str| match item.get(key) {
+                None => Ok(None),
+                Some(Value::String(s)) => Ok(Some(s.as_str())),
+                Some(_) => Err(invalid(&path.key(key), "expected a string")),
+            };
+
+            let matcher = match (string("glob")?, string("regex")?, string("path")?) {
+                (Some(glob), None, None) => Matcher::Glob(String::from(glob)),
+                (None, Some(regex), None) => Matcher::Regex(
+                    Regex::new(regex).map_err(|e| invalid(&path.key("regex"), &e.to_string()))?,
+                ),
+                (None, None, Some(json_path)) => Matcher::Path(steps(json_path)?),
+                _ => Err(invalid(
+                    &path,
+                    "expected one of \"glob\", \"regex\" or \"path\"",
+                ))?,
+            };
+
+            let action = match string("action")? {
+                Some("remove") => Action::Remove,
+                Some("mask") => Action::Mask(String::from(string("with")?.unwrap_or("***"))),
+                Some("hash") => Action::Hash,
+                Some("partial") => match item.get("keep") {
+                    None => Action::Partial { keep: 4 },
+                    Some(Value::Number(crate::Number::Integer(keep))) if *keep >= 0 => {
+                        Action::Partial {
+                            keep: usize::try_from(*keep).unwrap_or(usize::MAX),
+                        }
+                    }
+                    Some(_) => Err(invalid(&path.key("keep"), "expected a positive integer"))?,
+                },
+                _ => Err(invalid(
+                    &path.key("action"),
+                    "expected \"remove\", \"mask\", \"hash\" or \"partial\"",
+                ))?,
+            };
+
+            // Typos such as `"keys"` would otherwise silently match nothing.
+            const KNOWN: [&str; 6] = ["glob", "regex", "path", "action", "with", "keep"];
+            if let Some((key, _)) = members
+                .iter()
+                .find(|(key, _)| !KNOWN.contains(&key.as_str()))
+            {
+                Err(invalid(&path.key(key), "unknown field"))?
+            }
+
+            rules.push(Rule { matcher, action });
+        }
+        Ok(Rules(rules))
+    }
+
+    fn find(&self, path: &Path) -> Option<&Action> {
+        let key = match path.segments().last() {
+            Some(Segment::Key(key)) => Some(key.as_str()),
+            _ => None,
+        };
+        self.0
+            .iter()
+            .find(|rule| match (&rule.matcher, key) {
+                (Matcher::Glob(glob), Some(key)) => glob_matches(glob, key),
+                (Matcher::Regex(regex), Some(key)) => regex.is_match(key),
+                (Matcher::Path(steps), _) => path_matches(steps, path.segments()),
+                _ => false,
+            })
+            .map(|rule| &rule.action)
+    }
+}
+
+/// Apply `rules` to the json text `input`, `key` keys the hashes.
+pub fn redact(input: &str, rules: &Rules, key: &[u8]) -> Result<String> {
+    let value = crate::parse(input)?;
+    let tokens = crate::lex(input)?;
+    let node = Node::read(&tokens, &mut 0, None).context("match tokens to values")?;
+
+    let mut redactor = Redactor {
+        rules,
+        mac: Hmac::<Sha256>::new_from_slice(key).context("create hmac")?,
+        edits: Vec::new(),
+    };
+    match rules.find(&Path::root()) {
+        Some(Action::Remove) => Err(RedactError::RemoveRoot)?,
+        Some(action) => redactor.replace(&value, &node, action),
+        None => redactor.walk(&value, &node, &Path::root()),
+    }
+
+    let mut edits = redactor.edits;
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut out = String::with_capacity(input.len());
+    let mut at = 0;
+    for (range, replacement) in edits {
+        out.push_str(input.get(at..range.start).unwrap_or_default());
+        out.push_str(&replacement);
+        at = range.end;
+    }
+    out.push_str(input.get(at..).unwrap_or_default());
+    Ok(out)
+}
+
+/// A key of 16 random bytes, hashes are only comparable within a single run.
+pub fn random_key() -> Vec<u8> {
+    use std::hash::{BuildHasher, Hasher};
+
+    // `RandomState` keys derive from OS randomness, which is all that is needed here.
+    (0..2)
+        .flat_map(|_| {
+            std::collections::hash_map::RandomState::new()
+                .build_hasher()
+                .finish()
+                .to_le_bytes()
+        })
+        .collect()
+}
+
+/// Where a value sits in the source text.
+struct Node {
+    /// Start of the member's key, or of the value for array items.
+    start: usize,
+    value: Range<usize>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    /// Read the value starting at token `at`, `None` if the tokens aren't a document.
+    fn read(tokens: &[(Token, Span)], at: &mut usize, key: Option<usize>) -> Option<Node> {
+        let (token, span) = tokens.get(*at)?;
+        *at += 1;
+        let start = span.start.offset;
+        let mut children = Vec::new();
+
+        let end = match token {
+            Token::LBraces | Token::LBracket => loop {
+                let (next, next_span) = tokens.get(*at)?;
+                match next {
+                    Token::RBraces | Token::RBracket => {
+                        *at += 1;
+                        break next_span.end.offset;
+                    }
+                    Token::Coma => *at += 1,
+                    Token::Literal(_) if *token == Token::LBraces => {
+                        // The key, then the colon.
+                        *at += 2;
+                        children.push(Node::read(tokens, at, Some(next_span.start.offset))?);
+                    }
+                    _ => children.push(Node::read(tokens, at, None)?),
+                }
+            },
+            _ => span.end.offset,
+        };
+
+        Some(Node {
+            start: key.unwrap_or(start),
+            value: start..end,
+            children,
+        })
+    }
+}
+
+struct Redactor<'a> {
+    rules: &'a Rules,
+    mac: Hmac<Sha256>,
+    edits: Vec<(Range<usize>, String)>,
+}
+
+impl Redactor<'_> {
+    fn walk(&mut self, value: &Value, node: &Node, path: &Path) {
+        let children: Vec<(Path, &Value)> = match value {
+            Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (path.index(i), item))
+                .collect(),
+            Value::Object(members) => members
+                .iter()
+                .map(|(key, member)| (path.key(key), member))
+                .collect(),
+            _ => return,
+        };
+
+        let mut removed = Vec::new();
+        for (i, ((path, child), child_node)) in children.iter().zip(&node.children).enumerate() {
+            match self.rules.find(path) {
+                Some(Action::Remove) => removed.push(i),
+                Some(action) => self.replace(child, child_node, action),
+                None => self.walk(child, child_node, path),
+            }
+        }
+        self.remove(&node.children, &removed);
+    }
+
+    /// Remove the `removed` items of a container, along with the commas between them.
+    fn remove(&mut self, children: &[Node], removed: &[usize]) {
+        let kept = |i: &usize| !removed.contains(i);
+        let last_kept = (0..children.len()).rev().find(kept);
+
+        for &i in removed {
+            let (Some(node), Some(next)) = (children.get(i), children.get(i + 1)) else {
+                continue;
+            };
+            // Up to the next item, unless only removed items follow.
+            if (i + 1..children.len()).any(|j| kept(&j)) {
+                self.edits.push((node.start..next.start, String::new()));
+            }
+        }
+
+        let Some(last) = children.last() else {
+            return;
+        };
+        if !removed.contains(&(children.len() - 1)) {
+            return;
+        }
+        // The trailing run, from the end of the last item kept so its comma goes too.
+        let start = match last_kept.and_then(|i| children.get(i)) {
+            Some(kept) => kept.value.end,
+            None => children.first().map_or(last.start, |first| first.start),
+        };
+        self.edits.push((start..last.value.end, String::new()));
+    }
+
+    fn replace(&mut self, value: &Value, node: &Node, action: &Action) {
+        let replacement = match action {
+            Action::Remove => return,
+            Action::Mask(with) => Value::from(with.as_str()),
+            Action::Hash => {
+                let mut mac = self.mac.clone();
+                mac.update(value.to_string().as_bytes());
+                let digest = mac.finalize().into_bytes();
+                let hex: String = digest.iter().take(8).map(|b| format!("{b:02x}")).collect();
+                Value::from(format!("hmac:{hex}"))
+            }
+            Action::Partial { keep } => {
+                let text = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                let len = text.chars().count();
+                // Showing all of a short secret would defeat the point.
+                let masked = match len > *keep {
+                    true => len - keep,
+                    false => len,
+                };
+                let mut partial = "*".repeat(masked);
+                partial.extend(text.chars().skip(masked));
+                Value::from(partial)
+            }
+        };
+        self.edits
+            .push((node.value.clone(), replacement.to_string()));
+    }
+}
+
+/// Whether `key` matches `glob`, where `*` is any run of chars and `?` any one char.
+pub(crate) fn glob_matches(glob: &str, key: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let key: Vec<char> = key.chars().collect();
+    let (mut g, mut k) = (0, 0);
+    // Position of the last `*` and of the key char it was tried against.
+    let mut star = None;
+
+    while k < key.len() {
+        match glob.get(g) {
+            Some('*') => {
+                star = Some((g, k));
+                g += 1;
+            }
+            Some(&c) if c == '?' || Some(&c) == key.get(k) => {
+                g += 1;
+                k += 1;
+            }
+            _ => match star {
+                // Let the `*` take one more char.
+                Some((star_g, star_k)) => {
+                    g = star_g + 1;
+                    k = star_k + 1;
+                    star = Some((star_g, star_k + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    glob.iter().skip(g).all(|c| *c == '*')
+}
+
+fn path_matches(steps: &[Step], path: &[Segment]) -> bool {
+    match steps.split_first() {
+        None => path.is_empty(),
+        Some((Step::Descendants, rest)) => {
+            (0..=path.len()).any(|k| path_matches(rest, path.get(k..).unwrap_or_default()))
+        }
+        Some((step, rest)) => match path.split_first() {
+            Some((segment, tail)) => {
+                let matches = match (step, segment) {
+                    (Step::Any, _) => true,
+                    (Step::Key(a), Segment::Key(b)) => a == b,
+                    (Step::Index(a), Segment::Index(b)) => a == b,
+                    _ => false,
+                };
+                matches && path_matches(rest, tail)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Parse a JSONPath such as `$.users[*]['e-mail']` or `$..token`.
+fn steps(json_path: &str) -> Result<Vec<Step>, RedactError> {
+    let chars: Vec<char> = json_path.chars().collect();
+    let error = |column: usize, expected| RedactError::InvalidPath {
+        path: String::from(json_path),
+        column: column + 1,
+        expected,
+    };
+    if chars.first() != Some(&'$') {
+        Err(error(0, "'$'"))?
+    }
+
+    let mut steps = Vec::new();
+    let mut i = 1;
+    while i < chars.len() {
+        let dots = chars[i..].iter().take_while(|c| **c == '.').count();
+        match dots {
+            0 => {}
+            1 => i += 1,
+            2 => {
+                steps.push(Step::Descendants);
+                i += 2;
+            }
+            _ => Err(error(i + 2, "a key"))?,
+        }
+
+        match chars.get(i) {
+            Some('[') => {
+                i += 1;
+                match chars.get(i) {
+                    Some('*') => {
+                        steps.push(Step::Any);
+                        i += 1;
+                    }
+                    Some(&quote @ ('\'' | '"')) => {
+                        let mut key = String::new();
+                        i += 1;
+                        loop {
+                            match chars.get(i) {
+                                Some('\\') => {
+                                    key.extend(chars.get(i + 1));
+                                    i += 2;
+                                }
+                                Some(&c) if c == quote => break,
+                                Some(&c) => {
+                                    key.push(c);
+                                    i += 1;
+                                }
+                                None => Err(error(i, "a closing quote"))?,
+                            }
+                        }
+                        steps.push(Step::Key(key));
+                        i += 1;
+                    }
+                    _ => {
+                        let digits: String = chars[i..]
+                            .iter()
+                            .take_while(|c| c.is_ascii_digit())
+                            .collect();
+                        let index = digits
+                            .parse()
+                            .map_err(|_| error(i, "an index, '*' or a quoted key"))?;
+                        steps.push(Step::Index(index));
+                        i += digits.len();
+                    }
+                }
+                if chars.get(i) != Some(&']') {
+                    Err(error(i, "']'"))?
+                }
+                i += 1;
+            }
+            Some('*') if dots > 0 => {
+                steps.push(Step::Any);
+                i += 1;
+            }
+            Some(_) if dots > 0 => {
+                let key: String = chars[i..]
+                    .iter()
+                    .take_while(|c| **c != '.' && **c != '[')
+                    .collect();
+                i += key.chars().count();
+                steps.push(Step::Key(key));
+            }
+            _ if dots > 0 => Err(error(i, "a key"))?,
+            _ => Err(error(i, "'.' or '['"))?,
+        }
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules(input: &str) -> Rules {
+        Rules::parse(input).unwrap()
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(glob_matches("*password*", "db_password_hash"));
+        assert!(glob_matches("token", "token"));
+        assert!(glob_matches("t?k*n", "taken"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("*password", "password_hint"));
+        assert!(!glob_matches("a?", "a"));
+    }
+
+    #[test]
+    fn test_steps() {
+        assert_eq!(
+            steps("$.users[*]['e-mail']..id[0].*").unwrap(),
+            [
+                Step::Key(String::from("users")),
+                Step::Any,
+                Step::Key(String::from("e-mail")),
+                Step::Descendants,
+                Step::Key(String::from("id")),
+                Step::Index(0),
+                Step::Any,
+            ]
+        );
+        assert_eq!(steps("$").unwrap(), []);
+        assert_eq!(
+            steps("users").unwrap_err().to_string(),
+            "[column 1] Invalid JSONPath \"users\", Expected '$'."
+        );
+        assert_eq!(
+            steps("$.a[x]").unwrap_err().to_string(),
+            "[column 5] Invalid JSONPath \"$.a[x]\", Expected an index, '*' or a quoted key."
+        );
+        assert_eq!(
+            steps("$['a").unwrap_err().to_string(),
+            "[column 5] Invalid JSONPath \"$['a\", Expected a closing quote."
+        );
+    }
+
+    #[test]
+    fn test_redact() {
+        let input = r#"{
+  "user": "ann",
+  "password": "hunter2",
+  "card": { "number": "4111111111111111", "cvv": 123 },
+  "emails": ["a@x.io", "b@x.io", "a@x.io"],
+  "Api-Key": 1e3
+}"#;
+        let rules = rules(
+            r#"{ "rules": [
+                { "glob": "pass*", "action": "remove" },
+                { "path": "$.card.number", "action": "partial" },
+                { "glob": "cvv", "action": "mask" },
+                { "path": "$.emails[*]", "action": "hash" },
+                { "regex": "(?i)^api-key$", "action": "mask", "with": "[redacted]" }
+            ] }"#,
+        );
+        let redacted = redact(input, &rules, b"key").unwrap();
+        let hash = |value: &str| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(b"key").unwrap();
+            mac.update(value.as_bytes());
+            let digest = mac.finalize().into_bytes();
+            digest
+                .iter()
+                .take(8)
+                .map(|b| format!("{b:02x}"))
+                .collect::<String>()
+        };
+        let (a, b) = (hash("\"a@x.io\""), hash("\"b@x.io\""));
+        assert_eq!(
+            redacted,
+            format!(
+                r#"{{
+  "user": "ann",
+  "card": {{ "number": "************1111", "cvv": "***" }},
+  "emails": ["hmac:{a}", "hmac:{b}", "hmac:{a}"],
+  "Api-Key": "[redacted]"
+}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let remove = |input: &str, glob: &str| {
+            let rules = rules(&format!(
+                r#"{{ "rules": [{{ "glob": "{glob}", "action": "remove" }}] }}"#
+            ));
+            redact(input, &rules, b"").unwrap()
+        };
+        assert_eq!(
+            remove(r#"{"a": 1, "b": 2, "c": 3}"#, "a"),
+            r#"{"b": 2, "c": 3}"#
+        );
+        assert_eq!(
+            remove(r#"{"a": 1, "b": 2, "c": 3}"#, "b"),
+            r#"{"a": 1, "c": 3}"#
+        );
+        assert_eq!(
+            remove(r#"{"a": 1, "b": 2, "c": 3}"#, "c"),
+            r#"{"a": 1, "b": 2}"#
+        );
+        assert_eq!(remove(r#"{"x": 1, "ab": 2, "ac": 3}"#, "a*"), r#"{"x": 1}"#);
+        assert_eq!(remove(r#"{"ab": 1, "x": 2, "ac": 3}"#, "a*"), r#"{"x": 2}"#);
+        assert_eq!(remove(r#"{"ab": 1, "ac": 2}"#, "a*"), r#"{}"#);
+        assert_eq!(
+            remove("{\n  \"a\": [1],\n  \"b\": {\"a\": 2}\n}", "a"),
+            "{\n  \"b\": {}\n}"
+        );
+
+        let items = rules(r#"{ "rules": [{ "path": "$..[1]", "action": "remove" }] }"#);
+        assert_eq!(redact("[[1, 2], 3]", &items, b"").unwrap(), "[[1]]");
+
+        let root = rules(r#"{ "rules": [{ "path": "$", "action": "remove" }] }"#);
+        assert!(redact("{}", &root, b"").is_err());
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let err = |input: &str| Rules::parse(input).unwrap_err().to_string();
+        assert_eq!(err("[]"), "$: expected an object with a \"rules\" array.");
+        assert_eq!(
+            err(r#"{ "rules": [{ "glob": "a", "path": "$", "action": "hash" }] }"#),
+            "$.rules[0]: expected one of \"glob\", \"regex\" or \"path\"."
+        );
+        assert_eq!(
+            err(r#"{ "rules": [{ "glob": "a", "action": "drop" }] }"#),
+            "$.rules[0].action: expected \"remove\", \"mask\", \"hash\" or \"partial\"."
+        );
+        assert_eq!(
+            err(r#"{ "rules": [{ "glob": "a", "action": "hash", "keys": 1 }] }"#),
+            "$.rules[0].keys: unknown field."
+        );
+        assert!(err(r#"{ "rules": [{ "regex": "(", "action": "hash" }] }"#)
+            .starts_with("$.rules[0].regex: regex parse error"));
+    }
+}
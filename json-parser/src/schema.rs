@@ -0,0 +1,340 @@
+//! JSON Schema inference from sample documents.
+//!
+//! Every sample is folded into a `Shape` recording which types showed up at each
+//! position, then the shape is written out as a draft 2020-12 schema:
+//!
+//! - Types seen across samples are merged, integers are absorbed by numbers.
+//! - Object members present in every sample of that object are `required`.
+//! - Array items are merged into a single `items` schema.
+//! - Strings get a `format` when all of them match the same one of `date-time`,
+//!   `date`, `uuid` or `email`.
+
+use crate::value::{Number, Value};
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Infer a schema every sample validates against.
+pub fn infer<'a>(samples: impl IntoIterator<Item = &'a Value>) -> Value {
+    let mut shape = Shape::default();
+    for sample in samples {
+        shape.add(sample);
+    }
+
+    let mut schema = vec![(String::from("$schema"), Value::from(DRAFT))];
+    if let Value::Object(members) = shape.to_schema() {
+        schema.extend(members);
+    }
+    Value::Object(schema)
+}
+
+/// Everything seen at one position across samples.
+#[derive(Default)]
+struct Shape {
+    null: bool,
+    boolean: bool,
+    integer: bool,
+    number: bool,
+    /// `None` until a string shows up, then `Some(None)` if their formats disagree.
+    string: Option<Option<StringFormat>>,
+    /// Merged items of every array, `None` until an array shows up.
+    array: Option<Box<Shape>>,
+    object: Option<ObjectShape>,
+}
+
+#[derive(Default)]
+struct ObjectShape {
+    /// How many objects were seen here.
+    count: usize,
+    /// Members in first seen order, along with how many objects had them.
+    properties: Vec<(String, usize, Shape)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum StringFormat {
+    DateTime,
+    Date,
+    Uuid,
+    Email,
+}
+
+impl Shape {
+    fn add(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.boolean = true,
+            Value::Number(Number::Integer(_)) => self.integer = true,
+            Value::Number(Number::Float(_)) => self.number = true,
+            Value::String(s) => {
+                let format = StringFormat::detect(s);
+                self.string = Some(match self.string {
+                    None => format,
+                    Some(seen) if seen == format => seen,
+                    Some(_) => None,
+                });
+            }
+            Value::Array(items) => {
+                let shape = self.array.get_or_insert_with(Default::default);
+                for item in items {
+                    shape.add(item);
+                }
+            }
+            Value::Object(members) => {
+                let object = self.object.get_or_insert_with(Default::default);
+                object.count += 1;
+
+                let mut seen = Vec::<&str>::new();
+                for (key, value) in members {
+                    let index = match object.properties.iter().position(|(k, _, _)| k == key) {
+                        Some(index) => index,
+                        None => {
+                            object.properties.push((key.clone(), 0, Shape::default()));
+                            object.properties.len() - 1
+                        }
+                    };
+                    if let Some((_, count, shape)) = object.properties.get_mut(index) {
+                        // Duplicate keys count once towards `required`.
+                        if !seen.contains(&key.as_str()) {
+                            *count += 1;
+                            seen.push(key);
+                        }
+                        shape.add(value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn to_schema(&self) -> Value {
+        let mut types = Vec::new();
+        if self.null {
+            types.push("null");
+        }
+        if self.boolean {
+            types.push("boolean");
+        }
+        if self.number {
+            types.push("number");
+        } else if self.integer {
+            types.push("integer");
+        }
+        if self.string.is_some() {
+            types.push("string");
+        }
+        if self.array.is_some() {
+            types.push("array");
+        }
+        if self.object.is_some() {
+            types.push("object");
+        }
+
+        let mut schema = Vec::new();
+        match types.as_slice() {
+            [] => {}
+            [single] => schema.push((String::from("type"), Value::from(*single))),
+            _ => schema.push((String::from("type"), Value::from(types))),
+        }
+
+        if let Some(Some(format)) = self.string {
+            schema.push((String::from("format"), Value::from(format.name())));
+        }
+
+        // Arrays that were always empty say nothing about their items.
+        if let Some(items) = self.array.as_ref().filter(|items| !items.is_empty()) {
+            schema.push((String::from("items"), items.to_schema()));
+        }
+
+        if let Some(object) = &self.object {
+            let properties = object
+                .properties
+                .iter()
+                .map(|(key, _, shape)| (key.clone(), shape.to_schema()))
+                .collect();
+            schema.push((String::from("properties"), Value::Object(properties)));
+
+            let required = object
+                .properties
+                .iter()
+                .filter(|(_, count, _)| *count == object.count)
+                .map(|(key, _, _)| Value::from(key.as_str()))
+                .collect::<Vec<_>>();
+            if !required.is_empty() {
+                schema.push((String::from("required"), Value::Array(required)));
+            }
+        }
+
+        Value::Object(schema)
+    }
+
+    fn is_empty(&self) -> bool {
+        !(self.null
+            || self.boolean
+            || self.integer
+            || self.number
+            || self.string.is_some()
+            || self.array.is_some()
+            || self.object.is_some())
+    }
+}
+
+impl StringFormat {
+    fn detect(s: &str) -> Option<Self> {
+        if is_date_time(s) {
+            Some(StringFormat::DateTime)
+        } else if is_date(s) {
+            Some(StringFormat::Date)
+        } else if is_uuid(s) {
+            Some(StringFormat::Uuid)
+        } else if is_email(s) {
+            Some(StringFormat::Email)
+        } else {
+            None
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            StringFormat::DateTime => "date-time",
+            StringFormat::Date => "date",
+            StringFormat::Uuid => "uuid",
+            StringFormat::Email => "email",
+        }
+    }
+}
+
+/// Whether `s` is exactly `n` ASCII digits forming a number in `range`.
+fn is_digits(s: &str, n: usize, range: std::ops::RangeInclusive<u32>) -> bool {
+    s.len() == n
+        && s.bytes().all(|b| b.is_ascii_digit())
+        && s.parse().is_ok_and(|v: u32| range.contains(&v))
+}
+
+/// RFC 3339 `full-date`, e.g. `2024-02-29`.
+fn is_date(s: &str) -> bool {
+    match s.split('-').collect::<Vec<_>>().as_slice() {
+        [year, month, day] => {
+            is_digits(year, 4, 0..=9999) && is_digits(month, 2, 1..=12) && is_digits(day, 2, 1..=31)
+        }
+        _ => false,
+    }
+}
+
+/// RFC 3339 `date-time`, e.g. `2024-02-29T13:45:00.5+01:00`.
+fn is_date_time(s: &str) -> bool {
+    let Some((date, time)) = s.split_once(['T', 't']) else {
+        return false;
+    };
+
+    let (time, offset) = match time.find(['Z', 'z', '+', '-']) {
+        Some(i) => time.split_at(i),
+        None => return false,
+    };
+    let offset_valid = match offset {
+        "Z" | "z" => true,
+        _ => match offset.get(1..).and_then(|o| o.split_once(':')) {
+            Some((hours, minutes)) => is_digits(hours, 2, 0..=23) && is_digits(minutes, 2, 0..=59),
+            None => false,
+        },
+    };
+
+    let (time, fraction) = time.split_once('.').unwrap_or((time, "0"));
+    let time_valid = match time.split(':').collect::<Vec<_>>().as_slice() {
+        // 60 allows for leap seconds.
+        [hours, minutes, seconds] => {
+            is_digits(hours, 2, 0..=23)
+                && is_digits(minutes, 2, 0..=59)
+                && is_digits(seconds, 2, 0..=60)
+        }
+        _ => false,
+    };
+
+    is_date(date)
+        && time_valid
+        && offset_valid
+        && !fraction.is_empty()
+        && fraction.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Hyphenated hex form, e.g. `123e4567-e89b-12d3-a456-426614174000`.
+fn is_uuid(s: &str) -> bool {
+    let groups = s.split('-').map(str::len).collect::<Vec<_>>();
+    groups == [8, 4, 4, 4, 12] && s.bytes().all(|b| b == b'-' || b.is_ascii_hexdigit())
+}
+
+/// Loose check, a local part and a dotted domain around a single `@`.
+fn is_email(s: &str) -> bool {
+    match s.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').filter(|label| !label.is_empty()).count() >= 2
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !s.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_infer() {
+        let samples = [
+            json!({
+                "id": "123e4567-e89b-12d3-a456-426614174000",
+                "created": "2024-02-29T13:45:00.5+01:00",
+                "score": 1,
+                "tags": ["a", "b"],
+                "owner": { "email": "a@example.com" },
+            }),
+            json!({
+                "id": "00000000-0000-0000-0000-000000000000",
+                "created": "2024-03-01T00:00:00Z",
+                "score": 2.5,
+                "tags": [],
+                "owner": null,
+                "note": "x",
+            }),
+        ];
+
+        let schema = infer(&samples);
+        assert_eq!(
+            schema,
+            json!({
+                "$schema": DRAFT,
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "format": "uuid" },
+                    "created": { "type": "string", "format": "date-time" },
+                    "score": { "type": "number" },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "owner": {
+                        "type": ["null", "object"],
+                        "properties": { "email": { "type": "string", "format": "email" } },
+                        "required": ["email"],
+                    },
+                    "note": { "type": "string" },
+                },
+                "required": ["id", "created", "score", "tags", "owner"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_formats() {
+        assert!(is_date("2024-12-31"));
+        assert!(!is_date("2024-13-01"));
+        assert!(is_date_time("2024-12-31t23:59:60z"));
+        assert!(!is_date_time("2024-12-31T23:59:00"));
+        assert!(!is_date_time("2024-12-31T23:59:00.+01:00"));
+        assert!(!is_uuid("123e4567e89b12d3a456426614174000"));
+        assert!(!is_email("a@localhost"));
+
+        let schema = infer(&[json!("2024-12-31"), json!("not a date")]);
+        assert_eq!(schema, json!({ "$schema": DRAFT, "type": "string" }));
+        assert_eq!(infer(&[]), json!({ "$schema": DRAFT }));
+    }
+}
@@ -0,0 +1,327 @@
+//! Stage 1 of parsing: find where every token starts, 64 bytes at a time.
+//!
+//! Each block is first classified into bitmasks (quotes, backslashes, structural
+//! characters and whitespace), bit `i` standing for byte `i`. Classification is the
+//! only part that touches bytes one by one, so it comes in SSE2 and AVX2 flavours
+//! picked at runtime (at build time without std), plus a scalar fallback. The rest
+//! works on the masks alone and is shared by all of them, which keeps the results
+//! identical.
+
+use crate::{
+    errors::{Error, ErrorKind},
+    token::{Position, Span},
+};
+use alloc::vec::Vec;
+
+const BLOCK: usize = 64;
+
+/// How blocks are classified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    Scalar,
+    Sse2,
+    Avx2,
+}
+
+impl Kernel {
+    /// The fastest kernel the running CPU supports.
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if has_avx2() {
+                return Kernel::Avx2;
+            }
+            if has_sse2() {
+                return Kernel::Sse2;
+            }
+        }
+        Kernel::Scalar
+    }
+
+    /// Whether the running CPU can use this kernel.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            Kernel::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse2 => has_sse2(),
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => has_avx2(),
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
+
+    fn classify(&self, block: &[u8; BLOCK]) -> Masks {
+        match self {
+            // SAFETY: `is_supported` checked the CPU has the required features.
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse2 if self.is_supported() => unsafe { classify_sse2(block) },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 if self.is_supported() => unsafe { classify_avx2(block) },
+            _ => classify_scalar(block),
+        }
+    }
+}
+
+// Runtime detection needs std, without it only what the build targets counts.
+
+#[cfg(target_arch = "x86_64")]
+fn has_sse2() -> bool {
+    #[cfg(feature = "std")]
+    return std::is_x86_feature_detected!("sse2");
+    #[cfg(not(feature = "std"))]
+    return cfg!(target_feature = "sse2");
+}
+
+#[cfg(target_arch = "x86_64")]
+fn has_avx2() -> bool {
+    #[cfg(feature = "std")]
+    return std::is_x86_feature_detected!("avx2");
+    #[cfg(not(feature = "std"))]
+    return cfg!(target_feature = "avx2");
+}
+
+/// Byte offset where every token of `input` starts, strings are indexed by their
+/// opening quote and other scalars by their first byte.
+///
+/// Falls back to the scalar kernel when the CPU doesn't support `kernel`.
+pub fn index(input: &[u8], kernel: Kernel) -> Result<Vec<usize>, Error> {
+    index_tokens(input, kernel)
+}
+
+pub(crate) fn index_tokens(input: &[u8], kernel: Kernel) -> Result<Vec<usize>, Error> {
+    let mut tokens = Vec::with_capacity(input.len() / 4);
+    let mut state = State::default();
+
+    for (n, chunk) in input.chunks(BLOCK).enumerate() {
+        let masks = match <&[u8; BLOCK]>::try_from(chunk) {
+            Ok(block) => kernel.classify(block),
+            Err(_) => {
+                // Pad the last block with whitespace, which never starts a token.
+                let mut block = [b' '; BLOCK];
+                block
+                    .iter_mut()
+                    .zip(chunk)
+                    .for_each(|(dst, src)| *dst = *src);
+                kernel.classify(&block)
+            }
+        };
+
+        let mut starts = state.token_starts(&masks);
+        while starts != 0 {
+            tokens.push(n * BLOCK + starts.trailing_zeros() as usize);
+            starts &= starts - 1;
+        }
+    }
+
+    if state.in_string != 0 {
+        let opening = tokens
+            .iter()
+            .rev()
+            .find(|&&offset| input.get(offset) == Some(&b'"'))
+            .copied()
+            .unwrap_or_default();
+        let span = Span {
+            start: Position::at(input, opening),
+            end: Position::at(input, input.len()),
+        };
+        Err(Error::new(ErrorKind::UnterminatedString, span))?
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Masks {
+    quote: u64,
+    backslash: u64,
+    structural: u64,
+    whitespace: u64,
+}
+
+/// What carries over from one block to the next.
+#[derive(Default)]
+struct State {
+    /// The previous block ended with a backslash that escapes this block's first byte.
+    escape: bool,
+    /// All ones while inside a string.
+    in_string: u64,
+    /// The previous block ended in the middle of a scalar.
+    scalar: bool,
+}
+
+impl State {
+    /// Bits of the block's bytes that start a token.
+    fn token_starts(&mut self, masks: &Masks) -> u64 {
+        let escaped = self.escaped(masks.backslash);
+        let quotes = masks.quote & !escaped;
+
+        // Set from an opening quote up to, not including, its closing quote.
+        let in_string = prefix_xor(quotes) ^ self.in_string;
+        self.in_string = ((in_string as i64) >> 63) as u64;
+
+        let strings = in_string | quotes;
+        let opening = quotes & in_string;
+        let structural = masks.structural & !strings;
+
+        let scalar = !(masks.whitespace | masks.structural | strings);
+        let scalar_start = scalar & !((scalar << 1) | self.scalar as u64);
+        self.scalar = scalar >> 63 == 1;
+
+        structural | opening | scalar_start
+    }
+
+    /// Bits of the bytes preceded by an escaping backslash.
+    fn escaped(&mut self, backslash: u64) -> u64 {
+        let mut escaped = self.escape as u64;
+        let mut escaping = backslash & !escaped;
+        self.escape = false;
+
+        // Backslashes are rare enough to walk one by one, an escaped backslash
+        // escapes nothing.
+        while escaping != 0 {
+            let i = escaping.trailing_zeros();
+            if i == 63 {
+                self.escape = true;
+                break;
+            }
+            let next = 1 << (i + 1);
+            escaped |= next;
+            escaping &= !(1 << i) & !next;
+        }
+
+        escaped
+    }
+}
+
+/// Bit `i` of the result is the xor of bits `0..=i` of `x`.
+fn prefix_xor(mut x: u64) -> u64 {
+    x ^= x << 1;
+    x ^= x << 2;
+    x ^= x << 4;
+    x ^= x << 8;
+    x ^= x << 16;
+    x ^= x << 32;
+    x
+}
+
+fn classify_scalar(block: &[u8; BLOCK]) -> Masks {
+    let mut masks = Masks::default();
+
+    for (i, byte) in block.iter().enumerate() {
+        let bit = 1 << i;
+        match byte {
+            b'"' => masks.quote |= bit,
+            b'\\' => masks.backslash |= bit,
+            b'{' | b'}' | b'[' | b']' | b':' | b',' => masks.structural |= bit,
+            b' ' | b'\t' | b'\n' | b'\r' => masks.whitespace |= bit,
+            _ => {}
+        }
+    }
+
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn classify_sse2(block: &[u8; BLOCK]) -> Masks {
+    use core::arch::x86_64::*;
+
+    let mut masks = Masks::default();
+
+    for (i, lane) in block.chunks_exact(16).enumerate() {
+        // SAFETY: `lane` is exactly 16 bytes long and the load is unaligned.
+        let v = unsafe { _mm_loadu_si128(lane.as_ptr() as *const __m128i) };
+        let eq = |byte: u8| {
+            let bits = _mm_movemask_epi8(_mm_cmpeq_epi8(v, _mm_set1_epi8(byte as i8)));
+            (bits as u16 as u64) << (16 * i)
+        };
+
+        masks.quote |= eq(b'"');
+        masks.backslash |= eq(b'\\');
+        masks.structural |= eq(b'{') | eq(b'}') | eq(b'[') | eq(b']') | eq(b':') | eq(b',');
+        masks.whitespace |= eq(b' ') | eq(b'\t') | eq(b'\n') | eq(b'\r');
+    }
+
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn classify_avx2(block: &[u8; BLOCK]) -> Masks {
+    use core::arch::x86_64::*;
+
+    let mut masks = Masks::default();
+
+    for (i, lane) in block.chunks_exact(32).enumerate() {
+        // SAFETY: `lane` is exactly 32 bytes long and the load is unaligned.
+        let v = unsafe { _mm256_loadu_si256(lane.as_ptr() as *const __m256i) };
+        let eq = |byte: u8| {
+            let bits = _mm256_movemask_epi8(_mm256_cmpeq_epi8(v, _mm256_set1_epi8(byte as i8)));
+            (bits as u32 as u64) << (32 * i)
+        };
+
+        masks.quote |= eq(b'"');
+        masks.backslash |= eq(b'\\');
+        masks.structural |= eq(b'{') | eq(b'}') | eq(b'[') | eq(b']') | eq(b':') | eq(b',');
+        masks.whitespace |= eq(b' ') | eq(b'\t') | eq(b'\n') | eq(b'\r');
+    }
+
+    masks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const KERNELS: [Kernel; 3] = [Kernel::Scalar, Kernel::Sse2, Kernel::Avx2];
+
+    #[test]
+    fn test_index() {
+        let input = br#"{"a\"b": [1, -2.5e3, true], "c\\": null}"#;
+
+        assert_eq!(
+            index_tokens(input, Kernel::Scalar).unwrap(),
+            vec![0, 1, 7, 9, 10, 11, 13, 19, 21, 25, 26, 28, 33, 35, 39]
+        );
+    }
+
+    #[test]
+    fn test_kernels_agree() {
+        // Long enough to cross block boundaries in the middle of strings, escapes
+        // and scalars.
+        let mut input = Vec::new();
+        for i in 0..200 {
+            input.extend_from_slice(
+                format!(r#"{{"k{i}\\\"": ["v\\\\", {i}.5, false]}},"#).as_bytes(),
+            );
+            input.extend(std::iter::repeat_n(b' ', i % 7));
+        }
+
+        let expected = index_tokens(&input, Kernel::Scalar).unwrap();
+        for kernel in KERNELS.into_iter().filter(Kernel::is_supported) {
+            assert_eq!(
+                index_tokens(&input, kernel).unwrap(),
+                expected,
+                "{kernel:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_masks_agree() {
+        let block: [u8; BLOCK] = std::array::from_fn(|i| (i * 37 % 128) as u8);
+
+        let expected = classify_scalar(&block);
+        for kernel in KERNELS.into_iter().filter(Kernel::is_supported) {
+            assert_eq!(kernel.classify(&block), expected, "{kernel:?}");
+        }
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        for kernel in KERNELS {
+            assert!(index_tokens(b"[\"abc\\\"]", kernel).is_err());
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Stage 2 of parsing: build a value tree straight from the stage 1 index.
+//!
+//! Each token's text runs from its offset to the next token's, so nothing is read
+//! twice and strings without escapes are borrowed from the input as they are. This
+//! path only answers whether the document is valid, `None` sends the caller back to
+//! the lexer which knows how to report what went wrong.
+
+use crate::{
+    borrowed::BorrowedValue,
+    lexer::{is_valid_number, to_number, Lexer},
+    parser::MAX_DEPTH,
+    stage1::{self, Kernel},
+    token::Token,
+};
+use alloc::{borrow::Cow, vec::Vec};
+
+/// Parse `input`, `None` if it is not a valid document.
+pub(crate) fn parse(input: &str) -> Option<BorrowedValue<'_>> {
+    let tokens = stage1::index_tokens(input.as_bytes(), Kernel::detect()).ok()?;
+    let mut tape = Tape {
+        input,
+        tokens,
+        next: 0,
+        depth: 0,
+    };
+
+    let value = tape.parse_value()?;
+    (tape.next == tape.tokens.len()).then_some(value)
+}
+
+struct Tape<'a> {
+    input: &'a str,
+    tokens: Vec<usize>,
+    /// Index of the next token to read.
+    next: usize,
+    depth: usize,
+}
+
+impl<'a> Tape<'a> {
+    fn parse_value(&mut self) -> Option<BorrowedValue<'a>> {
+        let raw = self.next()?;
+
+        match raw.as_bytes().first()? {
+            b'{' => self.nested(Self::parse_object),
+            b'[' => self.nested(Self::parse_array),
+            b'"' => read_string(raw).map(BorrowedValue::String),
+            b't' if raw == "true" => Some(BorrowedValue::Bool(true)),
+            b'f' if raw == "false" => Some(BorrowedValue::Bool(false)),
+            b'n' if raw == "null" => Some(BorrowedValue::Null),
+            _ if is_valid_number(raw) => to_number(raw).map(BorrowedValue::Number),
+            _ => None,
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Option<BorrowedValue<'a>>,
+    ) -> Option<BorrowedValue<'a>> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return None;
+        }
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    /// Parse object members, the opening brace is already consumed.
+    fn parse_object(&mut self) -> Option<BorrowedValue<'a>> {
+        let mut members = Vec::new();
+
+        if self.eat(b'}') {
+            return Some(BorrowedValue::Object(members));
+        }
+
+        loop {
+            let key = self.next().filter(|raw| raw.starts_with('"'))?;
+            let key = read_string(key)?;
+            if !self.eat(b':') {
+                return None;
+            }
+            members.push((key, self.parse_value()?));
+
+            match self.next()? {
+                "," => {}
+                "}" => return Some(BorrowedValue::Object(members)),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Parse array items, the opening bracket is already consumed.
+    fn parse_array(&mut self) -> Option<BorrowedValue<'a>> {
+        let mut items = Vec::new();
+
+        if self.eat(b']') {
+            return Some(BorrowedValue::Array(items));
+        }
+
+        loop {
+            items.push(self.parse_value()?);
+
+            match self.next()? {
+                "," => {}
+                "]" => return Some(BorrowedValue::Array(items)),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Text of the next token, without the whitespace following it.
+    fn next(&mut self) -> Option<&'a str> {
+        let start = *self.tokens.get(self.next)?;
+        self.next += 1;
+
+        let raw = match self.input.as_bytes().get(start)? {
+            // Structural characters are tokens of their own.
+            b'{' | b'}' | b'[' | b']' | b':' | b',' => self.input.get(start..start + 1)?,
+            _ => {
+                let end = self
+                    .tokens
+                    .get(self.next)
+                    .copied()
+                    .unwrap_or(self.input.len());
+                self.input.get(start..end)?
+            }
+        };
+        Some(raw.trim_end_matches([' ', '\t', '\n', '\r']))
+    }
+
+    /// Consume the next token if it is the structural character `expected`.
+    fn eat(&mut self, expected: u8) -> bool {
+        let found = self
+            .tokens
+            .get(self.next)
+            .and_then(|&offset| self.input.as_bytes().get(offset))
+            == Some(&expected);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+}
+
+/// Contents of the quoted string `raw`.
+fn read_string(raw: &str) -> Option<Cow<'_, str>> {
+    let contents = raw.strip_prefix('"')?.strip_suffix('"')?;
+
+    if !contents.bytes().any(|b| b == b'\\' || b < 0x20) {
+        return Some(Cow::Borrowed(contents));
+    }
+
+    // Escapes and errors are the lexer's business.
+    match Lexer::new(raw).next_token() {
+        Ok(Some((Token::Literal(s), span))) if span.end.offset == raw.len() => Some(s),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::parse_tokens;
+
+    #[test]
+    fn test_matches_lexer() {
+        let inputs = [
+            r#"{"a": [1, -2.5e3, true, false, null], "b\"\\": "é😀", "": {}}"#,
+            " [ [] , {} , \"x\" ]\n",
+            "-0",
+            "\"caf\u{e9}\"",
+            "1e400",
+            "[1,]",
+            "{\"a\" 1}",
+            "[\"\t\"]",
+            "tru",
+            "[1 2]",
+            "1\u{a0}",
+            "\"abc",
+            "",
+        ];
+
+        for input in inputs {
+            assert_eq!(parse(input), parse_tokens(input).ok(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn test_too_deep() {
+        let input = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert!(parse(&input).is_none());
+
+        let input = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        assert!(parse(&input).is_some());
+    }
+}
@@ -0,0 +1,527 @@
+//! Statistics and a shape summary of a document, in one streaming pass.
+//!
+//! Tokens are pulled from the lexer one at a time and checked against the grammar
+//! without ever building a `Value`, so memory stays proportional to the nesting depth
+//! plus the shape summary.
+
+use crate::{
+    errors::{Error, ErrorKind},
+    lexer::Lexer,
+    parser::MAX_DEPTH,
+    path::Path,
+    token::{Span, Token},
+    value::Value,
+};
+use anyhow::Result;
+use std::{collections::HashMap, fmt};
+
+/// How many of the largest arrays, strings and most frequent keys are reported.
+const TOP: usize = 10;
+
+/// What `stats` found in a document.
+#[derive(Debug, Default)]
+pub struct Stats {
+    /// Deepest nesting of arrays and objects, 0 for a scalar document.
+    pub max_depth: usize,
+    pub counts: Counts,
+    /// Longest arrays by item count, longest first.
+    pub largest_arrays: Vec<(Path, usize)>,
+    /// Longest strings by char count, longest first.
+    pub largest_strings: Vec<(Path, usize)>,
+    /// How often each key shows up, most frequent first.
+    pub keys: Vec<(String, usize)>,
+    /// The document with values replaced by their types, e.g. `{"id": number}`.
+    /// Items of an array are merged into one shape, `[string | null]`, objects
+    /// by the union of their keys, those missing from some marked `"key"?`.
+    pub shape: String,
+}
+
+/// Number of values of each type.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counts {
+    pub null: usize,
+    pub boolean: usize,
+    pub number: usize,
+    pub string: usize,
+    pub array: usize,
+    pub object: usize,
+}
+
+/// Gather statistics about the document in `input`.
+pub fn stats(input: &str) -> Result<Stats> {
+    Ok(Walker::new(input).run()?)
+}
+
+impl Stats {
+    pub fn to_text(&self) -> String {
+        let c = &self.counts;
+        let mut out = format!(
+            "max depth: {}\nvalues: {} (null {}, boolean {}, number {}, string {}, array {}, object {})\nunique keys: {}\n",
+            self.max_depth,
+            c.total(),
+            c.null,
+            c.boolean,
+            c.number,
+            c.string,
+            c.array,
+            c.object,
+            self.keys.len(),
+        );
+
+        let mut section = |title: &str, rows: Vec<(usize, String)>| {
+            if rows.is_empty() {
+                return;
+            }
+            out.push_str(&format!("{title}:\n"));
+            let width = rows
+                .iter()
+                .map(|(n, _)| n.to_string().len())
+                .max()
+                .unwrap_or(0);
+            for (n, label) in rows {
+                out.push_str(&format!("  {n:>width$}  {label}\n"));
+            }
+        };
+        section("largest arrays", top(&self.largest_arrays));
+        section("largest strings", top(&self.largest_strings));
+        section(
+            "key frequency",
+            self.keys
+                .iter()
+                .take(TOP)
+                .map(|(key, n)| (*n, key.clone()))
+                .collect(),
+        );
+
+        out.push_str(&format!("shape:\n  {}\n", self.shape));
+        out
+    }
+
+    pub fn to_json(&self) -> Value {
+        let c = &self.counts;
+        let located = |entries: &[(Path, usize)]| {
+            Value::Array(
+                entries
+                    .iter()
+                    .map(|(path, len)| {
+                        Value::Object(vec![
+                            (String::from("path"), Value::from(path.to_string())),
+                            (String::from("pointer"), Value::from(path.pointer())),
+                            (String::from("length"), Value::from(*len)),
+                        ])
+                    })
+                    .collect(),
+            )
+        };
+
+        Value::Object(vec![
+            (String::from("max_depth"), Value::from(self.max_depth)),
+            (
+                String::from("counts"),
+                Value::Object(vec![
+                    (String::from("null"), Value::from(c.null)),
+                    (String::from("boolean"), Value::from(c.boolean)),
+                    (String::from("number"), Value::from(c.number)),
+                    (String::from("string"), Value::from(c.string)),
+                    (String::from("array"), Value::from(c.array)),
+                    (String::from("object"), Value::from(c.object)),
+                ]),
+            ),
+            (String::from("unique_keys"), Value::from(self.keys.len())),
+            (
+                String::from("largest_arrays"),
+                located(&self.largest_arrays),
+            ),
+            (
+                String::from("largest_strings"),
+                located(&self.largest_strings),
+            ),
+            (
+                String::from("keys"),
+                Value::Object(
+                    self.keys
+                        .iter()
+                        .take(TOP)
+                        .map(|(key, n)| (key.clone(), Value::from(*n)))
+                        .collect(),
+                ),
+            ),
+            (String::from("shape"), Value::from(self.shape.as_str())),
+        ])
+    }
+}
+
+impl Counts {
+    pub fn total(&self) -> usize {
+        self.null + self.boolean + self.number + self.string + self.array + self.object
+    }
+}
+
+fn top(entries: &[(Path, usize)]) -> Vec<(usize, String)> {
+    entries
+        .iter()
+        .map(|(path, len)| (*len, path.to_string()))
+        .collect()
+}
+
+/// Keep the `TOP` longest entries, longest first.
+fn record(entries: &mut Vec<(Path, usize)>, path: &Path, len: usize) {
+    if entries.len() == TOP && entries.last().is_some_and(|(_, min)| *min >= len) {
+        return;
+    }
+    let at = entries.partition_point(|(_, n)| *n >= len);
+    entries.insert(at, (path.clone(), len));
+    entries.truncate(TOP);
+}
+
+struct Walker<'a> {
+    lexer: Lexer<'a>,
+    /// Span of the last token read.
+    last: Span,
+    depth: usize,
+    keys: HashMap<String, usize>,
+    stats: Stats,
+}
+
+impl<'a> Walker<'a> {
+    fn new(input: &'a str) -> Self {
+        Walker {
+            lexer: Lexer::new(input),
+            last: Span::default(),
+            depth: 0,
+            keys: HashMap::new(),
+            stats: Stats::default(),
+        }
+    }
+
+    fn run(mut self) -> Result<Stats, Error> {
+        let first = self
+            .next()?
+            .ok_or_else(|| Error::at(ErrorKind::EmptyFile, self.lexer.position()))?;
+        self.stats.shape = self.value(first, &Path::root())?.to_string();
+
+        if self.next()?.is_some() {
+            Err(self.invalid_syntax("end of input"))?
+        }
+
+        let mut keys = self.keys.into_iter().collect::<Vec<_>>();
+        keys.sort_by(|(a, m), (b, n)| n.cmp(m).then_with(|| a.cmp(b)));
+        self.stats.keys = keys;
+        Ok(self.stats)
+    }
+
+    /// Walk the value starting with `token`, returning its shape.
+    fn value(&mut self, token: Token<'a>, path: &Path) -> Result<Shape, Error> {
+        let counts = &mut self.stats.counts;
+
+        Ok(match token {
+            Token::Null => {
+                counts.null += 1;
+                Shape {
+                    null: true,
+                    ..Shape::default()
+                }
+            }
+            Token::True | Token::False => {
+                counts.boolean += 1;
+                Shape {
+                    boolean: true,
+                    ..Shape::default()
+                }
+            }
+            Token::Number(_) => {
+                counts.number += 1;
+                Shape {
+                    number: true,
+                    ..Shape::default()
+                }
+            }
+            Token::Literal(s) => {
+                counts.string += 1;
+                record(&mut self.stats.largest_strings, path, s.chars().count());
+                Shape {
+                    string: true,
+                    ..Shape::default()
+                }
+            }
+            Token::LBracket | Token::LBraces => {
+                self.depth += 1;
+                if self.depth > MAX_DEPTH {
+                    Err(Error::new(ErrorKind::TooDeep { max: MAX_DEPTH }, self.last))?
+                }
+                self.stats.max_depth = self.stats.max_depth.max(self.depth);
+                let shape = match token {
+                    Token::LBracket => self.array(path)?,
+                    _ => self.object(path)?,
+                };
+                self.depth -= 1;
+                shape
+            }
+            _ => Err(self.invalid_syntax("value"))?,
+        })
+    }
+
+    /// Walk array items, the opening bracket is already consumed.
+    fn array(&mut self, path: &Path) -> Result<Shape, Error> {
+        self.stats.counts.array += 1;
+        let mut items = Shape::default();
+        let mut len = 0;
+
+        let mut token = self.next()?;
+        if token != Some(Token::RBracket) {
+            loop {
+                let item = token
+                    .take()
+                    .ok_or_else(|| self.end_of_input(ErrorKind::UnclosedArray))?;
+                let shape = self.value(item, &path.index(len))?;
+                items.merge(shape);
+                len += 1;
+
+                match self.next()? {
+                    Some(Token::Coma) => token = self.next()?,
+                    Some(Token::RBracket) => break,
+                    Some(_) => Err(self.invalid_syntax(", or ]"))?,
+                    None => Err(self.end_of_input(ErrorKind::UnclosedArray))?,
+                }
+            }
+        }
+
+        record(&mut self.stats.largest_arrays, path, len);
+        Ok(Shape {
+            array: Some(Box::new(items)),
+            ..Shape::default()
+        })
+    }
+
+    /// Walk object members, the opening brace is already consumed.
+    fn object(&mut self, path: &Path) -> Result<Shape, Error> {
+        self.stats.counts.object += 1;
+        let mut members = Vec::<Member>::new();
+
+        let mut token = self.next()?;
+        if token != Some(Token::RBraces) {
+            loop {
+                let key = match token.take() {
+                    Some(Token::Literal(key)) => key.into_owned(),
+                    Some(_) => Err(self.invalid_syntax("key"))?,
+                    None => Err(self.end_of_input(ErrorKind::UnclosedObject))?,
+                };
+                if self.next()? != Some(Token::Colon) {
+                    Err(self.invalid_syntax(":"))?
+                }
+
+                let value = self.next()?.ok_or_else(|| self.invalid_syntax("value"))?;
+                let shape = self.value(value, &path.key(&key))?;
+                match members.iter_mut().find(|member| member.key == key) {
+                    Some(member) => member.shape.merge(shape),
+                    None => members.push(Member {
+                        key: key.clone(),
+                        shape,
+                        optional: false,
+                    }),
+                }
+                *self.keys.entry(key).or_default() += 1;
+
+                match self.next()? {
+                    Some(Token::Coma) => token = self.next()?,
+                    Some(Token::RBraces) => break,
+                    Some(_) => Err(self.invalid_syntax(", or }"))?,
+                    None => Err(self.end_of_input(ErrorKind::UnclosedObject))?,
+                }
+            }
+        }
+
+        Ok(Shape {
+            object: Some(members),
+            ..Shape::default()
+        })
+    }
+
+    fn next(&mut self) -> Result<Option<Token<'a>>, Error> {
+        let next = self.lexer.next_token()?;
+        Ok(next.map(|(token, span)| {
+            self.last = span;
+            token
+        }))
+    }
+
+    fn invalid_syntax(&self, expected: &str) -> Error {
+        let expected = String::from(expected);
+        Error::new(ErrorKind::InvalidSyntax { expected }, self.last)
+    }
+
+    /// An error at the end of the last token, for input that ends too early.
+    fn end_of_input(&self, kind: ErrorKind) -> Error {
+        Error::at(kind, self.last.end)
+    }
+}
+
+/// Union of the types seen at one place of the document.
+#[derive(Debug, Default)]
+struct Shape {
+    object: Option<Vec<Member>>,
+    /// The merged shape of every item.
+    array: Option<Box<Shape>>,
+    string: bool,
+    number: bool,
+    boolean: bool,
+    null: bool,
+}
+
+#[derive(Debug)]
+struct Member {
+    key: String,
+    shape: Shape,
+    /// Missing from some of the merged objects.
+    optional: bool,
+}
+
+impl Shape {
+    fn merge(&mut self, other: Shape) {
+        self.string |= other.string;
+        self.number |= other.number;
+        self.boolean |= other.boolean;
+        self.null |= other.null;
+
+        match (&mut self.array, other.array) {
+            (Some(items), Some(other)) => items.merge(*other),
+            (array @ None, other) => *array = other,
+            (Some(_), None) => {}
+        }
+
+        match (&mut self.object, other.object) {
+            (Some(members), Some(mut other)) => {
+                for member in members.iter_mut() {
+                    match other.iter().position(|o| o.key == member.key) {
+                        Some(i) => {
+                            let o = other.remove(i);
+                            member.optional |= o.optional;
+                            member.shape.merge(o.shape);
+                        }
+                        None => member.optional = true,
+                    }
+                }
+                members.extend(other.into_iter().map(|o| Member {
+                    optional: true,
+                    ..o
+                }));
+            }
+            (object @ None, other) => *object = other,
+            (Some(_), None) => {}
+        }
+    }
+}
+
+/// Alternatives joined with `|`, containers first.
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut alternatives = Vec::new();
+        if let Some(members) = &self.object {
+            let members = members
+                .iter()
+                .map(|m| {
+                    let optional = if m.optional { "?" } else { "" };
+                    format!("{}{optional}: {}", Value::String(m.key.clone()), m.shape)
+                })
+                .collect::<Vec<_>>();
+            alternatives.push(format!("{{{}}}", members.join(", ")));
+        }
+        if let Some(items) = &self.array {
+            alternatives.push(format!("[{items}]"));
+        }
+        for (seen, name) in [
+            (self.string, "string"),
+            (self.number, "number"),
+            (self.boolean, "boolean"),
+            (self.null, "null"),
+        ] {
+            if seen {
+                alternatives.push(String::from(name));
+            }
+        }
+        write!(f, "{}", alternatives.join(" | "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let input = r#"{
+            "users": [
+                {"id": 1, "name": "alice", "tags": ["a", "b", "c"]},
+                {"id": 2, "name": "bob", "tags": [], "admin": true},
+                {"id": 3, "name": null}
+            ],
+            "next": null
+        }"#;
+        let stats = stats(input).unwrap();
+
+        assert_eq!(stats.max_depth, 4);
+        assert_eq!(
+            stats.counts,
+            Counts {
+                null: 2,
+                boolean: 1,
+                number: 3,
+                string: 5,
+                array: 3,
+                object: 4,
+            }
+        );
+        assert_eq!(
+            stats
+                .largest_arrays
+                .iter()
+                .map(|(path, n)| (path.to_string(), *n))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("$.users[0].tags"), 3),
+                (String::from("$.users"), 3),
+                (String::from("$.users[1].tags"), 0),
+            ]
+        );
+        assert_eq!(stats.largest_strings[0].0.to_string(), "$.users[0].name");
+        assert_eq!(stats.keys.len(), 6);
+        assert_eq!(stats.keys[0], (String::from("id"), 3));
+        assert_eq!(
+            stats.shape,
+            r#"{"users": [{"id": number, "name": string | null, "tags"?: [string], "admin"?: boolean}], "next": null}"#
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(stats("").is_err());
+        assert!(stats("[1,]").is_err());
+        assert!(stats("{\"a\" 1}").is_err());
+        assert!(stats("[1] 2").is_err());
+        assert!(stats(&"[".repeat(MAX_DEPTH + 1)).is_err());
+        assert_eq!(stats("1").unwrap().shape, "number");
+    }
+
+    #[test]
+    fn test_shape() {
+        let shape = |input: &str| stats(input).unwrap().shape;
+        assert_eq!(shape("[]"), "[]");
+        assert_eq!(
+            shape("[1, \"a\", null, 2, [true], [[]]]"),
+            "[[[] | boolean] | string | number | null]"
+        );
+        assert_eq!(
+            shape("[{\"a\": 1}, 2, {\"b\": {\"c\": 1}}, {\"a\": \"x\", \"b\": {\"d\": 1}}]"),
+            r#"[{"a"?: string | number, "b"?: {"c"?: number, "d"?: number}} | number]"#
+        );
+        // One alternative however many items.
+        let items = (0..10_000)
+            .map(|i| format!("{{\"k{}\": {i}}}", i % 3))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(
+            shape(&format!("[{items}]")),
+            r#"[{"k0"?: number, "k1"?: number, "k2"?: number}]"#
+        );
+    }
+}
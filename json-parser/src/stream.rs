@@ -0,0 +1,213 @@
+//! Parsing from a `tokio::io::AsyncRead`, behind the `async` feature.
+//!
+//! Bytes are handed to an `incremental::Feeder` as they are read, so a document is
+//! parsed as soon as its last byte arrives instead of after the whole input has been
+//! buffered.
+//!
+//! ```no_run
+//! use tokio::io::{AsyncRead, AsyncReadExt};
+//!
+//! # async fn run(socket: impl AsyncRead + Unpin) -> anyhow::Result<()> {
+//!
+//! // At most 1 MiB of body.
+//! let value = json_parser::stream::read_value(socket.take(1 << 20)).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{incremental::Feeder, value::Value};
+use anyhow::{bail, Result};
+use futures_core::Stream;
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+/// How much is read at once.
+const CHUNK: usize = 8 * 1024;
+
+/// Read the single document of `reader`, only whitespace may follow it.
+pub async fn read_value<R: AsyncRead + Unpin>(mut reader: R) -> Result<Value> {
+    let mut feeder = Feeder::new();
+    let mut chunk = vec![0; CHUNK];
+    let mut value = None;
+
+    loop {
+        let n = reader.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        feeder.feed(chunk.get(..n).unwrap_or_default());
+        if let Some(next) = feeder.next_value()? {
+            if value.replace(next).is_some() {
+                bail!("unexpected data after the document");
+            }
+        }
+    }
+
+    match (value, feeder.finish()?) {
+        (Some(value), None) => Ok(value),
+        (None, Some(value)) => Ok(value),
+        (Some(_), Some(_)) => bail!("unexpected data after the document"),
+        (None, None) => Ok(crate::parse("")?),
+    }
+}
+
+/// Newline delimited documents of `reader`, each one as soon as it is complete.
+///
+/// An invalid document comes out as an error and the stream carries on with the
+/// next one, I/O errors end it.
+pub fn ndjson<R: AsyncRead + Unpin>(reader: R) -> Ndjson<R> {
+    Ndjson {
+        reader,
+        feeder: Feeder::new(),
+        chunk: vec![0; CHUNK],
+        eof: false,
+        done: false,
+    }
+}
+
+/// Stream of documents returned by `ndjson`.
+#[derive(Debug)]
+pub struct Ndjson<R> {
+    reader: R,
+    feeder: Feeder,
+    chunk: Vec<u8>,
+    eof: bool,
+    done: bool,
+}
+
+impl<R> Ndjson<R> {
+    /// Fail documents longer than `max_len` bytes rather than buffer them, see
+    /// `Feeder::with_max_len`.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.feeder = self.feeder.with_max_len(max_len);
+        self
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for Ndjson<R> {
+    type Item = Result<Value>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            if this.eof {
+                this.done = true;
+                return Poll::Ready(this.feeder.finish().map_err(Into::into).transpose());
+            }
+            if let Some(next) = this.feeder.next_value().transpose() {
+                return Poll::Ready(Some(next.map_err(Into::into)));
+            }
+
+            let mut chunk = ReadBuf::new(&mut this.chunk);
+            match ready!(Pin::new(&mut this.reader).poll_read(cx, &mut chunk)) {
+                Ok(()) if chunk.filled().is_empty() => this.eof = true,
+                Ok(()) => this.feeder.feed(chunk.filled()),
+                Err(e) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+    use std::{collections::VecDeque, io};
+
+    /// Hands out `chunks` one per read, with a pending read before each.
+    struct Chunks {
+        chunks: VecDeque<Vec<u8>>,
+        ready: bool,
+    }
+
+    impl Chunks {
+        fn new(input: &str, size: usize) -> Self {
+            Chunks {
+                chunks: input.as_bytes().chunks(size).map(<[u8]>::to_vec).collect(),
+                ready: false,
+            }
+        }
+    }
+
+    impl AsyncRead for Chunks {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            self.ready = !self.ready;
+            if self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if let Some(chunk) = self.chunks.pop_front() {
+                buf.put_slice(&chunk);
+            }
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    async fn collect(mut stream: Ndjson<Chunks>) -> Vec<Result<Value, String>> {
+        let mut items = Vec::new();
+        while let Some(item) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+        {
+            items.push(item.map_err(|e| e.to_string()));
+        }
+        items
+    }
+
+    #[tokio::test]
+    async fn test_read_value() {
+        let input = "{\"a\": [1, 2, {\"b\": \"c\"}]}\n";
+        for size in [1, 3, 100] {
+            let value = read_value(Chunks::new(input, size)).await.unwrap();
+            assert_eq!(value, json!({ "a": [1, 2, { "b": "c" }] }));
+        }
+        assert_eq!(read_value(Chunks::new(" 42 ", 1)).await.unwrap(), json!(42));
+
+        let err = |input: &'static str| async move {
+            read_value(Chunks::new(input, 2))
+                .await
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(err("[1] [2]").await, "unexpected data after the document");
+        assert_eq!(err("1 2").await, "unexpected data after the document");
+        assert_eq!(
+            err("[1, 2").await,
+            "[line 1, column 6] E0012 unclosed-array: Unterminated Array, Expected ]."
+        );
+        assert_eq!(
+            err("").await,
+            "[line 1, column 1] E0001 empty-file: Empty JSON file is invalid."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ndjson() {
+        let input = "{\"id\": 1}\n{\"id\": 2,}\n\"a long line\"\n3";
+        let items = collect(ndjson(Chunks::new(input, 4)).with_max_len(10)).await;
+        assert_eq!(
+            items,
+            [
+                Ok(json!({ "id": 1 })),
+                Err(String::from(
+                    "[line 2, column 9] E0003 trailing-comma: Trailing Comma."
+                )),
+                Err(String::from(
+                    "[line 3, column 1] E0015 too-large: Value is larger than 10 bytes."
+                )),
+                Ok(json!(3)),
+            ]
+        );
+    }
+}
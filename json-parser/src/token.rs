@@ -1,10 +1,18 @@
-#[derive(Debug, PartialEq)]
-pub enum Token {
+use crate::value::Number;
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+};
+
+/// A token, strings borrow from the input unless they contain escapes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token<'a> {
     LBraces,
     RBraces,
-    Literal(String),
-    Number(usize),
-    Array,
+    LBracket,
+    RBracket,
+    Literal(Cow<'a, str>),
+    Number(Number),
     Colon,
     Coma,
     Null,
@@ -12,14 +20,32 @@ pub enum Token {
     False,
 }
 
-impl Token {
+impl Token<'_> {
+    /// Copy borrowed strings so the token can outlive the input.
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::LBraces => Token::LBraces,
+            Token::RBraces => Token::RBraces,
+            Token::LBracket => Token::LBracket,
+            Token::RBracket => Token::RBracket,
+            Token::Literal(s) => Token::Literal(Cow::Owned(s.into_owned())),
+            Token::Number(n) => Token::Number(n),
+            Token::Colon => Token::Colon,
+            Token::Coma => Token::Coma,
+            Token::Null => Token::Null,
+            Token::True => Token::True,
+            Token::False => Token::False,
+        }
+    }
+
     pub(crate) fn literal(&self) -> String {
         match self {
             Token::LBraces => String::from("{"),
             Token::RBraces => String::from("}"),
-            Token::Literal(s) => s.clone(),
+            Token::LBracket => String::from("["),
+            Token::RBracket => String::from("]"),
+            Token::Literal(s) => s.to_string(),
             Token::Number(n) => n.to_string(),
-            Token::Array => String::from("[]"),
             Token::Colon => String::from(":"),
             Token::Coma => String::from(","),
             Token::Null => String::from("null"),
@@ -28,3 +54,40 @@ impl Token {
         }
     }
 }
+
+/// A location in the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column, counted in chars.
+    pub column: usize,
+    /// 0-based byte offset.
+    pub offset: usize,
+}
+
+/// The region of source text a token or value was read from, `end` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Position {
+    /// Position of the byte at `offset` in `input`.
+    pub(crate) fn at(input: &[u8], offset: usize) -> Self {
+        let before = input.get(..offset).unwrap_or(input);
+        let line_start = before
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        let line = before.get(line_start..).unwrap_or_default();
+
+        Position {
+            line: before.iter().filter(|&&b| b == b'\n').count() + 1,
+            // Continuation bytes don't start a char.
+            column: line.iter().filter(|&&b| b & 0xC0 != 0x80).count() + 1,
+            offset: before.len(),
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+/// A parsed JSON document.
+///
+/// Object members keep their source order, duplicate keys included.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+/// A JSON number, integers that fit in an `i64` are kept exact.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Value {
+    /// Look up the first member named `key`, `None` if this is not an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Follow an RFC 6901 JSON Pointer such as `/items/0/name`.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        if !pointer.is_empty() && !pointer.starts_with('/') {
+            return None;
+        }
+        pointer.split('/').skip(1).try_fold(self, |value, segment| {
+            let segment = segment.replace("~1", "/").replace("~0", "~");
+            match value {
+                Value::Object(_) => value.get(&segment),
+                Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Name of the value's JSON type.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+
+    /// Serialize with newlines and `indent` spaces per nesting level.
+    pub fn to_pretty_string(&self, indent: usize) -> String {
+        let mut out = String::new();
+        write_pretty(self, indent, 0, &mut out);
+        out
+    }
+}
+
+impl Number {
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Number::Integer(n) => n as f64,
+            Number::Float(n) => n,
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Integer(n) => write!(f, "{n}"),
+            // JSON has no representation for these.
+            Number::Float(n) if !n.is_finite() => write!(f, "null"),
+            // `Debug` keeps the fraction on whole floats ("1.0") and uses
+            // exponents for very large or small ones.
+            Number::Float(n) => write!(f, "{n:?}"),
+        }
+    }
+}
+
+/// Compact serialization.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::String(s) => write_string(s, f),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(key, f)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_pretty(value: &Value, indent: usize, depth: usize, out: &mut String) {
+    let pad = |depth: usize| " ".repeat(indent * depth);
+
+    match value {
+        Value::Array(items) if !items.is_empty() => {
+            out.push_str("[\n");
+            for (i, item) in items.iter().enumerate() {
+                out.push_str(&pad(depth + 1));
+                write_pretty(item, indent, depth + 1, out);
+                out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&pad(depth));
+            out.push(']');
+        }
+        Value::Object(members) if !members.is_empty() => {
+            out.push_str("{\n");
+            for (i, (key, value)) in members.iter().enumerate() {
+                out.push_str(&pad(depth + 1));
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push_str(": ");
+                write_pretty(value, indent, depth + 1, out);
+                out.push_str(if i + 1 < members.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&pad(depth));
+            out.push('}');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+/// Write `s` as a quoted JSON string, escaping what JSON requires.
+fn write_string(s: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            '\u{08}' => write!(f, "\\b")?,
+            '\u{0C}' => write!(f, "\\f")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<Number> for Value {
+    fn from(n: Number) -> Self {
+        Value::Number(n)
+    }
+}
+
+macro_rules! from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Self {
+                    Value::Number(Number::Integer(n.into()))
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i8, i16, i32, i64, u8, u16, u32);
+
+macro_rules! from_wide_integer {
+    ($($ty:ty),*) => {
+        $(
+            /// Integers beyond `i64::MAX` become floats.
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Self {
+                    match i64::try_from(n) {
+                        Ok(n) => Value::Number(Number::Integer(n)),
+                        Err(_) => Value::Number(Number::Float(n as f64)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_wide_integer!(isize, u64, usize);
+
+impl From<f32> for Value {
+    fn from(n: f32) -> Self {
+        Value::Number(Number::Float(n.into()))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(Number::Float(n))
+    }
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Null
+    }
+}
+
+/// `None` becomes `null`.
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Self {
+        option.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<Value>> From<&[T]> for Value {
+    fn from(items: &[T]) -> Self {
+        Value::Array(items.iter().cloned().map(Into::into).collect())
+    }
+}
+
+/// Members are sorted by key, `HashMap`'s own order is random.
+#[cfg(feature = "std")]
+impl<K: Into<String>, V: Into<Value>, S> From<std::collections::HashMap<K, V, S>> for Value {
+    fn from(map: std::collections::HashMap<K, V, S>) -> Self {
+        let mut members = map
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect::<Vec<(String, Value)>>();
+        members.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Value::Object(members)
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        Value::Object(map.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::json;
+
+    #[test]
+    fn test_pointer() {
+        let value = json!({ "a": [{ "b/c": 1, "d~e": 2 }], "": 3 });
+
+        assert_eq!(value.pointer(""), Some(&value));
+        assert_eq!(value.pointer("/a/0/b~1c"), Some(&json!(1)));
+        assert_eq!(value.pointer("/a/0/d~0e"), Some(&json!(2)));
+        assert_eq!(value.pointer("/"), Some(&json!(3)));
+        assert_eq!(value.pointer("/a/1"), None);
+        assert_eq!(value.pointer("/a/x"), None);
+        assert_eq!(value.pointer("a"), None);
+    }
+}
@@ -0,0 +1,251 @@
+//! Walking a `Value` tree, for lint rules and rewrites.
+//!
+//! `Visitor` and `VisitorMut` have a method per kind of node, each of which walks
+//! into the children by default. Override the ones a rule cares about and call the
+//! matching `walk_*` function to keep descending. Every node comes with a `Context`
+//! telling where it is, and its source span when the value was parsed with spans.
+//!
+//! ```
+//! use json_parser::visit::{Context, Visitor};
+//!
+//! /// Paths of the keys containing uppercase letters.
+//! struct Uppercase(Vec<String>);
+//!
+//! impl Visitor for Uppercase {
+//!     fn visit_key(&mut self, key: &str, cx: &Context) {
+//!         if key.chars().any(char::is_uppercase) {
+//!             self.0.push(cx.path().to_string());
+//!         }
+//!     }
+//! }
+//!
+//! let (value, spans) = json_parser::parse_with_spans(r#"{"a": [{"bC": 1}]}"#)?;
+//! let mut rule = Uppercase(Vec::new());
+//! rule.visit_value(&value, &Context::root().with_spans(&spans));
+//! assert_eq!(rule.0, ["$.a[0].bC"]);
+//! # Ok::<(), json_parser::Error>(())
+//! ```
+
+use crate::{
+    parser::Spans,
+    path::{Path, Segment},
+    token::Span,
+    value::Value,
+};
+use alloc::{string::String, vec::Vec};
+
+/// Where the visited node is.
+#[derive(Debug, Clone, Default)]
+pub struct Context<'a> {
+    path: Path,
+    spans: Option<&'a Spans>,
+}
+
+impl<'a> Context<'a> {
+    /// Context of a root value, without spans.
+    pub fn root() -> Self {
+        Context {
+            path: Path::root(),
+            spans: None,
+        }
+    }
+
+    /// Look spans up in `spans`, as returned by `parse_with_spans`.
+    pub fn with_spans(mut self, spans: &'a Spans) -> Self {
+        self.spans = Some(spans);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Source span of the node, for a key the span of its value.
+    ///
+    /// Spans are those of the parsed text, after `VisitorMut` has moved nodes
+    /// around they may point elsewhere.
+    pub fn span(&self) -> Option<Span> {
+        self.spans?.get(&self.path.pointer()).copied()
+    }
+
+    /// Context of a child of this node.
+    pub fn join(&self, segment: Segment) -> Self {
+        Context {
+            path: self.path.join(segment),
+            spans: self.spans,
+        }
+    }
+}
+
+/// Read-only traversal, see the module documentation.
+pub trait Visitor {
+    fn visit_value(&mut self, value: &Value, cx: &Context) {
+        walk_value(self, value, cx);
+    }
+
+    fn visit_object(&mut self, members: &[(String, Value)], cx: &Context) {
+        walk_object(self, members, cx);
+    }
+
+    fn visit_array(&mut self, items: &[Value], cx: &Context) {
+        walk_array(self, items, cx);
+    }
+
+    /// A key of an object, `cx` is that of its member.
+    fn visit_key(&mut self, _key: &str, _cx: &Context) {}
+
+    /// Null, a boolean, a number or a string.
+    fn visit_scalar(&mut self, _value: &Value, _cx: &Context) {}
+}
+
+pub fn walk_value<V: Visitor + ?Sized>(visitor: &mut V, value: &Value, cx: &Context) {
+    match value {
+        Value::Object(members) => visitor.visit_object(members, cx),
+        Value::Array(items) => visitor.visit_array(items, cx),
+        _ => visitor.visit_scalar(value, cx),
+    }
+}
+
+pub fn walk_object<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    members: &[(String, Value)],
+    cx: &Context,
+) {
+    for (key, value) in members {
+        let cx = cx.join(Segment::Key(key.clone()));
+        visitor.visit_key(key, &cx);
+        visitor.visit_value(value, &cx);
+    }
+}
+
+pub fn walk_array<V: Visitor + ?Sized>(visitor: &mut V, items: &[Value], cx: &Context) {
+    for (i, item) in items.iter().enumerate() {
+        visitor.visit_value(item, &cx.join(Segment::Index(i)));
+    }
+}
+
+/// Traversal that can rewrite the tree in place, see the module documentation.
+///
+/// Children are walked after their parent's method has run, so members added
+/// or removed there are taken into account.
+pub trait VisitorMut {
+    fn visit_value_mut(&mut self, value: &mut Value, cx: &Context) {
+        walk_value_mut(self, value, cx);
+    }
+
+    fn visit_object_mut(&mut self, members: &mut Vec<(String, Value)>, cx: &Context) {
+        walk_object_mut(self, members, cx);
+    }
+
+    fn visit_array_mut(&mut self, items: &mut Vec<Value>, cx: &Context) {
+        walk_array_mut(self, items, cx);
+    }
+
+    /// A key of an object, `cx` is that of its member under the old key.
+    fn visit_key_mut(&mut self, _key: &mut String, _cx: &Context) {}
+
+    /// Null, a boolean, a number or a string.
+    fn visit_scalar_mut(&mut self, _value: &mut Value, _cx: &Context) {}
+}
+
+pub fn walk_value_mut<V: VisitorMut + ?Sized>(visitor: &mut V, value: &mut Value, cx: &Context) {
+    match value {
+        Value::Object(members) => visitor.visit_object_mut(members, cx),
+        Value::Array(items) => visitor.visit_array_mut(items, cx),
+        _ => visitor.visit_scalar_mut(value, cx),
+    }
+}
+
+pub fn walk_object_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    members: &mut [(String, Value)],
+    cx: &Context,
+) {
+    for (key, value) in members {
+        let cx = cx.join(Segment::Key(key.clone()));
+        visitor.visit_key_mut(key, &cx);
+        visitor.visit_value_mut(value, &cx);
+    }
+}
+
+pub fn walk_array_mut<V: VisitorMut + ?Sized>(visitor: &mut V, items: &mut [Value], cx: &Context) {
+    for (i, item) in items.iter_mut().enumerate() {
+        visitor.visit_value_mut(item, &cx.join(Segment::Index(i)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::json;
+
+    /// Every node visited, with its path and the line it starts on.
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl Visitor for Trace {
+        fn visit_value(&mut self, value: &Value, cx: &Context) {
+            let line = cx.span().map(|span| span.start.line);
+            self.0
+                .push(format!("{} {} {line:?}", cx.path(), value.type_name()));
+            walk_value(self, value, cx);
+        }
+
+        fn visit_key(&mut self, key: &str, _cx: &Context) {
+            self.0.push(format!("key {key}"));
+        }
+    }
+
+    #[test]
+    fn test_visitor() {
+        let (value, spans) =
+            crate::parse_with_spans("{\n\"a\": [1,\n{\"b c\": null}],\n\"d\": {}\n}").unwrap();
+        let mut trace = Trace::default();
+        trace.visit_value(&value, &Context::root().with_spans(&spans));
+        assert_eq!(
+            trace.0,
+            [
+                "$ object Some(1)",
+                "key a",
+                "$.a array Some(2)",
+                "$.a[0] number Some(2)",
+                "$.a[1] object Some(3)",
+                "key b c",
+                "$.a[1][\"b c\"] null Some(3)",
+                "key d",
+                "$.d object Some(4)",
+            ]
+        );
+
+        let mut trace = Trace::default();
+        trace.visit_value(&json!([true]), &Context::root());
+        assert_eq!(trace.0, ["$ array None", "$[0] boolean None"]);
+    }
+
+    /// Lowercases keys, drops null members and replaces numbers with their path.
+    struct Rewrite;
+
+    impl VisitorMut for Rewrite {
+        fn visit_object_mut(&mut self, members: &mut Vec<(String, Value)>, cx: &Context) {
+            members.retain(|(_, value)| *value != Value::Null);
+            walk_object_mut(self, members, cx);
+        }
+
+        fn visit_key_mut(&mut self, key: &mut String, _cx: &Context) {
+            *key = key.to_lowercase();
+        }
+
+        fn visit_scalar_mut(&mut self, value: &mut Value, cx: &Context) {
+            if let Value::Number(_) = value {
+                *value = Value::from(cx.path().to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        let mut value = json!({ "A": [1, { "Bc": null, "D": 2 }], "e": null });
+        Rewrite.visit_value_mut(&mut value, &Context::root());
+        assert_eq!(value, json!({ "a": ["$.A[0]", { "d": "$.A[1].D" }] }));
+    }
+}
@@ -0,0 +1,83 @@
+//! Runs the parsing corpus of <https://github.com/nst/JSONTestSuite> through the parser.
+//!
+//! - `y_` files must be accepted.
+//! - `n_` files must be rejected.
+//! - `i_` files are implementation defined, the ones we accept are pinned below so
+//!   any change in behaviour shows up as a failure.
+
+use std::{fs, path::Path};
+
+/// `i_` cases this parser accepts, every other `i_` case is rejected.
+///
+/// Numbers that underflow or don't fit an `i64` become floats, lone surrogates become
+/// U+FFFD, BOMs are skipped and UTF-16 is transcoded. Numbers overflowing an `f64`,
+/// nesting deeper than 128 levels and invalid UTF-8 are rejected.
+const ACCEPTED_IMPLEMENTATION_DEFINED: &[&str] = &[
+    "i_number_double_huge_neg_exp.json",
+    "i_number_real_underflow.json",
+    "i_number_too_big_neg_int.json",
+    "i_number_too_big_pos_int.json",
+    "i_number_very_big_negative_int.json",
+    "i_object_key_lone_2nd_surrogate.json",
+    "i_string_1st_surrogate_but_2nd_missing.json",
+    "i_string_1st_valid_surrogate_2nd_invalid.json",
+    "i_string_UTF-16LE_with_BOM.json",
+    "i_string_incomplete_surrogate_and_escape_valid.json",
+    "i_string_incomplete_surrogate_pair.json",
+    "i_string_incomplete_surrogates_escape_valid.json",
+    "i_string_invalid_lonely_surrogate.json",
+    "i_string_invalid_surrogate.json",
+    "i_string_inverted_surrogates_U+1D11E.json",
+    "i_string_lone_second_surrogate.json",
+    "i_string_utf16BE_no_BOM.json",
+    "i_string_utf16LE_no_BOM.json",
+    "i_structure_UTF-8_BOM_empty_object.json",
+];
+
+fn accepts(contents: &[u8]) -> bool {
+    let Ok(input) = json_parser::encoding::decode(contents) else {
+        return false;
+    };
+
+    // `parse` takes the stage 1 fast path, `parse_with_spans` always goes through the
+    // lexer, both must agree.
+    let fast = json_parser::parse_bytes(contents).ok();
+    let slow = json_parser::parse_with_spans(&input)
+        .ok()
+        .map(|(value, _)| value);
+    assert_eq!(fast, slow, "{input:?}");
+
+    fast.is_some()
+}
+
+#[test]
+fn test_json_test_suite() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/JSONTestSuite/test_parsing");
+
+    let mut entries = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let mut failures = Vec::new();
+    let mut accepted_implementation_defined = Vec::new();
+
+    for path in entries {
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        let accepted = accepts(&fs::read(&path).unwrap());
+
+        match name.split_once('_').map(|(prefix, _)| prefix) {
+            Some("y") if !accepted => failures.push(format!("{name}: rejected")),
+            Some("n") if accepted => failures.push(format!("{name}: accepted")),
+            Some("i") if accepted => accepted_implementation_defined.push(name),
+            _ => {}
+        }
+    }
+
+    assert!(failures.is_empty(), "{failures:#?}");
+    assert_eq!(
+        accepted_implementation_defined,
+        ACCEPTED_IMPLEMENTATION_DEFINED
+    );
+}
@@ -0,0 +1,51 @@
+//! Runs the jq reference cases in `tests/jq.test` through `json_parser::jq`.
+//!
+//! The file uses the format of jq's own test suite: blocks separated by blank lines
+//! holding a filter, its input and every expected output, one per line. `%%FAIL`
+//! blocks hold a filter that must be rejected along with part of the error message.
+
+use json_parser::{jq::Filter, parse};
+
+#[test]
+fn test_jq_reference() {
+    let cases = include_str!("jq.test");
+
+    let mut blocks = vec![Vec::new()];
+    for line in cases.lines().filter(|line| !line.starts_with('#')) {
+        match blocks.last_mut() {
+            Some(block) if !line.is_empty() => block.push(line),
+            _ => blocks.push(Vec::new()),
+        }
+    }
+
+    let mut failures = Vec::new();
+    let mut count = 0;
+    for block in blocks.iter().filter(|block| !block.is_empty()) {
+        count += 1;
+        match block.as_slice() {
+            ["%%FAIL", filter, message] => match Filter::new(filter) {
+                Ok(_) => failures.push(format!("{filter}: accepted")),
+                Err(e) if !e.to_string().contains(message) => {
+                    failures.push(format!("{filter}: {e}"))
+                }
+                Err(_) => {}
+            },
+            [filter, input, expected @ ..] => {
+                let input = parse(input).unwrap();
+                let expected = expected
+                    .iter()
+                    .map(|output| parse(output).unwrap())
+                    .collect::<Vec<_>>();
+                match Filter::new(filter).and_then(|f| f.run(&input)) {
+                    Ok(outputs) if outputs == expected => {}
+                    Ok(outputs) => failures.push(format!("{filter}: {outputs:?}")),
+                    Err(e) => failures.push(format!("{filter}: {e}")),
+                }
+            }
+            block => panic!("malformed block {block:?}"),
+        }
+    }
+
+    assert!(count > 150, "only {count} cases");
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
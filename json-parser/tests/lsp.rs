@@ -0,0 +1,217 @@
+//! Drives `json-parser lsp` over stdio the way an editor would.
+
+use json_parser::{json, parse, Value};
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_json-parser"))
+            .arg("lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Client {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            match line.trim_end().split_once(": ") {
+                Some(("Content-Length", n)) => length = n.parse().unwrap(),
+                _ => break,
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        parse(&String::from_utf8(body).unwrap()).unwrap()
+    }
+
+    /// Send a request and return its whole response.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        let response = self.receive();
+        assert_eq!(response.get("id"), Some(&json!(id)));
+        response
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Replace the document's text and return the diagnostics published for it.
+    fn change(&mut self, uri: &str, text: &str) -> Value {
+        let change = json!({ "text": text });
+        let params =
+            json!({ "textDocument": { "uri": uri, "version": 2 }, "contentChanges": [change] });
+        self.notify("textDocument/didChange", params);
+        let published = self.receive();
+        assert_eq!(
+            published.get("method"),
+            Some(&json!("textDocument/publishDiagnostics"))
+        );
+        published.pointer("/params/diagnostics").unwrap().clone()
+    }
+}
+
+fn at(uri: &str, line: usize, character: usize) -> Value {
+    json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } })
+}
+
+fn labels(items: &Value) -> Vec<String> {
+    let Value::Array(items) = items else {
+        panic!("not a list of completions: {items}");
+    };
+    items
+        .iter()
+        .map(|item| match item.get("label") {
+            Some(Value::String(label)) => label.clone(),
+            label => panic!("bad label {label:?}"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_session() {
+    let dir = std::env::temp_dir().join(format!("json-parser-lsp-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string", "description": "Display name." },
+            "kind": { "enum": ["app", "lib"] },
+            "private": { "type": "boolean" },
+        },
+    });
+    fs::write(dir.join("schema.json"), schema.to_string()).unwrap();
+    let uri = format!("file://{}/config.json", dir.display());
+    let uri = uri.as_str();
+
+    let mut client = Client::start();
+
+    let early = client.request("textDocument/documentSymbol", json!({}));
+    assert_eq!(early.pointer("/error/code"), Some(&json!(-32002)));
+
+    let init = client.request("initialize", json!({ "capabilities": {} }));
+    assert_eq!(
+        init.pointer("/result/capabilities/documentFormattingProvider"),
+        Some(&json!(true))
+    );
+    client.notify("initialized", json!({}));
+
+    // A valid document gets an empty diagnostics list.
+    let text = r#"{"$schema": "./schema.json", "name": "demo"}"#;
+    client.notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": uri, "languageId": "json", "version": 1, "text": text } }),
+    );
+    let published = client.receive();
+    assert_eq!(published.pointer("/params/diagnostics"), Some(&json!([])));
+
+    // Syntax errors are published as the user types.
+    let diagnostics = client.change(uri, "{\n  \"name\": \"demo\",\n}");
+    assert_eq!(diagnostics.pointer("/0/code"), Some(&json!("E0003")));
+    assert_eq!(
+        diagnostics.pointer("/0/range"),
+        Some(&json!({
+            "start": { "line": 1, "character": 16 },
+            "end": { "line": 1, "character": 17 },
+        }))
+    );
+
+    let diagnostics = client.change(uri, text);
+    assert_eq!(diagnostics, json!([]));
+
+    let formatting = client.request(
+        "textDocument/formatting",
+        json!({ "textDocument": { "uri": uri }, "options": { "tabSize": 2, "insertSpaces": true } }),
+    );
+    assert_eq!(
+        formatting.pointer("/result/0/newText"),
+        Some(&json!(
+            "{\n  \"$schema\": \"./schema.json\",\n  \"name\": \"demo\"\n}\n"
+        ))
+    );
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": uri } }),
+    );
+    assert_eq!(symbols.pointer("/result/1/name"), Some(&json!("name")));
+    assert_eq!(
+        symbols.pointer("/result/1/detail"),
+        Some(&json!("\"demo\""))
+    );
+
+    // Hovering the `name` key shows its description from the schema.
+    let hover = client.request("textDocument/hover", at(uri, 0, 31));
+    assert_eq!(
+        hover.pointer("/result/contents/value"),
+        Some(&json!("Display name.\n\nType: `string`"))
+    );
+
+    // Completion works on text that doesn't parse yet, with the schema loaded earlier.
+    client.change(uri, r#"{"$schema": "./schema.json", "name": "demo", ""#);
+    let completion = client.request("textDocument/completion", at(uri, 0, 46));
+    assert_eq!(
+        labels(completion.get("result").unwrap()),
+        ["kind", "private"]
+    );
+    assert_eq!(
+        completion.pointer("/result/0/textEdit/newText"),
+        Some(&json!("\"kind\""))
+    );
+
+    client.change(uri, r#"{"$schema": "./schema.json", "kind": "#);
+    let completion = client.request("textDocument/completion", at(uri, 0, 37));
+    assert_eq!(
+        labels(completion.get("result").unwrap()),
+        ["\"app\"", "\"lib\""]
+    );
+
+    let unknown = client.request("workspace/symbol", json!({ "query": "" }));
+    assert_eq!(unknown.pointer("/error/code"), Some(&json!(-32601)));
+
+    let shutdown = client.request("shutdown", Value::Null);
+    assert_eq!(shutdown.get("result"), Some(&Value::Null));
+    client.notify("exit", Value::Null);
+    assert!(client.child.wait().unwrap().success());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_exit_without_shutdown() {
+    let mut client = Client::start();
+    client.notify("exit", Value::Null);
+    assert!(!client.child.wait().unwrap().success());
+}
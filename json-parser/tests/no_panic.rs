@@ -0,0 +1,47 @@
+//! Malformed input has to come back as an error, never as a panic.
+//!
+//! Cheap stand-in for the fuzz targets under `fuzz/`, run on every `cargo test`.
+
+use std::{fs, path::Path};
+
+fn corpus() -> Vec<String> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/JSONTestSuite/test_parsing");
+    let mut inputs = fs::read_dir(dir)
+        .unwrap()
+        .filter_map(|entry| fs::read_to_string(entry.unwrap().path()).ok())
+        .collect::<Vec<_>>();
+
+    for whitespace in ["", " ", "\n", "\t", "\r\n"] {
+        inputs.push(whitespace.to_string());
+    }
+    inputs.push("[".repeat(100_000));
+    inputs.push("{\"a\":".repeat(100_000));
+    inputs
+}
+
+#[test]
+fn test_truncated_inputs() {
+    for input in corpus() {
+        for (end, _) in input.char_indices().take(256) {
+            let _ = json_parser::lex(&input[..end]);
+            let _ = json_parser::parse_with_spans(&input[..end]);
+            #[cfg(feature = "std")]
+            let _ = json_parser::stats::stats(&input[..end]);
+        }
+    }
+}
+
+#[test]
+fn test_roundtrip() {
+    for input in corpus() {
+        let Ok(value) = json_parser::parse(&input) else {
+            continue;
+        };
+
+        assert_eq!(json_parser::parse(&value.to_string()).unwrap(), value);
+        assert_eq!(
+            json_parser::parse(&value.to_pretty_string(2)).unwrap(),
+            value
+        );
+    }
+}